@@ -0,0 +1,631 @@
+//! A small local HTTP API for reading activity and guild data, and for changing activities from
+//! outside of Discord. Changes go through the same code as the commands, so rosters and the
+//! roster board are kept up to date.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset};
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json as json;
+use serenity::model::channel::Channel;
+use serenity::model::id::{ChannelId, GuildId, UserId};
+use serenity::prelude::Context;
+use tracing::{error, info};
+
+use crate::command::data::{Activity, GuildData, StartTime};
+use crate::command::ical;
+use crate::command::imp::{self, data_keys, ActivityUpdateError};
+use crate::command::service::Actor;
+use crate::util::ActivityToken;
+
+/// Request bodies larger than this are rejected.
+const MAX_BODY_LEN: u64 = 64 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct ApiConfig {
+    pub address: SocketAddr,
+    /// The bearer token required by requests that change data. Those requests are refused if
+    /// no token is set.
+    pub token: Option<String>,
+}
+
+/// Serves the API until the process exits.
+pub async fn serve(ctx: Context, config: ApiConfig) {
+    let address = config.address;
+
+    let make_service = make_service_fn(move |_| {
+        let ctx = ctx.clone();
+        let config = config.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let ctx = ctx.clone();
+                let config = config.clone();
+
+                async move { Ok::<_, Infallible>(handle(&ctx, &config, req).await) }
+            }))
+        }
+    });
+
+    let server = match Server::try_bind(&address) {
+        Ok(builder) => builder.serve(make_service),
+        Err(e) => {
+            error!(%address, error = %e, "Unable to bind the HTTP API");
+            return;
+        }
+    };
+
+    info!(%address, "Serving the HTTP API");
+
+    if let Err(e) = server.await {
+        error!(error = %e, "HTTP API error");
+    }
+}
+
+/// An error response, sent as `{"error": message}`.
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new<S: Into<String>>(status: StatusCode, message: S) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn not_found() -> Self {
+        Self::new(StatusCode::NOT_FOUND, "Not found")
+    }
+
+    fn bad_request<S: Into<String>>(message: S) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+}
+
+impl From<ActivityUpdateError> for ApiError {
+    fn from(e: ActivityUpdateError) -> Self {
+        match e {
+            ActivityUpdateError::InvalidActivity | ActivityUpdateError::InvalidTemplate => {
+                Self::not_found()
+            }
+            ActivityUpdateError::NotAllowed => {
+                Self::new(StatusCode::FORBIDDEN, "Not allowed to change that activity")
+            }
+            ActivityUpdateError::InvalidStart => {
+                Self::bad_request("The activity has already started")
+            }
+            ActivityUpdateError::Activity(e) => Self::new(StatusCode::CONFLICT, e.to_string()),
+            ActivityUpdateError::Conflict(conflicts) => Self::new(
+                StatusCode::CONFLICT,
+                format!(
+                    "Overlaps with activities the user is already in:\n{}",
+                    conflicts
+                ),
+            ),
+            ActivityUpdateError::Serenity(e) => {
+                error!(error = %e, "Discord error while handling an API request");
+                Self::new(StatusCode::BAD_GATEWAY, "Discord request failed")
+            }
+        }
+    }
+}
+
+type ApiResult = Result<Response<Body>, ApiError>;
+
+async fn handle(ctx: &Context, config: &ApiConfig, req: Request<Body>) -> Response<Body> {
+    match route(ctx, config, req).await {
+        Ok(response) => response,
+        Err(e) => json_response(e.status, &ErrorBody { error: e.message }),
+    }
+}
+
+async fn route(ctx: &Context, config: &ApiConfig, req: Request<Body>) -> ApiResult {
+    let segments = req
+        .uri()
+        .path()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+
+    match (req.method(), segments.as_slice()) {
+        (&Method::GET, ["guilds"]) => list_guilds(ctx).await,
+        (&Method::GET, ["guilds", guild]) => get_guild(ctx, parse_id(guild)?).await,
+        (&Method::GET, ["guilds", guild, "activities"]) => {
+            list_activities(ctx, parse_id(guild)?).await
+        }
+        (&Method::GET, ["guilds", guild, "activities.ics"]) => {
+            guild_calendar(ctx, parse_id(guild)?).await
+        }
+        (&Method::GET, ["guilds", guild, "activities", id]) => {
+            get_activity(ctx, parse_id(guild)?, parse_id(id)?, false).await
+        }
+        (&Method::GET, ["guilds", guild, "activities", id, "roster"]) => {
+            get_activity(ctx, parse_id(guild)?, parse_id(id)?, true).await
+        }
+        (&Method::POST, ["guilds", guild, "activities"]) => {
+            let guild_id = GuildId(parse_id(guild)?);
+            authorize(config, &req)?;
+            create_activity(ctx, guild_id, read_json(req).await?).await
+        }
+        (&Method::POST, ["guilds", guild, "activities", id, action]) => {
+            let guild_id = GuildId(parse_id(guild)?);
+            let activity_id = parse_id(id)?;
+            let action = match *action {
+                "join" => MemberAction::Join,
+                "alt" => MemberAction::Alternate,
+                "leave" => MemberAction::Leave,
+                _ => return Err(ApiError::not_found()),
+            };
+
+            authorize(config, &req)?;
+            update_member(ctx, guild_id, activity_id, action, read_json(req).await?).await
+        }
+        (&Method::DELETE, ["guilds", guild, "activities", id]) => {
+            let guild_id = GuildId(parse_id(guild)?);
+            let activity_id = parse_id(id)?;
+            authorize(config, &req)?;
+            delete_activity(ctx, guild_id, activity_id, read_json(req).await?).await
+        }
+        _ => Err(ApiError::not_found()),
+    }
+}
+
+fn parse_id(s: &str) -> Result<u64, ApiError> {
+    s.parse::<u64>()
+        .map_err(|_| ApiError::bad_request(format!("Invalid ID `{}`", s)))
+}
+
+fn authorize(config: &ApiConfig, req: &Request<Body>) -> Result<(), ApiError> {
+    let expected = match config.token.as_deref() {
+        Some(token) if !token.is_empty() => token,
+        _ => {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "Changes are disabled because no API token is configured",
+            ))
+        }
+    };
+
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid API token",
+        )),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Reads a JSON body, stopping as soon as it's longer than `MAX_BODY_LEN`. Chunked bodies don't
+/// say how long they are up front, so the limit is checked as the chunks come in.
+async fn read_json<T: DeserializeOwned>(req: Request<Body>) -> Result<T, ApiError> {
+    let too_large = || ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, "Request body is too large");
+
+    let mut body = req.into_body();
+
+    if body.size_hint().lower() > MAX_BODY_LEN {
+        return Err(too_large());
+    }
+
+    let mut bytes = Vec::new();
+
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|_| ApiError::bad_request("Unable to read the request body"))?;
+
+        if (bytes.len() + chunk.len()) as u64 > MAX_BODY_LEN {
+            return Err(too_large());
+        }
+
+        bytes.extend_from_slice(&chunk);
+    }
+
+    json::from_slice(&bytes).map_err(|e| ApiError::bad_request(format!("Invalid JSON: {}", e)))
+}
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    let body = json::to_vec(body).unwrap_or_default();
+
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap_or_default()
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+// Discord IDs are sent as strings, since they don't fit in a JavaScript number.
+
+#[derive(Serialize)]
+struct GuildView {
+    id: String,
+    activities: usize,
+    prefix: Option<String>,
+    activity_channel: Option<String>,
+    roster_channel: Option<String>,
+    block_conflicts: bool,
+}
+
+impl GuildView {
+    fn new(guild_id: u64, guild_data: &GuildData) -> Self {
+        let settings = guild_data.settings();
+
+        Self {
+            id: guild_id.to_string(),
+            activities: guild_data.activities().len(),
+            prefix: settings.prefix.clone(),
+            activity_channel: settings.activity_channel.map(|id| id.to_string()),
+            roster_channel: settings.roster_channel.map(|id| id.to_string()),
+            block_conflicts: settings.block_conflicts,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct ActivityView {
+    id: u64,
+    name: String,
+    description: String,
+    start: String,
+    end: String,
+    size: u8,
+    creator: String,
+    members: Vec<String>,
+    alternates: Vec<String>,
+    roster_url: String,
+}
+
+impl From<&Activity> for ActivityView {
+    fn from(activity: &Activity) -> Self {
+        Self {
+            id: activity.id,
+            name: activity.name.clone(),
+            description: activity.description.clone(),
+            start: activity.start.date_time.to_rfc3339(),
+            end: activity.end().to_rfc3339(),
+            size: activity.size,
+            creator: activity.creator.to_string(),
+            members: activity.members.iter().map(|id| id.to_string()).collect(),
+            alternates: activity.alternate.iter().map(|id| id.to_string()).collect(),
+            roster_url: activity.embed_msg.link(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RosterView {
+    size: u8,
+    members: Vec<String>,
+    alternates: Vec<String>,
+}
+
+async fn list_guilds(ctx: &Context) -> ApiResult {
+    let type_map = ctx.data.read().await;
+
+    let guilds = type_map
+        .get::<data_keys::GetGuildData>()
+        .map(|guild_data_map| {
+            guild_data_map
+                .iter()
+                .map(|(&id, guild_data)| GuildView::new(id, guild_data))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    Ok(json_response(StatusCode::OK, &guilds))
+}
+
+async fn get_guild(ctx: &Context, guild_id: u64) -> ApiResult {
+    let type_map = ctx.data.read().await;
+
+    let guild_data = type_map
+        .get::<data_keys::GetGuildData>()
+        .and_then(|guild_data_map| guild_data_map.get(&guild_id))
+        .ok_or_else(ApiError::not_found)?;
+
+    Ok(json_response(
+        StatusCode::OK,
+        &GuildView::new(guild_id, guild_data),
+    ))
+}
+
+async fn list_activities(ctx: &Context, guild_id: u64) -> ApiResult {
+    let type_map = ctx.data.read().await;
+
+    let activities = type_map
+        .get::<data_keys::GetGuildData>()
+        .and_then(|guild_data_map| guild_data_map.get(&guild_id))
+        .map(|guild_data| {
+            guild_data
+                .activities_by_start()
+                .into_iter()
+                .map(ActivityView::from)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    Ok(json_response(StatusCode::OK, &activities))
+}
+
+async fn guild_calendar(ctx: &Context, guild_id: u64) -> ApiResult {
+    let type_map = ctx.data.read().await;
+
+    let calendar = match type_map
+        .get::<data_keys::GetGuildData>()
+        .and_then(|guild_data_map| guild_data_map.get(&guild_id))
+    {
+        Some(guild_data) => ical::calendar("Activities", guild_data.activities_by_start()),
+        None => ical::calendar("Activities", None),
+    };
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/calendar; charset=utf-8")
+        .body(Body::from(calendar))
+        .map_err(|_| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Unable to build calendar",
+            )
+        })
+}
+
+async fn get_activity(ctx: &Context, guild_id: u64, activity_id: u64, roster: bool) -> ApiResult {
+    let type_map = ctx.data.read().await;
+
+    let activity = type_map
+        .get::<data_keys::GetGuildData>()
+        .and_then(|guild_data_map| guild_data_map.get(&guild_id))
+        .and_then(|guild_data| guild_data.activity(activity_id))
+        .ok_or_else(ApiError::not_found)?;
+
+    if roster {
+        let view = RosterView {
+            size: activity.size,
+            members: activity.members.iter().map(|id| id.to_string()).collect(),
+            alternates: activity.alternate.iter().map(|id| id.to_string()).collect(),
+        };
+
+        Ok(json_response(StatusCode::OK, &view))
+    } else {
+        Ok(json_response(StatusCode::OK, &ActivityView::from(activity)))
+    }
+}
+
+#[derive(Deserialize)]
+struct CreateActivity {
+    name: String,
+    size: u8,
+    #[serde(default)]
+    description: String,
+    /// An RFC 3339 date and time, such as `2021-09-14T20:00:00-05:00`.
+    start: String,
+    duration_minutes: Option<u64>,
+    creator: String,
+    /// The channel to post the roster in, if the guild doesn't have a roster or activity
+    /// channel.
+    channel_id: Option<String>,
+}
+
+async fn create_activity(ctx: &Context, guild_id: GuildId, body: CreateActivity) -> ApiResult {
+    let name = body.name.trim();
+
+    if name.is_empty() || name.len() > 256 {
+        return Err(ApiError::bad_request(
+            "`name` must be between 1 and 256 characters",
+        ));
+    }
+
+    if body.size == 0 {
+        return Err(ApiError::bad_request("`size` must be at least 1"));
+    }
+
+    if body.description.len() > 1024 {
+        return Err(ApiError::bad_request(
+            "`description` must be at most 1024 characters",
+        ));
+    }
+
+    let date_time = DateTime::<FixedOffset>::parse_from_rfc3339(&body.start)
+        .map_err(|_| ApiError::bad_request("`start` must be an RFC 3339 date and time"))?;
+
+    let start_time = StartTime {
+        date_time,
+        input: body.start.clone(),
+    };
+
+    if start_time.until().is_none() {
+        return Err(ApiError::bad_request("`start` must be in the future"));
+    }
+
+    let creator = UserId(parse_id(&body.creator)?);
+
+    let settings = {
+        let type_map = ctx.data.read().await;
+
+        type_map
+            .get::<data_keys::GetGuildData>()
+            .and_then(|guild_data_map| guild_data_map.get(&guild_id.0))
+            .map(|guild_data| guild_data.settings().clone())
+            .unwrap_or_default()
+    };
+
+    let channel_id =
+        match (settings.roster_channel, settings.activity_channel) {
+            (Some(channel), _) | (None, Some(channel)) => channel,
+            (None, None) => match body.channel_id.as_deref() {
+                Some(id) => ChannelId(parse_id(id)?),
+                None => return Err(ApiError::bad_request(
+                    "`channel_id` is required, since the guild has no roster or activity channel",
+                )),
+            },
+        };
+
+    // The token allows changes in every guild, so a request for one guild must not be able to
+    // post into another guild's channels.
+    let channel_guild = match channel_id.to_channel(ctx).await {
+        Ok(Channel::Guild(channel)) => Some(channel.guild_id),
+        _ => None,
+    };
+
+    if channel_guild != Some(guild_id) {
+        return Err(ApiError::bad_request(
+            "`channel_id` must be a text channel in this guild",
+        ));
+    }
+
+    let embed_msg = imp::post_placeholder_roster(ctx, channel_id, name)
+        .await
+        .map_err(ActivityUpdateError::from)?;
+
+    let mut activity = Activity::new(
+        name,
+        &body.description,
+        start_time,
+        0,
+        body.size,
+        creator,
+        embed_msg,
+        ActivityToken::new(),
+    );
+
+    activity.duration = body
+        .duration_minutes
+        .map(|minutes| Duration::from_secs(minutes * 60));
+
+    let activity_id =
+        imp::register_activity(ctx, guild_id, Actor::Member(creator), activity).await?;
+
+    get_activity(ctx, guild_id.0, activity_id, false)
+        .await
+        .map(|mut response| {
+            *response.status_mut() = StatusCode::CREATED;
+            response
+        })
+}
+
+enum MemberAction {
+    Join,
+    Alternate,
+    Leave,
+}
+
+#[derive(Deserialize)]
+struct MemberBody {
+    user_id: String,
+    /// The role to join in. Only used when joining the main fireteam.
+    #[serde(default)]
+    role: Option<String>,
+}
+
+async fn update_member(
+    ctx: &Context,
+    guild_id: GuildId,
+    activity_id: u64,
+    action: MemberAction,
+    body: MemberBody,
+) -> ApiResult {
+    let user_id = UserId(parse_id(&body.user_id)?);
+    // The API acts on behalf of the user, so the same rules apply as when they use a command.
+    let actor = Actor::Member(user_id);
+
+    match action {
+        MemberAction::Join => {
+            let role = body
+                .role
+                .as_deref()
+                .map(str::trim)
+                .filter(|role| !role.is_empty());
+
+            imp::join_activity(ctx, guild_id, actor, activity_id, user_id, false, role).await?;
+        }
+        MemberAction::Alternate => {
+            imp::join_activity(ctx, guild_id, actor, activity_id, user_id, true, None).await?;
+        }
+        MemberAction::Leave => {
+            imp::leave_activity(ctx, guild_id, actor, activity_id, user_id).await?;
+        }
+    }
+
+    get_activity(ctx, guild_id.0, activity_id, false).await
+}
+
+async fn delete_activity(
+    ctx: &Context,
+    guild_id: GuildId,
+    activity_id: u64,
+    body: MemberBody,
+) -> ApiResult {
+    let user_id = UserId(parse_id(&body.user_id)?);
+    // Like `activity delete`, only the creator can delete an activity unless the user is an admin.
+    let actor = if imp::is_admin(ctx, user_id).await {
+        Actor::Admin(user_id)
+    } else {
+        Actor::Member(user_id)
+    };
+
+    let activity = imp::delete_activity(ctx, guild_id, actor, activity_id).await?;
+
+    Ok(json_response(
+        StatusCode::OK,
+        &ActivityView::from(&activity),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Debug)]
+    struct Named {
+        name: String,
+    }
+
+    fn chunked_request(chunks: Vec<Vec<u8>>) -> Request<Body> {
+        let (mut sender, body) = Body::channel();
+
+        tokio::spawn(async move {
+            for chunk in chunks {
+                if sender.send_data(chunk.into()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Request::new(body)
+    }
+
+    #[tokio::test]
+    async fn chunked_bodies_are_read_up_to_the_limit() {
+        let req = chunked_request(vec![b"{\"name\":".to_vec(), b"\"Vault\"}".to_vec()]);
+        let named = read_json::<Named>(req).await.unwrap();
+        assert_eq!(named.name, "Vault");
+
+        let chunk = vec![b' '; 1024];
+        let req = chunked_request(vec![chunk; MAX_BODY_LEN as usize / 1024 + 1]);
+        let e = read_json::<Named>(req).await.unwrap_err();
+        assert_eq!(e.status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -6,120 +6,93 @@ use serenity::{
         gateway::Ready,
         prelude::{EmojiId, Message, UserId},
     },
-    prelude::{Context, EventHandler, TypeMap},
+    prelude::{Context, EventHandler},
     Client,
 };
 use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
-use tokio::fs::OpenOptions;
-
-use crate::command::{data::EmbedWithMeta, imp::data_keys};
-
-use crate::config::BotConfig;
-
-pub async fn initialize_emoji_map(paths: &JsonPaths, type_map: &mut TypeMap) {
-    let open = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(&paths.assets)
-        .await;
-
-    match open {
-        Ok(file) => {
-            let result = json::from_reader::<_, JsonMap<String, JsonValue>>(file.into_std().await);
-
-            match result {
-                Ok(mut json_map) => {
-                    if let Some(JsonValue::Object(emojis)) = json_map.remove("emojis") {
-                        let mut emoji_map = HashMap::with_capacity(emojis.len());
-
-                        for (name, val) in emojis.into_iter() {
-                            if let Some(id) = val.as_u64() {
-                                emoji_map.insert(name, EmojiId::from(id));
-                            } else {
-                                eprintln!("[ERR] JSON field {} is not a u64.", name);
-                            }
-                        }
-
-                        type_map.insert::<data_keys::GetEmojiMap>(emoji_map);
-                    }
-                }
-                Err(e) => {
-                    eprintln!(
-                        "[ERR] JSON deserialization error. Expected Object, got error: {}",
-                        e
-                    );
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!(
-                "[ERR] Unable to read assets file {}. Error: {:?}",
-                &paths.assets.to_string_lossy(),
-                e
-            );
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context as _};
+use tracing::{debug, info, warn};
+
+use crate::api::{self, ApiConfig};
+use crate::command::events::{EventBus, WebhookSubscriber};
+use crate::command::report::ReportConfig;
+use crate::command::{data::EmbedWithMeta, hooks, imp::data_keys};
+use crate::metrics;
+use crate::webhook::{WebhookConfig, Webhooks};
+
+use crate::config::{BotConfig, ConfigSource};
+use crate::reload::{self, RuntimeConfig};
+
+/// Reads the emoji IDs from `assets.json`.
+pub fn load_emoji_map(path: &Path) -> anyhow::Result<HashMap<String, EmojiId>> {
+    let file = File::open(path)
+        .with_context(|| format!("Unable to read the assets file {}", path.display()))?;
+
+    let mut json_map = json::from_reader::<_, JsonMap<String, JsonValue>>(BufReader::new(file))
+        .context("Assets file is not a JSON object")?;
+
+    let emojis = match json_map.remove("emojis") {
+        Some(JsonValue::Object(emojis)) => emojis,
+        _ => return Err(anyhow!("Assets file has no `emojis` object")),
+    };
+
+    let mut emoji_map = HashMap::with_capacity(emojis.len());
+
+    for (name, val) in emojis.into_iter() {
+        if let Some(id) = val.as_u64() {
+            emoji_map.insert(name, EmojiId::from(id));
+        } else {
+            warn!(field = %name, "Emoji ID in the assets file is not a u64");
         }
     }
-}
 
-pub async fn initialize_embed_map(paths: &JsonPaths, type_map: &mut TypeMap) {
-    let open = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(&paths.embeds)
-        .await;
-
-    match open {
-        Ok(file) => {
-            let result =
-                json::from_reader::<_, HashMap<String, EmbedWithMeta>>(file.into_std().await);
-
-            match result {
-                Ok(map) => type_map.insert::<data_keys::GetEmbedMap>(map),
-                Err(e) => {
-                    eprintln!(
-                        "[ERR] JSON deserialization error. Expected Map, got error: {}",
-                        e
-                    );
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!(
-                "[ERR] Unable to read embeds file {}. Error: {:?}",
-                &paths.embeds.to_string_lossy(),
-                e
-            );
-        }
-    }
+    Ok(emoji_map)
 }
 
-async fn push_paths(paths: JsonPaths, type_map: &mut TypeMap) {
-    type_map.insert::<data_keys::GetJsonPaths>(paths);
+/// Reads the embeds from `embeds.json`.
+pub fn load_embed_map(path: &Path) -> anyhow::Result<HashMap<String, EmbedWithMeta>> {
+    let file = File::open(path)
+        .with_context(|| format!("Unable to read the embeds file {}", path.display()))?;
+
+    json::from_reader(BufReader::new(file)).context("Embeds file is not a map of embeds")
 }
 
-#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+#[derive(Clone, Debug, Default)]
 struct Handler {
     changelog_file_path: PathBuf,
     assets_file_path: PathBuf,
     embeds_file_path: PathBuf,
+    api: Option<ApiConfig>,
+    metrics_address: Option<SocketAddr>,
+    // `ready` is called again whenever the bot reconnects, but the API, metrics and the config
+    // watcher should only be started once.
+    tasks_started: Arc<AtomicBool>,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
 pub struct JsonPaths {
-    changelog: PathBuf,
-    assets: PathBuf,
-    embeds: PathBuf,
+    pub changelog: PathBuf,
+    pub assets: PathBuf,
+    pub embeds: PathBuf,
 }
 
 #[serenity::async_trait]
 impl EventHandler for Handler {
     async fn ready(&self, ctx: Context, ready: Ready) {
-        println!("Bingus is ready and connected to Discord.");
-        println!("Initializing bot data.");
-        eprintln!("Ready info: {:?}", ready);
+        info!(
+            user = %ready.user.tag(),
+            guilds = ready.guilds.len(),
+            shard = ?ready.shard,
+            "Connected to Discord"
+        );
+        debug!("Initializing bot data");
 
         let paths = JsonPaths {
             changelog: self.changelog_file_path.clone(),
@@ -127,19 +100,37 @@ impl EventHandler for Handler {
             embeds: self.embeds_file_path.clone(),
         };
 
-        let mut type_map = ctx.data.write().await;
+        ctx.data
+            .write()
+            .await
+            .insert::<data_keys::GetJsonPaths>(paths);
+
+        reload::log(&reload::reload(&ctx, reload::Source::JSON).await);
+
+        if !self.tasks_started.swap(true, Ordering::AcqRel) {
+            tokio::spawn(reload::watch(ctx.clone()));
 
-        initialize_emoji_map(&paths, &mut type_map).await;
-        initialize_embed_map(&paths, &mut type_map).await;
-        push_paths(paths, &mut type_map).await;
+            if let Some(config) = self.api.clone() {
+                tokio::spawn(api::serve(ctx.clone(), config));
+            }
+
+            if let Some(address) = self.metrics_address {
+                tokio::spawn(metrics::serve(ctx, address));
+            }
+        }
     }
 }
 
 #[derive(Default)]
 pub struct BotClient {
     token: String,
+    runtime_config: RuntimeConfig,
+    config_source: Option<ConfigSource>,
+    event_bus: EventBus,
     event_handler: Handler,
-    framework: StandardFramework,
+    framework: hooks::TracedFramework,
+    command_groups: Vec<&'static CommandGroup>,
+    report_config: ReportConfig,
 }
 
 impl BotClient {
@@ -149,10 +140,22 @@ impl BotClient {
 
     pub async fn start(self) -> serenity::Result<()> {
         let mut client = Client::builder(self.token)
+            .type_map_insert::<data_keys::GetRuntimeConfig>(self.runtime_config)
+            .type_map_insert::<data_keys::GetEventBus>(self.event_bus)
+            .type_map_insert::<data_keys::GetCommandGroups>(self.command_groups)
+            .type_map_insert::<data_keys::GetReportConfig>(self.report_config)
             .event_handler(self.event_handler)
             .framework(self.framework)
             .await?;
 
+        if let Some(source) = self.config_source {
+            client
+                .data
+                .write()
+                .await
+                .insert::<data_keys::GetConfigSource>(source);
+        }
+
         client.start().await
     }
 }
@@ -161,12 +164,17 @@ impl BotClient {
 pub struct BotBuilder {
     token: String,
     owner_ids: Option<HashSet<UserId>>,
+    forward_errors_to_owners: Option<bool>,
+    config_source: Option<ConfigSource>,
     allow_dm: Option<bool>,
     ignore_bots: Option<bool>,
     prefix: Option<String>,
     assets_file_path: Option<PathBuf>,
     embeds_file_path: Option<PathBuf>,
     changelog_file_path: Option<PathBuf>,
+    api: Option<ApiConfig>,
+    metrics_address: Option<SocketAddr>,
+    webhook: Option<WebhookConfig>,
     message_handler: Option<for<'fut> fn(&'fut Context, &'fut Message) -> BoxFuture<'fut, ()>>,
     command_groups: Option<Vec<&'static CommandGroup>>,
 }
@@ -176,12 +184,17 @@ impl BotBuilder {
         Self {
             token: token.into(),
             owner_ids: None,
+            forward_errors_to_owners: None,
+            config_source: None,
             allow_dm: None,
             ignore_bots: None,
             prefix: None,
             assets_file_path: None,
             embeds_file_path: None,
             changelog_file_path: None,
+            api: None,
+            metrics_address: None,
+            webhook: None,
             message_handler: None,
             command_groups: None,
         }
@@ -189,6 +202,7 @@ impl BotBuilder {
 
     pub fn from_config(config: BotConfig) -> Self {
         let builder = Self::new(config.token)
+            .forward_errors_to_owners(config.forward_errors_to_owners)
             .allow_dm(config.allow_dm)
             .ignore_bots(!config.allow_bots)
             .prefix(config.prefix)
@@ -196,6 +210,27 @@ impl BotBuilder {
             .embeds_file(&config.embeds_file)
             .changelog_file(&config.changelog_file);
 
+        let builder = match config.api_address {
+            Some(address) => builder.api(address, config.api_token),
+            None => builder,
+        };
+
+        let builder = match config.metrics_address {
+            Some(address) => builder.metrics(address),
+            None => builder,
+        };
+
+        let builder = match config.webhook_url {
+            Some(url) => builder.webhook(
+                url,
+                config.webhook_secret,
+                config
+                    .webhook_dead_letter_file
+                    .unwrap_or_else(|| PathBuf::from("./config/webhook_dead_letters.jsonl")),
+            ),
+            None => builder,
+        };
+
         match config.owner_ids {
             Some(ids) => builder.owners(ids),
             None => builder,
@@ -236,6 +271,17 @@ impl BotBuilder {
         self
     }
 
+    pub fn forward_errors_to_owners(mut self, forward: bool) -> Self {
+        self.forward_errors_to_owners = Some(forward);
+        self
+    }
+
+    /// Sets where the config was loaded from, so that it can be reloaded while the bot is running.
+    pub fn config_source(mut self, source: ConfigSource) -> Self {
+        self.config_source = Some(source);
+        self
+    }
+
     pub fn allow_dm(mut self, allow_dm: bool) -> Self {
         self.allow_dm = Some(allow_dm);
         self
@@ -269,6 +315,30 @@ impl BotBuilder {
         self
     }
 
+    pub fn api(mut self, address: SocketAddr, token: Option<String>) -> Self {
+        self.api = Some(ApiConfig { address, token });
+        self
+    }
+
+    pub fn metrics(mut self, address: SocketAddr) -> Self {
+        self.metrics_address = Some(address);
+        self
+    }
+
+    pub fn webhook<P: AsRef<Path>>(
+        mut self,
+        url: String,
+        secret: Option<String>,
+        dead_letter_file: P,
+    ) -> Self {
+        self.webhook = Some(WebhookConfig {
+            url,
+            secret,
+            dead_letter_file: PathBuf::from(dead_letter_file.as_ref()),
+        });
+        self
+    }
+
     pub fn message_handler(
         mut self,
         f: for<'fut> fn(&'fut Context, &'fut Message) -> BoxFuture<'fut, ()>,
@@ -308,39 +378,43 @@ impl BotBuilder {
     }
 
     pub fn build(self) -> BotClient {
-        let allow_dm = match self.allow_dm {
-            Some(val) => val,
-            None => false,
-        };
-
-        let ignore_bots = match self.ignore_bots {
-            Some(val) => val,
-            None => true,
-        };
+        let allow_dm = self.allow_dm.unwrap_or_default();
+        let ignore_bots = self.ignore_bots.unwrap_or(true);
 
         let prefix = match self.prefix {
             Some(prefix) => prefix,
             None => String::from("!"),
         };
 
-        let command_groups = match self.command_groups {
-            Some(cg) => cg,
-            None => Vec::new(),
-        };
+        let command_groups = self.command_groups.unwrap_or_default();
+        let owner_ids = self.owner_ids.unwrap_or_default();
 
-        let owner_ids = match self.owner_ids {
-            Some(ids) => ids,
-            None => HashSet::new(),
+        let runtime_config = RuntimeConfig {
+            prefix,
+            allow_dm,
+            owners: owner_ids,
         };
 
-        let mut framework = StandardFramework::new().configure(|c| {
-            c.allow_dm(allow_dm)
-                .ignore_bots(ignore_bots)
-                .prefix(&prefix)
-                .owners(owner_ids)
-        });
+        let report_config = ReportConfig {
+            forward_to_owners: self.forward_errors_to_owners.unwrap_or_default(),
+        };
 
-        for group in command_groups {
+        // The static prefix is disabled so that guilds with their own prefix don't also respond
+        // to the default one. The default prefix is resolved by `hooks::guild_prefix` instead.
+        // DMs and owners can change when the config is reloaded, so they're checked by
+        // `hooks::before_command` and the `Owner` check instead of the framework.
+        let mut framework = StandardFramework::new()
+            .configure(|c| {
+                c.allow_dm(true)
+                    .ignore_bots(ignore_bots)
+                    .prefix("")
+                    .dynamic_prefix(hooks::guild_prefix)
+            })
+            .before(hooks::before_command)
+            .after(hooks::after_command)
+            .on_dispatch_error(hooks::dispatch_error);
+
+        for &group in &command_groups {
             framework.group_add(group);
         }
 
@@ -355,12 +429,27 @@ impl BotBuilder {
             assets_file_path: self.assets_file_path.unwrap_or_default(),
             embeds_file_path: self.embeds_file_path.unwrap_or_default(),
             changelog_file_path: self.changelog_file_path.unwrap_or_default(),
+            api: self.api,
+            metrics_address: self.metrics_address,
+            tasks_started: Arc::new(AtomicBool::new(false)),
+        };
+
+        let event_bus = match self.webhook {
+            Some(config) => {
+                EventBus::with_defaults().subscribe(WebhookSubscriber(Webhooks::new(config)))
+            }
+            None => EventBus::with_defaults(),
         };
 
         BotClient {
             token: self.token,
+            runtime_config,
+            config_source: self.config_source,
+            event_bus,
             event_handler,
-            framework: framework_with_handler,
+            framework: hooks::TracedFramework(framework_with_handler),
+            command_groups,
+            report_config,
         }
     }
 }
@@ -1,12 +1,12 @@
-use crate::command::data::{ActivityError, GuildData};
-use crate::command::imp::{self, data_keys};
-use crate::util::CancelActivity;
-use chrono::{FixedOffset, TimeZone, Utc};
+use crate::command::data::{mention_string, Attendance, GuildData, GuildSettings};
+use crate::command::imp::{self, data_keys, ActivityUpdateError};
+use crate::command::service::Actor;
 use futures::StreamExt;
+use serenity::builder::CreateEmbed;
 use serenity::model::channel::ChannelType;
 use serenity::model::id::ChannelId;
 use serenity::model::misc::Mention;
-use serenity::model::prelude::{User, UserId};
+use serenity::model::prelude::UserId;
 use serenity::model::Permissions;
 use serenity::{
     framework::standard::{
@@ -17,11 +17,11 @@ use serenity::{
     prelude::Context,
 };
 use std::collections::HashMap;
-use std::time::Duration;
+use tracing::warn;
 
 #[group]
 #[prefix("admin")]
-#[commands(activity, echo, pin, buildcache, nick)]
+#[commands(activity, config, echo, pin, buildcache, nick)]
 struct AdminsOnly;
 
 #[command]
@@ -46,11 +46,43 @@ async fn activity(ctx: &Context, original_msg: &Message, args: Args) -> CommandR
             "delete" => admin_activity_delete(ctx, original_msg, args).await?,
             "start" => admin_activity_start(ctx, original_msg, args).await?,
             "ping" => admin_activity_ping(ctx, original_msg, args).await?,
+            "attendance" => admin_activity_attendance(ctx, original_msg, args).await?,
             _ => {
                 imp::send_error_message(
                     ctx,
                     original_msg,
-                    "Invalid subcommand. Valid subcommands are `add`, `alt`, `remove`, `delete`, `start`, and `ping`.",
+                    "Invalid subcommand. Valid subcommands are `add`, `alt`, `remove`, `edit`, `delete`, `start`, `ping`, and `attendance`.",
+                )
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[command]
+async fn config(ctx: &Context, original_msg: &Message, args: Args) -> CommandResult {
+    if imp::is_admin(ctx, original_msg.author.id).await {
+        let subcommand = match args.current() {
+            Some(arg) => arg,
+            None => {
+                original_msg
+                    .channel_id
+                    .say(&ctx, "Please provide a subcommand.")
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        match subcommand {
+            "get" => admin_config_get(ctx, original_msg, args).await?,
+            "set" => admin_config_set(ctx, original_msg, args).await?,
+            _ => {
+                imp::send_error_message(
+                    ctx,
+                    original_msg,
+                    "Invalid subcommand. Valid subcommands are `get` and `set`.",
                 )
                 .await?;
             }
@@ -125,11 +157,7 @@ async fn buildcache(ctx: &Context, original_msg: &Message) -> CommandResult {
 #[command]
 async fn echo(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
     if imp::is_admin(ctx, original_msg.author.id).await {
-        let channel_id = match args
-            .current()
-            .map(|string| string.parse::<u64>().ok())
-            .flatten()
-        {
+        let channel_id = match args.current().and_then(|string| string.parse::<u64>().ok()) {
             Some(id) => id,
             None => {
                 imp::send_error_message(ctx, original_msg, "Please provide a valid channel ID.")
@@ -153,10 +181,7 @@ async fn echo(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandR
 #[command]
 async fn pin(ctx: &Context, original_msg: &Message, args: Args) -> CommandResult {
     if imp::is_admin(ctx, original_msg.author.id).await {
-        let id_opt = args
-            .current()
-            .map(|string| string.parse::<u64>().ok())
-            .flatten();
+        let id_opt = args.current().and_then(|string| string.parse::<u64>().ok());
 
         let id = match id_opt {
             Some(id) => id,
@@ -173,6 +198,130 @@ async fn pin(ctx: &Context, original_msg: &Message, args: Args) -> CommandResult
     Ok(())
 }
 
+async fn admin_config_get(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match original_msg.guild_id {
+        Some(id) => id,
+        None => {
+            imp::send_error_message(ctx, original_msg, "This command is not supported in DMs.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let key = args.advance().current().map(str::to_ascii_lowercase);
+
+    let settings = {
+        let type_map = ctx.data.read().await;
+
+        type_map
+            .get::<data_keys::GetGuildData>()
+            .and_then(|guild_map| guild_map.get(&guild_id.0))
+            .map(|guild_data| guild_data.settings().clone())
+            .unwrap_or_default()
+    };
+
+    match key {
+        Some(key) => match settings.get(&key) {
+            Ok(value) => {
+                original_msg
+                    .channel_id
+                    .say(ctx, format!("`{}`: {}", key, value))
+                    .await?;
+            }
+            Err(e) => imp::send_error_message(ctx, original_msg, e).await?,
+        },
+        None => {
+            let mut settings_embed = CreateEmbed::default();
+
+            for &key in GuildSettings::KEYS {
+                // Every key in `KEYS` is valid, so this never hits the error case.
+                if let Ok(value) = settings.get(key) {
+                    settings_embed.field(key, value, false);
+                }
+            }
+
+            settings_embed.color(0x212121).title("Server Settings");
+
+            original_msg
+                .channel_id
+                .send_message(ctx, |msg| {
+                    msg.embed(|embed| {
+                        *embed = settings_embed;
+                        embed
+                    })
+                })
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn admin_config_set(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match original_msg.guild_id {
+        Some(id) => id,
+        None => {
+            imp::send_error_message(ctx, original_msg, "This command is not supported in DMs.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let key = match args.advance().current() {
+        Some(key) => key.to_ascii_lowercase(),
+        None => {
+            imp::send_error_message(ctx, original_msg, "Please provide a setting to change.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let value = args.advance().rest().to_string();
+
+    if value.is_empty() {
+        imp::send_error_message(
+            ctx,
+            original_msg,
+            "Please provide a value for that setting, or `none` to reset it.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let mut type_map = ctx.data.write().await;
+
+    let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
+    let guild_data = guild_data_map
+        .entry(guild_id.0)
+        .or_insert_with(|| GuildData::new(guild_id));
+
+    match guild_data.settings_mut().set(&key, &value) {
+        Ok(()) => {
+            let new_value = guild_data.settings().get(&key)?;
+            drop(type_map);
+
+            // The setting is already saved, so a board that can't be posted is only logged. The
+            // board is brought up to date again with the next activity change.
+            if key == "roster_channel" {
+                if let Err(e) = imp::update_roster_board(ctx, guild_id).await {
+                    warn!(guild_id = %guild_id, error = %e, "Failed to update the roster board");
+                }
+            }
+
+            original_msg
+                .channel_id
+                .say(ctx, format!("Updated `{}` to {}.", key, new_value))
+                .await?;
+        }
+        Err(e) => {
+            drop(type_map);
+            imp::send_error_message(ctx, original_msg, e).await?;
+        }
+    }
+
+    Ok(())
+}
+
 async fn admin_activity_add(
     ctx: &Context,
     original_msg: &Message,
@@ -181,8 +330,7 @@ async fn admin_activity_add(
     let user_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let user_id = match user_opt {
         Some(id) => UserId::from(id),
@@ -195,8 +343,7 @@ async fn admin_activity_add(
     let activity_id_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let activity_id = match activity_id_opt {
         Some(id) => id,
@@ -215,29 +362,36 @@ async fn admin_activity_add(
         }
     };
 
-    let mut type_map = ctx.data.write().await;
+    args.advance();
+    let role = Some(args.rest().trim()).filter(|role| !role.is_empty());
 
-    let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-    let guild_data = guild_data_map
-        .entry(guild_id.0)
-        .or_insert_with(|| GuildData::new(guild_id));
+    // Admins can add users to overlapping activities even when they're blocked, but should
+    // still know about the overlap.
+    let actor = Actor::Admin(original_msg.author.id);
+    let result = imp::join_activity(ctx, guild_id, actor, activity_id, user_id, false, role).await;
 
-    let activity = match guild_data.activity_mut(activity_id) {
-        Some(activity) => activity,
-        None => {
-            imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
-            return Ok(());
-        }
-    };
+    let error = match result {
+        Ok(conflicts) => {
+            if !conflicts.is_empty() {
+                original_msg
+                    .channel_id
+                    .say(
+                        ctx,
+                        format!(
+                            "Note: that activity overlaps with activities {} is already in:\n{}",
+                            Mention::from(user_id),
+                            conflicts
+                        ),
+                    )
+                    .await?;
+            }
 
-    let error = match activity.add_member(user_id) {
-        Err(ActivityError::MemberAlreadyInList) => {
-            Some("That user is already in that fireteam.")
+            None
         }
-        Err(ActivityError::MemberListFull) => Some("The fireteam for that activity is already full."),
-        Err(ActivityError::MemberNotInAlternate) => Some("Attempted to move that user to the fireteam, but they were not an alternate. Please try again."),
-        Err(_) => Some("Some other error occurred adding that user to the fireteam."),
-        Ok(()) => None,
+        Err(ActivityUpdateError::InvalidActivity) => Some("Invalid activity ID.".into()),
+        Err(ActivityUpdateError::Activity(e)) => Some(e.to_string()),
+        Err(ActivityUpdateError::Serenity(e)) => return Err(e.into()),
+        Err(_) => Some("Some other error occurred adding that user to the fireteam.".into()),
     };
 
     if let Some(msg) = error {
@@ -245,18 +399,6 @@ async fn admin_activity_add(
         return Ok(());
     }
 
-    let activity_embed = activity.as_create_embed(0x212121);
-
-    activity
-        .embed_msg
-        .edit(ctx, |msg| {
-            msg.embed(|embed| {
-                *embed = activity_embed;
-                embed
-            })
-        })
-        .await?;
-
     original_msg.delete(ctx).await?;
     Ok(())
 }
@@ -269,8 +411,7 @@ async fn admin_activity_alt(
     let user_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let user_id = match user_opt {
         Some(id) => UserId::from(id),
@@ -283,8 +424,7 @@ async fn admin_activity_alt(
     let activity_id_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let activity_id = match activity_id_opt {
         Some(id) => id,
@@ -303,30 +443,15 @@ async fn admin_activity_alt(
         }
     };
 
-    let mut type_map = ctx.data.write().await;
+    let actor = Actor::Admin(original_msg.author.id);
+    let result = imp::join_activity(ctx, guild_id, actor, activity_id, user_id, true, None).await;
 
-    let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-    let guild_data = guild_data_map
-        .entry(guild_id.0)
-        .or_insert_with(|| GuildData::new(guild_id));
-
-    let activity = match guild_data.activity_mut(activity_id) {
-        Some(activity) => activity,
-        None => {
-            imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
-            return Ok(());
-        }
-    };
-
-    let error = match activity.add_member_alt(user_id) {
-        Err(ActivityError::MemberAlreadyInAlternate) => {
-            Some("That user are already in that alternate fireteam.")
-        }
-        Err(ActivityError::AlternateFull) => {
-            Some("The alternate fireteam for that activity is already full.")
-        }
-        Err(_) => Some("Some other error occurred adding that user to the fireteam."),
-        Ok(()) => None,
+    let error = match result {
+        Ok(_) => None,
+        Err(ActivityUpdateError::InvalidActivity) => Some("Invalid activity ID.".into()),
+        Err(ActivityUpdateError::Activity(e)) => Some(e.to_string()),
+        Err(ActivityUpdateError::Serenity(e)) => return Err(e.into()),
+        Err(_) => Some("Some other error occurred adding that user to the fireteam.".into()),
     };
 
     if let Some(msg) = error {
@@ -334,18 +459,6 @@ async fn admin_activity_alt(
         return Ok(());
     }
 
-    let activity_embed = activity.as_create_embed(0x212121);
-
-    activity
-        .embed_msg
-        .edit(ctx, |msg| {
-            msg.embed(|embed| {
-                *embed = activity_embed;
-                embed
-            })
-        })
-        .await?;
-
     original_msg.delete(ctx).await?;
 
     Ok(())
@@ -359,8 +472,7 @@ async fn admin_activity_remove(
     let user_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let user_id = match user_opt {
         Some(id) => UserId::from(id),
@@ -373,8 +485,7 @@ async fn admin_activity_remove(
     let activity_id_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let activity_id = match activity_id_opt {
         Some(id) => id,
@@ -393,30 +504,15 @@ async fn admin_activity_remove(
         }
     };
 
-    let mut type_map = ctx.data.write().await;
+    let actor = Actor::Admin(original_msg.author.id);
+    let result = imp::leave_activity(ctx, guild_id, actor, activity_id, user_id).await;
 
-    let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-    let guild_data = guild_data_map
-        .entry(guild_id.0)
-        .or_insert_with(|| GuildData::new(guild_id));
-
-    let activity = match guild_data.activity_mut(activity_id) {
-        Some(activity) => activity,
-        None => {
-            imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
-            return Ok(());
-        }
-    };
-
-    let error = match activity.remove_member(user_id) {
-        Err(_) => match activity.remove_member_alt(user_id) {
-            Err(ActivityError::MemberNotInAlternate) => {
-                Some("That user is not in that activity's fireteam.")
-            }
-            Err(_) => Some("Some other error occurred removing that user from the fireteam."),
-            Ok(()) => None,
-        },
+    let error = match result {
         Ok(()) => None,
+        Err(ActivityUpdateError::InvalidActivity) => Some("Invalid activity ID.".into()),
+        Err(ActivityUpdateError::Activity(e)) => Some(e.to_string()),
+        Err(ActivityUpdateError::Serenity(e)) => return Err(e.into()),
+        Err(_) => Some("Some other error occurred removing that user from the fireteam.".into()),
     };
 
     if let Some(msg) = error {
@@ -424,18 +520,6 @@ async fn admin_activity_remove(
         return Ok(());
     }
 
-    let activity_embed = activity.as_create_embed(0x212121);
-
-    activity
-        .embed_msg
-        .edit(ctx, |msg| {
-            msg.embed(|embed| {
-                *embed = activity_embed;
-                embed
-            })
-        })
-        .await?;
-
     original_msg.delete(ctx).await?;
 
     Ok(())
@@ -449,8 +533,7 @@ async fn admin_activity_edit(
     let activity_id_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let activity_id = match activity_id_opt {
         Some(id) => id,
@@ -469,223 +552,70 @@ async fn admin_activity_edit(
         }
     };
 
-    let mut type_map = ctx.data.write().await;
+    imp::prompt_activity_edit(
+        ctx,
+        original_msg,
+        guild_id,
+        Actor::Admin(original_msg.author.id),
+        activity_id,
+    )
+    .await
+}
 
-    let embed_map = match type_map.get::<data_keys::GetEmbedMap>() {
-        Some(map) => map.clone(),
+async fn admin_activity_delete(
+    ctx: &Context,
+    original_msg: &Message,
+    mut args: Args,
+) -> CommandResult {
+    let activity_id_opt = args
+        .advance()
+        .current()
+        .and_then(|string| string.parse::<u64>().ok());
+
+    let activity_id = match activity_id_opt {
+        Some(id) => id,
         None => {
-            original_msg
-                .channel_id
-                .say(ctx, "Embed map was not registered.")
-                .await?;
+            imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
             return Ok(());
         }
     };
 
-    let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-    let guild_data = guild_data_map
-        .entry(guild_id.0)
-        .or_insert_with(|| GuildData::new(guild_id));
-
-    match guild_data.activity(activity_id) {
-        Some(activity) => {
-            let mut activity = activity.clone();
-
-            drop(type_map);
-
-            let timeout = Duration::from_secs(120);
-
-            let time_embed = match embed_map.get("activity_roster_time") {
-                Some(embed) => embed,
-                None => {
-                    imp::send_error_message(ctx, original_msg, "An error has occurred getting embed `activity_roster_time`. Please contact Factorial about this.").await?;
-                    return Ok(());
-                }
-            };
-
-            let mut embed_msg = original_msg
-                .channel_id
-                .send_message(ctx, |msg| {
-                    msg.embed(|embed| {
-                        *embed = imp::create_embed(&time_embed.embed);
-                        embed
-                    })
-                })
-                .await?;
-
-            let (date_time, date_time_str) = loop {
-                let collector = embed_msg
-                    .channel_id
-                    .await_reply(ctx)
-                    .author_id(original_msg.author.id)
-                    .timeout(timeout);
-
-                let time_message = match collector.await {
-                    Some(message) => message,
-                    None => {
-                        imp::send_error_message(
-                            ctx,
-                            original_msg,
-                            format!(
-                                "You did not send a reply in time. Please reply within {} minutes",
-                                timeout.as_secs() / 60
-                            ),
-                        )
-                        .await?;
-                        return Ok(());
-                    }
-                };
-
-                match imp::parse_date_time(&time_message.content) {
-                    Some((date_time, date_time_str)) => {
-                        time_message.delete(ctx).await?;
-                        break (date_time, date_time_str);
-                    }
-                    None => {
-                        imp::send_error_message(ctx, &time_message, "Please enter a valid date and time in the format `mm/dd/yyyy hh:mm am|pm`").await?;
-                    }
-                }
-            };
-
-            let description_embed = match embed_map.get("activity_roster_description") {
-                Some(embed) => embed,
-                None => {
-                    imp::send_error_message(ctx, original_msg, "An error has occurred getting embed `activity_roster_description`. Please contact Factorial about this.").await?;
-                    return Ok(());
-                }
-            };
-
-            embed_msg
-                .edit(ctx, |msg| {
-                    msg.embed(|embed| {
-                        *embed = imp::create_embed(&description_embed.embed);
-                        embed
-                    })
-                })
-                .await?;
-
-            let description = loop {
-                let collector = embed_msg
-                    .channel_id
-                    .await_reply(ctx)
-                    .author_id(original_msg.author.id)
-                    .timeout(timeout);
-
-                let description_message = match collector.await {
-                    Some(message) => message,
-                    None => {
-                        imp::send_error_message(
-                            ctx,
-                            original_msg,
-                            format!(
-                                "You did not send a reply in time. Please reply within {} minutes",
-                                timeout.as_secs() / 60
-                            ),
-                        )
-                        .await?;
-                        return Ok(());
-                    }
-                };
-
-                let safe_content = description_message.content_safe(ctx).await;
-
-                if safe_content.len() <= 1024 {
-                    description_message.delete(ctx).await?;
-                    break safe_content;
-                } else {
-                    imp::send_error_message(
-                        ctx,
-                        &description_message,
-                        "Please enter a description that is less than or equal to 1024 characters.",
-                    )
-                    .await?;
-                }
-            };
-
-            let (cancel_future, cancel_token) = CancelActivity::new_pair();
-            tokio::pin!(cancel_future);
-
-            activity.date = date_time_str;
-            activity.description = description;
-            activity.cancel_token = cancel_token;
-
-            let activity_embed = activity.as_create_embed(0x212121);
-
-            activity
-                .embed_msg
-                .edit(ctx, |msg| {
-                    msg.embed(|embed| {
-                        *embed = activity_embed;
-                        embed
-                    })
-                })
+    let guild_id = match original_msg.guild_id {
+        Some(id) => id,
+        None => {
+            imp::send_error_message(ctx, original_msg, "This command is not supported in DMs.")
                 .await?;
+            return Ok(());
+        }
+    };
 
-            embed_msg.delete(ctx).await?;
-
-            let mut type_map = ctx.data.write().await;
-            let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-            let guild_data = guild_data_map.entry(guild_id.0).or_default();
-
-            let old_activity = match guild_data.activity_mut(activity_id) {
-                Some(activity) => activity,
-                None => {
-                    imp::send_error_message(ctx, original_msg, "Could not get the old activity from GuildData. Please contact Factorial about this.").await?;
-                    return Ok(());
-                }
-            };
-
+    match imp::delete_activity(
+        ctx,
+        guild_id,
+        Actor::Admin(original_msg.author.id),
+        activity_id,
+    )
+    .await
+    {
+        Ok(activity) => {
             original_msg
                 .channel_id
                 .say(
                     ctx,
-                    format!(
-                        "Activity {} ({}) updated successfully.",
-                        activity.id, activity.name
-                    ),
+                    format!("Deleted activity {}: {}.", activity.id, activity.name),
                 )
                 .await?;
-
-            *old_activity = activity;
-
-            drop(type_map);
-
-            let date_time_now =
-                FixedOffset::west(4 * 3600).from_utc_datetime(&Utc::now().naive_utc());
-            let duration_until_start = match (date_time - date_time_now).to_std() {
-                Ok(duration) => duration,
-                Err(_) => {
-                    imp::send_error_message(
-                        ctx,
-                        original_msg,
-                        "Invalid date and time. Please enter a valid date and time that is in the future.",
-                    )
-                        .await?;
-                    return Ok(());
-                }
-            };
-
-            let sleep = tokio::time::sleep(duration_until_start);
-            tokio::pin!(sleep);
-
-            tokio::select! {
-                _ = &mut cancel_future => (),
-                _ = &mut sleep => {
-                    let mut type_map = ctx.data.write().await;
-                    imp::start_activity(ctx, &mut type_map, guild_id, activity_id).await?;
-                }
-            }
-
-            Ok(())
         }
-        None => {
+        Err(ActivityUpdateError::Serenity(e)) => return Err(e.into()),
+        Err(_) => {
             imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
-            Ok(())
         }
     }
+
+    Ok(())
 }
 
-async fn admin_activity_delete(
+async fn admin_activity_start(
     ctx: &Context,
     original_msg: &Message,
     mut args: Args,
@@ -693,8 +623,7 @@ async fn admin_activity_delete(
     let activity_id_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let activity_id = match activity_id_opt {
         Some(id) => id,
@@ -713,30 +642,25 @@ async fn admin_activity_delete(
         }
     };
 
-    let mut type_map = ctx.data.write().await;
-
-    let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-    let guild_data = guild_data_map
-        .entry(guild_id.0)
-        .or_insert_with(|| GuildData::new(guild_id));
-
-    let activity_opt = guild_data.remove_activity(activity_id);
-    if let Some(activity) = activity_opt {
-        activity.embed_msg.delete(ctx).await?;
-        activity.cancel_token.cancel();
-        original_msg
-            .channel_id
-            .say(
-                ctx,
-                format!("Deleted activity {}: {}.", activity.id, activity.name),
-            )
-            .await?;
+    match imp::start_activity(
+        ctx,
+        guild_id,
+        Actor::Admin(original_msg.author.id),
+        activity_id,
+    )
+    .await
+    {
+        Ok(()) => (),
+        Err(ActivityUpdateError::Serenity(e)) => return Err(e.into()),
+        Err(_) => {
+            imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
+        }
     }
 
     Ok(())
 }
 
-async fn admin_activity_start(
+async fn admin_activity_ping(
     ctx: &Context,
     original_msg: &Message,
     mut args: Args,
@@ -744,8 +668,7 @@ async fn admin_activity_start(
     let activity_id_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let activity_id = match activity_id_opt {
         Some(id) => id,
@@ -764,30 +687,33 @@ async fn admin_activity_start(
         }
     };
 
-    let mut type_map = ctx.data.write().await;
-
-    let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-    let guild_data = guild_data_map
-        .entry(guild_id.0)
-        .or_insert_with(|| GuildData::new(guild_id));
+    let ping = {
+        let type_map = ctx.data.read().await;
 
-    let activity_opt = guild_data.activity(activity_id);
+        type_map
+            .get::<data_keys::GetGuildData>()
+            .and_then(|guild_data_map| guild_data_map.get(&guild_id.0))
+            .and_then(|guild_data| guild_data.activity(activity_id))
+            .map(|activity| (activity.embed_msg.channel_id, activity.members.clone()))
+    };
 
-    let activity_id = match activity_opt {
-        Some(activity) => {
-            activity.cancel_token.cancel();
-            activity.id
+    match ping {
+        Some((channel_id, members)) => {
+            if !members.is_empty() {
+                channel_id.say(ctx, mention_string(members.iter())).await?;
+            }
         }
         None => {
             imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
-            return Ok(());
         }
-    };
+    }
 
-    Ok(imp::start_activity(ctx, &mut type_map, guild_id, activity_id).await?)
+    Ok(())
 }
 
-async fn admin_activity_ping(
+/// Shows who showed up to a started activity, or marks users as present or absent with
+/// `attendance <id> present|absent <users...>`.
+async fn admin_activity_attendance(
     ctx: &Context,
     original_msg: &Message,
     mut args: Args,
@@ -795,8 +721,7 @@ async fn admin_activity_ping(
     let activity_id_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let activity_id = match activity_id_opt {
         Some(id) => id,
@@ -815,44 +740,112 @@ async fn admin_activity_ping(
         }
     };
 
-    let mut type_map = ctx.data.write().await;
-
-    let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-    let guild_data = guild_data_map
-        .entry(guild_id.0)
-        .or_insert_with(|| GuildData::new(guild_id));
+    let attendance = match args.advance().current() {
+        Some(arg) => match arg.parse::<Attendance>() {
+            Ok(attendance) => attendance,
+            Err(()) => {
+                imp::send_error_message(
+                    ctx,
+                    original_msg,
+                    "Attendance must be `present` or `absent`.",
+                )
+                .await?;
+                return Ok(());
+            }
+        },
+        None => {
+            let embed_opt = ctx
+                .data
+                .read()
+                .await
+                .get::<data_keys::GetGuildData>()
+                .and_then(|guild_map| guild_map.get(&guild_id.0))
+                .and_then(|guild_data| guild_data.archived(activity_id))
+                .map(|archived| archived.as_attendance_embed(0x212121));
+
+            match embed_opt {
+                Some(attendance_embed) => {
+                    original_msg
+                        .channel_id
+                        .send_message(ctx, |msg| {
+                            msg.embed(|embed| {
+                                *embed = attendance_embed;
+                                embed
+                            })
+                        })
+                        .await?;
+                }
+                None => {
+                    imp::send_error_message(ctx, original_msg, "No started activity has that ID.")
+                        .await?;
+                }
+            }
 
-    let activity_opt = guild_data.activity(activity_id);
-    match activity_opt {
-        Some(activity) => {
-            if !activity.members.is_empty() {
-                let member_count = activity.members.len();
-
-                let mention_string = activity
-                    .members
-                    .iter()
-                    .enumerate()
-                    .map(|(idx, &user)| {
-                        if idx == 0 {
-                            Mention::from(user).to_string()
-                        } else if idx == member_count - 1 {
-                            format!(", and {}", Mention::from(user))
-                        } else {
-                            format!(", {}", Mention::from(user))
-                        }
-                    })
-                    .collect::<String>();
+            return Ok(());
+        }
+    };
 
-                let content = format!("{}", mention_string);
+    let mut users = Vec::new();
 
-                activity.embed_msg.channel_id.say(ctx, content).await?;
+    for arg in args.advance().iter::<String>().filter_map(Result::ok) {
+        match imp::parse_user_id(&arg) {
+            Some(user_id) => users.push(user_id),
+            None => {
+                imp::send_error_message(ctx, original_msg, format!("Invalid user `{}`.", arg))
+                    .await?;
+                return Ok(());
             }
         }
-        None => {
-            imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
+    }
+
+    if users.is_empty() {
+        imp::send_error_message(ctx, original_msg, "Please list the users to mark.").await?;
+        return Ok(());
+    }
+
+    let actor = Actor::Admin(original_msg.author.id);
+    let results = imp::with_service(ctx, guild_id, |service| {
+        users
+            .iter()
+            .map(|&user_id| {
+                (
+                    user_id,
+                    service.mark_attendance(actor, activity_id, user_id, attendance),
+                )
+            })
+            .collect::<Vec<_>>()
+    })
+    .await;
+
+    let mut marked = 0;
+    let mut not_on_roster = Vec::new();
+
+    for (user_id, result) in results {
+        match result {
+            Ok(()) => marked += 1,
+            Err(ActivityUpdateError::InvalidActivity) => {
+                imp::send_error_message(ctx, original_msg, "No started activity has that ID.")
+                    .await?;
+                return Ok(());
+            }
+            Err(ActivityUpdateError::Activity(_)) => {
+                not_on_roster.push(Mention::from(user_id).to_string())
+            }
+            Err(e) => return Err(e.into()),
         }
     }
 
+    let mut content = format!("Marked {} user(s) as {}.", marked, attendance);
+
+    if !not_on_roster.is_empty() {
+        content.push_str(&format!(
+            " {} weren't on the roster, so they weren't marked.",
+            not_on_roster.join(", ")
+        ));
+    }
+
+    original_msg.channel_id.say(ctx, content).await?;
+
     Ok(())
 }
 
@@ -1,34 +1,41 @@
 use crate::command::imp;
 
 use std::{
+    cmp::Ordering,
     collections::{HashMap, HashSet},
     error, fmt,
     time::Duration,
 };
 
 use crate::util::ActivityToken;
+use chrono::{DateTime, FixedOffset, NaiveTime, TimeZone, Utc};
+use itertools::Itertools;
 use markov::Chain;
 use serde::{Deserialize, Serialize};
 use serenity::{
     builder::CreateEmbed,
-    collector::ReactionAction,
-    model::{
-        channel::ReactionType,
-        prelude::{ChannelId, Embed, GuildId, Mention, Message, UserId},
-    },
-    prelude::Context,
+    model::prelude::{ChannelId, Embed, GuildId, Mention, Message, UserId},
 };
 use std::fmt::Formatter;
+use std::str::FromStr;
 
 #[derive(Debug, Default)]
 pub struct GuildData {
+    #[allow(dead_code)]
     guild_id: GuildId,
     activities: HashMap<u64, Activity>,
     next_activity_id: u64,
     free_activity_ids: Vec<u64>,
     messages: HashMap<UserId, Vec<String>>,
     markov: HashMap<String, MarkovInfo>,
+    #[allow(dead_code)]
     changelog_channel: Option<ChannelId>,
+    settings: GuildSettings,
+    roster_board: Option<Message>,
+    /// Saved activities that can be created again, keyed by their lowercase name.
+    templates: HashMap<String, ActivityTemplate>,
+    /// Activities that have started, oldest first.
+    history: Vec<ArchivedActivity>,
 }
 
 impl GuildData {
@@ -41,10 +48,17 @@ impl GuildData {
             messages: HashMap::new(),
             markov: HashMap::new(),
             changelog_channel: None,
+            settings: GuildSettings::default(),
+            roster_board: None,
+            templates: HashMap::new(),
+            history: Vec::new(),
         }
     }
 
-    pub fn add_activity(&mut self, activity: Activity) -> Result<(), Activity> {
+    /// How many started activities are kept for attendance and stats.
+    pub const HISTORY_LIMIT: usize = 500;
+
+    pub fn add_activity(&mut self, activity: Activity) -> Result<(), Box<Activity>> {
         if !self.activities.contains_key(&activity.id) {
             let idx_opt = self
                 .free_activity_ids
@@ -65,7 +79,7 @@ impl GuildData {
 
             Ok(())
         } else {
-            Err(activity)
+            Err(Box::new(activity))
         }
     }
 
@@ -90,6 +104,30 @@ impl GuildData {
         &self.activities
     }
 
+    /// Returns the other activities that `user` is in the main fireteam of and that overlap
+    /// with the activity with ID `activity_id`.
+    pub fn conflicts(&self, activity_id: u64, user: UserId) -> Vec<&Activity> {
+        let activity = match self.activities.get(&activity_id) {
+            Some(activity) => activity,
+            None => return Vec::new(),
+        };
+
+        self.activities_by_start()
+            .into_iter()
+            .filter(|other| other.id != activity_id)
+            .filter(|other| other.members.contains(&user))
+            .filter(|other| activity.overlaps(other))
+            .collect()
+    }
+
+    /// Returns every activity in this guild, ordered by start time and then by ID.
+    pub fn activities_by_start(&self) -> Vec<&Activity> {
+        self.activities
+            .values()
+            .sorted_by_key(|activity| (activity.start.date_time, activity.id))
+            .collect()
+    }
+
     pub fn activity_id(&self) -> u64 {
         self.free_activity_ids
             .first()
@@ -113,457 +151,1781 @@ impl GuildData {
         &mut self.markov
     }
 
-    pub fn set_changelog_channel(&mut self, id: u64) {
-        let id = ChannelId::from(id);
-        self.changelog_channel = Some(id);
-    }
-}
-
-#[non_exhaustive]
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
-pub enum ActivityError {
-    MemberListFull,
-    MemberAlreadyInList,
-    MemberNotInList,
-    AlternateFull,
-    MemberAlreadyInAlternate,
-    MemberNotInAlternate,
-}
-
-#[derive(Clone, Debug)]
-pub struct Activity {
-    pub name: String,
-    pub description: String,
-    pub date: String,
-    pub id: u64,
-    pub size: u8,
-    pub creator: UserId,
-    pub embed_msg: Message,
-    pub cancel_token: ActivityToken,
-    pub members: HashSet<UserId>,
-    pub alternate: Vec<UserId>,
-}
+    /// Deletes the cached messages of `user` and every markov chain built from them. Returns how
+    /// many messages and chains were deleted.
+    pub fn forget_user(&mut self, user: UserId) -> (usize, usize) {
+        let messages = self
+            .messages
+            .remove(&user)
+            .map_or(0, |messages| messages.len());
 
-impl Activity {
-    pub fn new<S: ToString>(
-        name: S,
-        description: S,
-        date: S,
-        id: u64,
-        size: u8,
-        creator: UserId,
-        embed_msg: Message,
-        cancel_token: ActivityToken,
-    ) -> Self {
-        let name = name.to_string();
-        let description = description.to_string();
-        let date = date.to_string();
+        let chains = self.markov.len();
+        self.markov.retain(|_, info| info.user() != user);
 
-        Self {
-            name,
-            description,
-            date,
-            id,
-            size,
-            creator,
-            embed_msg,
-            cancel_token,
-            members: HashSet::with_capacity(size as usize),
-            alternate: Vec::with_capacity(size as usize),
-        }
+        (messages, chains - self.markov.len())
     }
 
-    pub fn add_member(&mut self, member: UserId) -> Result<(), ActivityError> {
-        if !self.alternate.contains(&member) {
-            if self.members.len() < self.size as usize {
-                if self.members.insert(member) {
-                    Ok(())
-                } else {
-                    Err(ActivityError::MemberAlreadyInList)
-                }
-            } else {
-                Err(ActivityError::MemberListFull)
-            }
-        } else {
-            let idx = self
-                .alternate
-                .iter()
-                .position(|other_member| member == *other_member);
-
-            match idx {
-                Some(idx) => {
-                    if self.members.len() < self.size as usize {
-                        self.alternate.remove(idx);
-                        self.members.insert(member);
-                    } else {
-                        return Err(ActivityError::MemberListFull);
-                    }
-                    Ok(())
-                }
-                None => Err(ActivityError::MemberNotInAlternate),
-            }
-        }
+    #[allow(dead_code)]
+    pub fn set_changelog_channel(&mut self, id: u64) {
+        let id = ChannelId::from(id);
+        self.changelog_channel = Some(id);
     }
 
-    pub fn add_member_alt(&mut self, member: UserId) -> Result<(), ActivityError> {
-        if !self.members.contains(&member) {
-            if self.alternate.len() < self.size as usize {
-                if !self.alternate.contains(&member) {
-                    self.alternate.push(member);
-                    Ok(())
-                } else {
-                    Err(ActivityError::MemberAlreadyInAlternate)
-                }
-            } else {
-                Err(ActivityError::AlternateFull)
-            }
-        } else {
-            if self.alternate.len() < self.size as usize {
-                self.members.remove(&member);
-                self.alternate.push(member);
-                Ok(())
-            } else {
-                Err(ActivityError::AlternateFull)
-            }
-        }
+    pub fn settings(&self) -> &GuildSettings {
+        &self.settings
     }
 
-    pub fn remove_member(&mut self, member: UserId) -> Result<(), ActivityError> {
-        if self.members.remove(&member) {
-            Ok(())
-        } else {
-            Err(ActivityError::MemberNotInList)
-        }
+    pub fn settings_mut(&mut self) -> &mut GuildSettings {
+        &mut self.settings
     }
 
-    pub fn remove_member_alt(&mut self, member: UserId) -> Result<(), ActivityError> {
-        let idx = self
-            .alternate
-            .iter()
-            .position(|other_member| member == *other_member);
-
-        match idx {
-            Some(idx) => {
-                self.alternate.remove(idx);
-                Ok(())
-            }
-            None => Err(ActivityError::MemberNotInAlternate),
-        }
-    }
+    pub fn as_board_embed(&self, color: u32) -> CreateEmbed {
+        // Discord only allows 25 fields in one embed.
+        const MAX_FIELDS: usize = 25;
 
-    pub fn as_create_embed(&self, color: u32) -> CreateEmbed {
         let mut embed = CreateEmbed::default();
+        let activities = self.activities_by_start();
 
-        let members_string = if !self.members.is_empty() {
-            self.members
-                .iter()
-                .copied()
-                .enumerate()
-                .map(|(idx, id)| {
-                    if idx == 0 {
-                        Mention::from(id).to_string()
-                    } else {
-                        let mention = Mention::from(id).to_string();
-                        format!(", {}", mention)
-                    }
-                })
-                .collect::<String>()
-        } else {
-            String::from("None")
-        };
+        embed.color(color).title("Upcoming Activities");
 
-        let alternate_string = if !self.alternate.is_empty() {
-            self.alternate
-                .iter()
-                .copied()
-                .enumerate()
-                .map(|(idx, id)| {
-                    if idx == 0 {
-                        Mention::from(id).to_string()
-                    } else {
-                        let mention = Mention::from(id).to_string();
-                        format!(", {}", mention)
-                    }
-                })
-                .collect::<String>()
-        } else {
-            String::from("None")
-        };
+        if activities.is_empty() {
+            embed.description("No activities are currently scheduled.");
+        }
 
-        embed
-            .color(color)
-            .title("Activity Roster")
-            .field("Activity:", &self.name, true)
-            .field("Time:", &self.date, true)
-            .field("Activity ID:", self.id, true)
-            .field("Description:", &self.description, false)
-            .field("Member List:", members_string, false)
-            .field("Alternate Members:", alternate_string, false)
-            .field(
-                "Joining And Leaving:",
+        for activity in activities.iter().take(MAX_FIELDS) {
+            embed.field(
+                format!("{} (ID: {})", activity.name, activity.id),
                 format!(
-                    "Use ~activity join {}, ~activity alt {}, or ~activity leave {} to \
-                     join, join as an alternate, or leave an activity.",
-                    self.id, self.id, self.id
+                    "{}\n{}/{} members, {} alternates\n[View roster]({})",
+                    activity.start,
+                    activity.members.len(),
+                    activity.size,
+                    activity.alternate.len(),
+                    activity.embed_msg.link()
                 ),
                 false,
-            )
-            .field(
-                "Important Information:",
-                "Bingus will ping you in this channel when your activity is ready. \
-                 Please keep this channel unmuted.",
-                false,
-            )
-            .footer(|footer| {
-                footer.text("For command documentation, please see Bingus's GitHub page.")
+            );
+        }
+
+        if activities.len() > MAX_FIELDS {
+            let hidden = activities.len() - MAX_FIELDS;
+            embed.footer(|footer| {
+                footer.text(format!(
+                    "{} more activities are not shown. Use activity list to see them.",
+                    hidden
+                ))
             });
+        }
 
         embed
     }
-}
 
-#[derive(Debug)]
-#[non_exhaustive]
-pub enum EmbedError {
-    InvalidReaction,
-    RemovedReaction,
-    TimedOut,
-    Other,
-    Serenity(serenity::Error),
-}
+    pub fn roster_board(&self) -> Option<&Message> {
+        self.roster_board.as_ref()
+    }
 
-impl From<serenity::Error> for EmbedError {
-    fn from(e: serenity::Error) -> Self {
-        Self::Serenity(e)
+    pub fn set_roster_board(&mut self, message: Option<Message>) {
+        self.roster_board = message;
     }
-}
 
-impl fmt::Display for EmbedError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        use EmbedError::*;
+    /// Returns every saved template, ordered by name.
+    pub fn templates(&self) -> Vec<&ActivityTemplate> {
+        self.templates
+            .values()
+            .sorted_by_key(|template| template.name.to_lowercase())
+            .collect()
+    }
 
-        let display = match self {
-            InvalidReaction => "Invalid reaction".into(),
-            RemovedReaction => "Removed reaction".into(),
-            TimedOut => "Timed out".into(),
-            Other => "Other".into(),
-            Serenity(e) => format!("Serenity error: {}", e),
-        };
+    /// Finds a template by name, ignoring case.
+    pub fn template(&self, name: &str) -> Option<&ActivityTemplate> {
+        self.templates.get(&name.to_lowercase())
+    }
 
-        write!(f, "{}", display)
+    /// Saves a template, replacing any template with the same name.
+    pub fn save_template(&mut self, template: ActivityTemplate) {
+        self.templates
+            .insert(template.name.to_lowercase(), template);
     }
-}
 
-impl error::Error for EmbedError {}
+    pub fn remove_template(&mut self, name: &str) -> Option<ActivityTemplate> {
+        self.templates.remove(&name.to_lowercase())
+    }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct EmbedWithMeta {
-    pub embed: Embed,
-    pub meta: Option<Vec<SelectionInfo>>,
-}
+    /// Removes `user` as the creator of the templates they saved. The templates are kept for the
+    /// rest of the guild. Returns how many templates were changed.
+    pub fn forget_templates(&mut self, user: UserId) -> usize {
+        let mut forgotten = 0;
 
-impl EmbedWithMeta {
-    pub async fn send_embed_chain(
-        self,
-        ctx: &Context,
-        embed_map: &HashMap<String, EmbedWithMeta>,
-        channel: ChannelId,
-        timeout: Option<Duration>,
-        from_user: Option<UserId>,
-    ) -> Result<RosterData, EmbedError> {
-        let mut embed_with_meta = self;
-        let mut embed_msg = None;
-
-        loop {
-            if embed_msg.is_none() {
-                embed_msg = Some(
-                    channel
-                        .send_message(ctx, |msg| {
-                            msg.set_embed(imp::create_embed(&embed_with_meta.embed))
-                        })
-                        .await?,
-                );
-            } else if let Some(ref mut edited_msg) = embed_msg {
-                edited_msg.delete_reactions(ctx).await?;
-
-                edited_msg
-                    .edit(ctx, |msg| {
-                        msg.embed(|edited_embed| {
-                            *edited_embed = imp::create_embed(&embed_with_meta.embed);
-                            edited_embed
-                        })
-                    })
-                    .await?;
+        for template in self.templates.values_mut() {
+            if template.creator == Some(user) {
+                template.creator = None;
+                forgotten += 1;
             }
+        }
+
+        forgotten
+    }
 
-            let embed_msg = embed_msg.clone().ok_or(EmbedError::Other)?;
-            let meta = embed_with_meta.meta.ok_or(EmbedError::Other)?;
+    /// Keeps the final roster of an activity that has started. The oldest activities are
+    /// dropped once there are more than `HISTORY_LIMIT`.
+    pub fn archive(&mut self, activity: &Activity) {
+        self.history.push(ArchivedActivity::from_activity(activity));
 
-            for selection_info in meta.iter() {
-                embed_msg
-                    .react(ctx, ReactionType::Unicode(selection_info.name.clone()))
-                    .await?;
-            }
+        if self.history.len() > Self::HISTORY_LIMIT {
+            let overflow = self.history.len() - Self::HISTORY_LIMIT;
+            self.history.drain(..overflow);
+        }
+    }
+
+    pub fn history(&self) -> &[ArchivedActivity] {
+        &self.history
+    }
+
+    /// Finds the most recent started activity with ID `id`. IDs are reused once an activity
+    /// starts, so older activities with the same ID can't be found this way.
+    pub fn archived(&self, id: u64) -> Option<&ArchivedActivity> {
+        self.history.iter().rev().find(|archived| archived.id == id)
+    }
 
-            let mut collector = embed_msg.await_reaction(ctx);
+    pub fn archived_mut(&mut self, id: u64) -> Option<&mut ArchivedActivity> {
+        self.history
+            .iter_mut()
+            .rev()
+            .find(|archived| archived.id == id)
+    }
+
+    /// Adds up how each user took part in the started activities.
+    pub fn stats(&self) -> HashMap<UserId, UserStats> {
+        let mut stats = HashMap::<UserId, UserStats>::new();
 
-            if let Some(id) = from_user {
-                collector = collector.author_id(id);
+        for archived in self.history.iter() {
+            stats.entry(archived.creator).or_default().hosted += 1;
+
+            for &member in archived.members.iter() {
+                stats.entry(member).or_default().joined += 1;
             }
 
-            if let Some(duration) = timeout {
-                collector = collector.timeout(duration);
+            // Alternates only count once they're marked, so attended never exceeds joined.
+            for alternate in archived.alternates.iter() {
+                if archived.attendance.contains_key(alternate) {
+                    stats.entry(*alternate).or_default().joined += 1;
+                }
             }
 
-            let selection_info =
-                helpers::await_reaction(ctx, collector, embed_msg.clone(), timeout, meta).await?;
+            for (&user, &attendance) in archived.attendance.iter() {
+                let user_stats = stats.entry(user).or_default();
 
-            match selection_info.kind {
-                RosterKind::SelectNext(name) => {
-                    embed_with_meta = embed_map
-                        .get(&name)
-                        .cloned()
-                        .ok_or(EmbedError::InvalidReaction)?;
-                }
-                RosterKind::Finished {
-                    activity_name,
-                    size,
-                } => {
-                    return Ok(RosterData {
-                        activity_name,
-                        size,
-                        message: embed_msg,
-                        timeout,
-                    })
+                match attendance {
+                    Attendance::Present => user_stats.attended += 1,
+                    Attendance::Absent => user_stats.no_shows += 1,
                 }
             }
         }
+
+        stats
     }
 
-    // pub async fn send_and_await_reaction(
-    //     &self,
-    //     ctx: &Context,
-    //     channel: ChannelId,
-    //     timeout: Option<Duration>,
-    //     from_user: Option<UserId>,
-    // ) -> Result<MessageData, ReactionError> {
-    //     let embed_msg = channel
-    //         .send_message(ctx, |msg| msg.set_embed(imp::create_embed(&self.embed)))
-    //         .await?;
-    //
-    //     for selection_info in self.meta.iter() {
-    //         embed_msg.react(ctx, selection_info.name).await?;
-    //     }
-    //
-    //     let mut collector = embed_msg.await_reaction(ctx);
-    //
-    //     if let Some(id) = from_user {
-    //         collector = collector.author_id(id);
-    //     }
-    //
-    //     if let Some(duration) = timeout {
-    //         collector = collector.timeout(duration);
-    //     }
-    //
-    //     helpers::await_reaction(
-    //         ctx,
-    //         collector,
-    //         embed_msg,
-    //         timeout,
-    //         from_user,
-    //         embed_with_meta.meta,
-    //     )
-    //     .await
-    // }
-}
+    /// The users who showed up to the most activities, most reliable first among ties. Users who
+    /// never had their attendance marked aren't ranked.
+    pub fn leaderboard(&self, limit: usize) -> Vec<(UserId, UserStats)> {
+        self.stats()
+            .into_iter()
+            .filter(|(_, stats)| stats.reliability().is_some())
+            .sorted_by(|(a_user, a), (b_user, b)| {
+                b.attended
+                    .cmp(&a.attended)
+                    .then_with(|| {
+                        b.reliability()
+                            .partial_cmp(&a.reliability())
+                            .unwrap_or(Ordering::Equal)
+                    })
+                    .then_with(|| a_user.cmp(b_user))
+            })
+            .take(limit)
+            .collect()
+    }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct SelectionInfo {
-    pub name: String,
-    pub kind: RosterKind,
-}
+    /// Removes `user` from the rosters and attendance of started activities. Returns how many
+    /// activities they were removed from. Activities they hosted are kept, since every archived
+    /// activity needs a creator.
+    pub fn forget_history(&mut self, user: UserId) -> usize {
+        let mut forgotten = 0;
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub enum RosterKind {
-    SelectNext(String),
-    Finished { activity_name: String, size: u8 },
+        for archived in self.history.iter_mut() {
+            let before = (archived.members.len(), archived.alternates.len());
+
+            archived.members.retain(|&member| member != user);
+            archived.alternates.retain(|&alternate| alternate != user);
+            archived.member_roles.remove(&user);
+            let had_attendance = archived.attendance.remove(&user).is_some();
+
+            if had_attendance || before != (archived.members.len(), archived.alternates.len()) {
+                forgotten += 1;
+            }
+        }
+
+        forgotten
+    }
 }
 
-#[derive(Clone, Debug)]
-pub struct RosterData {
-    pub activity_name: String,
-    pub size: u8,
-    pub message: Message,
+/// Per-guild settings that override the bot-wide defaults from `config.json`.
+#[derive(Clone, Debug, Default)]
+pub struct GuildSettings {
+    pub prefix: Option<String>,
+    pub activity_channel: Option<ChannelId>,
+    pub roster_channel: Option<ChannelId>,
+    pub block_conflicts: bool,
     pub timeout: Option<Duration>,
+    pub command_channels: HashSet<ChannelId>,
 }
 
-#[derive(Debug)]
-pub struct MarkovInfo {
-    id: UserId,
-    chain: Chain<String>,
-}
+impl GuildSettings {
+    pub const KEYS: &'static [&'static str] = &[
+        "prefix",
+        "activity_channel",
+        "roster_channel",
+        "block_conflicts",
+        "timeout",
+        "command_channels",
+    ];
 
-impl MarkovInfo {
-    pub fn new(id: UserId, order: usize) -> Self {
-        Self {
-            id,
-            chain: Chain::of_order(order),
-        }
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout.unwrap_or(Self::DEFAULT_TIMEOUT)
     }
 
-    pub fn feed_str(&mut self, s: &str) {
-        self.chain.feed_str(s);
+    /// Returns true if commands may be run in `channel`. An empty list allows every channel.
+    pub fn allows_commands_in(&self, channel: ChannelId) -> bool {
+        self.command_channels.is_empty() || self.command_channels.contains(&channel)
     }
 
-    pub fn gen_string(&self) -> String {
-        self.chain.generate_str()
+    pub fn get(&self, key: &str) -> Result<String, SettingsError> {
+        let value = match key {
+            "prefix" => match &self.prefix {
+                Some(prefix) => format!("`{}`", prefix),
+                None => String::from("Not set (using the default prefix)"),
+            },
+            "activity_channel" => match self.activity_channel {
+                Some(id) => Mention::from(id).to_string(),
+                None => String::from("Not set (using the channel the command was run in)"),
+            },
+            "roster_channel" => match self.roster_channel {
+                Some(id) => Mention::from(id).to_string(),
+                None => String::from("Not set (no roster board)"),
+            },
+            "block_conflicts" => {
+                if self.block_conflicts {
+                    String::from("On (members cannot join overlapping activities)")
+                } else {
+                    String::from("Off (members are warned about overlapping activities)")
+                }
+            }
+            "timeout" => format!("{} seconds", self.timeout().as_secs()),
+            "command_channels" => {
+                if self.command_channels.is_empty() {
+                    String::from("All channels")
+                } else {
+                    self.command_channels
+                        .iter()
+                        .map(|&id| Mention::from(id).to_string())
+                        .join(", ")
+                }
+            }
+            _ => return Err(SettingsError::InvalidKey(Self::KEYS)),
+        };
+
+        Ok(value)
     }
-}
 
-mod helpers {
-    use super::*;
-    use serenity::collector::CollectReaction;
-
-    pub async fn await_reaction(
-        ctx: &Context,
-        collector: CollectReaction<'_>,
-        message: Message,
-        timeout: Option<Duration>,
-        selections: Vec<SelectionInfo>,
-    ) -> Result<SelectionInfo, EmbedError> {
-        // Interacting with Discord's API can be... verbose at times.
-        match collector.await {
-            Some(action) => {
-                match &*action {
-                    ReactionAction::Added(reaction) => {
-                        let name = reaction.emoji.as_data();
-                        selections
-                            .into_iter()
-                            .find(|info| info.name == name)
-                            .ok_or(EmbedError::InvalidReaction)
-                    }
-                    ReactionAction::Removed(_) => {
-                        message.channel_id.say(ctx, "You removed a reaction before the bot was ready. Please try again.").await?;
-                        Err(EmbedError::RemovedReaction)
-                    }
+    /// Sets `key` from a user-provided string. A value of `none` resets the setting.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), SettingsError> {
+        let value = value.trim();
+        let reset = value.eq_ignore_ascii_case("none");
+
+        match key {
+            "prefix" => {
+                if reset {
+                    self.prefix = None;
+                } else if value.is_empty() || value.len() > 5 || value.contains(char::is_whitespace)
+                {
+                    return Err(SettingsError::InvalidValue(
+                        "Prefixes must be between 1 and 5 characters long and cannot contain spaces.",
+                    ));
+                } else {
+                    self.prefix = Some(value.to_string());
                 }
             }
-            None => match timeout {
-                Some(duration) => {
-                    message.channel_id.say(
-                        ctx,
-                        format!("You did not add a reaction in time. Please react within {} seconds.", duration.as_secs()),
-                    ).await?;
-                    Err(EmbedError::TimedOut)
+            "activity_channel" => {
+                if reset {
+                    self.activity_channel = None;
+                } else {
+                    let id = imp::parse_channel_id(value).ok_or(SettingsError::InvalidValue(
+                        "Please provide a valid channel mention or ID.",
+                    ))?;
+                    self.activity_channel = Some(id);
                 }
-                None => {
-                    message.channel_id.say(
-                        ctx,
-                        "Some error occurred with getting a reaction. Please contact Factorial about this."
-                    ).await?;
-                    Err(EmbedError::Other)
+            }
+            "roster_channel" => {
+                if reset {
+                    self.roster_channel = None;
+                } else {
+                    let id = imp::parse_channel_id(value).ok_or(SettingsError::InvalidValue(
+                        "Please provide a valid channel mention or ID.",
+                    ))?;
+                    self.roster_channel = Some(id);
                 }
-            },
+            }
+            "block_conflicts" => {
+                self.block_conflicts = match value.to_ascii_lowercase().as_str() {
+                    "on" | "true" | "yes" => true,
+                    "off" | "false" | "no" | "none" => false,
+                    _ => {
+                        return Err(SettingsError::InvalidValue(
+                            "Please use `on` or `off` for this setting.",
+                        ))
+                    }
+                };
+            }
+            "timeout" => {
+                if reset {
+                    self.timeout = None;
+                } else {
+                    let secs = value
+                        .parse::<u64>()
+                        .ok()
+                        .filter(|secs| (30..=900).contains(secs))
+                        .ok_or(SettingsError::InvalidValue(
+                            "The timeout must be a number of seconds between 30 and 900.",
+                        ))?;
+                    self.timeout = Some(Duration::from_secs(secs));
+                }
+            }
+            "command_channels" => {
+                if reset {
+                    self.command_channels.clear();
+                } else {
+                    let channels = value
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .filter(|s| !s.is_empty())
+                        .map(imp::parse_channel_id)
+                        .collect::<Option<HashSet<_>>>()
+                        .filter(|channels| !channels.is_empty())
+                        .ok_or(SettingsError::InvalidValue(
+                            "Please provide a list of valid channel mentions or IDs.",
+                        ))?;
+                    self.command_channels = channels;
+                }
+            }
+            _ => return Err(SettingsError::InvalidKey(Self::KEYS)),
+        }
+
+        Ok(())
+    }
+}
+
+/// Something a user can be told about an activity they're in.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Notification {
+    /// The activity is starting now.
+    Start,
+    /// The activity starts soon.
+    Reminder,
+}
+
+/// Where a user wants to be told about their activities.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Delivery {
+    /// A ping in the activity's channel.
+    Channel,
+    Dm,
+    Both,
+    Off,
+}
+
+impl Delivery {
+    pub fn pings_channel(self) -> bool {
+        matches!(self, Delivery::Channel | Delivery::Both)
+    }
+
+    pub fn sends_dm(self) -> bool {
+        matches!(self, Delivery::Dm | Delivery::Both)
+    }
+}
+
+impl FromStr for Delivery {
+    type Err = SettingsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "channel" => Ok(Delivery::Channel),
+            "dm" => Ok(Delivery::Dm),
+            "both" => Ok(Delivery::Both),
+            "none" | "off" => Ok(Delivery::Off),
+            _ => Err(SettingsError::InvalidValue(
+                "Please use `channel`, `dm`, `both` or `none` for this setting.",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Delivery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Delivery::Channel => "A ping in the activity's channel",
+            Delivery::Dm => "A DM",
+            Delivery::Both => "A ping in the activity's channel and a DM",
+            Delivery::Off => "None",
+        })
+    }
+}
+
+/// A time of day range, in a fixed UTC offset, when a user doesn't want to be notified. The range
+/// can wrap past midnight, like 23:00 to 07:00.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub offset: FixedOffset,
+}
+
+impl QuietHours {
+    /// Start times are read in UTC-5, so quiet hours use it too unless another offset is given.
+    pub const DEFAULT_OFFSET_SECS: i32 = -5 * 3600;
+
+    pub fn contains<Tz: TimeZone>(&self, at: &DateTime<Tz>) -> bool {
+        let time = at.with_timezone(&self.offset).time();
+
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+impl FromStr for QuietHours {
+    type Err = SettingsError;
+
+    /// Reads quiet hours like `23:00-07:00`, optionally followed by an offset like `utc+1`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const INVALID: SettingsError = SettingsError::InvalidValue(
+            "Please provide quiet hours like `23:00-07:00`, optionally followed by a UTC offset like `utc+1`. Times without an offset are in UTC-5.",
+        );
+
+        let mut parts = s.split_whitespace();
+        let (start, end) = parts
+            .next()
+            .and_then(|range| range.split_once('-'))
+            .ok_or(INVALID)?;
+
+        let parse_time = |time: &str| NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| INVALID);
+        let start = parse_time(start)?;
+        let end = parse_time(end)?;
+
+        let offset_secs = match parts.next() {
+            Some(offset) => parse_utc_offset(offset).ok_or(INVALID)?,
+            None => Self::DEFAULT_OFFSET_SECS,
+        };
+
+        if parts.next().is_some() || start == end {
+            return Err(INVALID);
+        }
+
+        Ok(Self {
+            start,
+            end,
+            offset: FixedOffset::east_opt(offset_secs).ok_or(INVALID)?,
+        })
+    }
+}
+
+impl fmt::Display for QuietHours {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} (UTC{})",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M"),
+            self.offset
+        )
+    }
+}
+
+/// Reads an offset like `utc+1`, `utc-5` or `utc+5:30` into seconds east of UTC.
+fn parse_utc_offset(s: &str) -> Option<i32> {
+    let s = s.to_ascii_lowercase();
+    let offset = s.strip_prefix("utc")?;
+
+    let (sign, offset) = match offset.split_at_checked(1)? {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return None,
+    };
+
+    let (hours, minutes) = match offset.split_once(':') {
+        Some((hours, minutes)) => (hours.parse::<i32>().ok()?, minutes.parse::<i32>().ok()?),
+        None => (offset.parse::<i32>().ok()?, 0),
+    };
+
+    if !(0..=14).contains(&hours) || !(0..60).contains(&minutes) {
+        return None;
+    }
+
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Settings that users choose for themselves. These apply in every guild.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UserSettings {
+    pub starts: Delivery,
+    pub reminders: Delivery,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl Default for UserSettings {
+    /// Users are pinged in the channel when their activities start, which is how the bot worked
+    /// before these settings existed.
+    fn default() -> Self {
+        Self {
+            starts: Delivery::Channel,
+            reminders: Delivery::Off,
+            quiet_hours: None,
+        }
+    }
+}
+
+impl UserSettings {
+    pub const KEYS: &'static [&'static str] = &["starts", "reminders", "quiet_hours"];
+
+    /// How the user wants to get `notification` at `at`. Nothing is sent during quiet hours.
+    pub fn delivery<Tz: TimeZone>(
+        &self,
+        notification: Notification,
+        at: &DateTime<Tz>,
+    ) -> Delivery {
+        if self
+            .quiet_hours
+            .is_some_and(|quiet_hours| quiet_hours.contains(at))
+        {
+            return Delivery::Off;
+        }
+
+        match notification {
+            Notification::Start => self.starts,
+            Notification::Reminder => self.reminders,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Result<String, SettingsError> {
+        let value = match key {
+            "starts" => self.starts.to_string(),
+            "reminders" => self.reminders.to_string(),
+            "quiet_hours" => match self.quiet_hours {
+                Some(quiet_hours) => quiet_hours.to_string(),
+                None => String::from("Not set"),
+            },
+            _ => return Err(SettingsError::InvalidKey(Self::KEYS)),
+        };
+
+        Ok(value)
+    }
+
+    /// Sets `key` from a user-provided string. For `quiet_hours`, a value of `none` removes them.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), SettingsError> {
+        let value = value.trim();
+
+        match key {
+            "starts" => self.starts = value.parse()?,
+            "reminders" => self.reminders = value.parse()?,
+            "quiet_hours" => {
+                self.quiet_hours = if value.eq_ignore_ascii_case("none") {
+                    None
+                } else {
+                    Some(value.parse()?)
+                };
+            }
+            _ => return Err(SettingsError::InvalidKey(Self::KEYS)),
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[non_exhaustive]
+pub enum SettingsError {
+    /// The key isn't one of the valid keys, which are listed in the error.
+    InvalidKey(&'static [&'static str]),
+    InvalidValue(&'static str),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::InvalidKey(keys) => write!(
+                f,
+                "Invalid setting. Valid settings are {}.",
+                keys.iter().map(|key| format!("`{}`", key)).join(", ")
+            ),
+            SettingsError::InvalidValue(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl error::Error for SettingsError {}
+
+#[non_exhaustive]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum ActivityError {
+    MemberListFull,
+    MemberAlreadyInList,
+    AlternateFull,
+    MemberAlreadyInAlternate,
+    /// The user is in neither the fireteam nor the alternates.
+    NotInActivity,
+    /// The activity doesn't have a role with that name.
+    InvalidRole,
+    /// Every spot for that role is taken.
+    RoleFull,
+}
+
+impl ActivityError {
+    /// The error as told to the user it's about, for commands that users run on themselves.
+    /// `Display` describes the user in the third person instead.
+    pub fn message_for_self(&self) -> String {
+        self.message("You are")
+    }
+
+    /// The error with `subject` ("You are", "That user is") as who it's about.
+    fn message(&self, subject: &str) -> String {
+        use ActivityError::*;
+
+        match self {
+            MemberListFull => "The fireteam for that activity is already full.".into(),
+            MemberAlreadyInList => {
+                format!("{} already in the fireteam for that activity.", subject)
+            }
+            AlternateFull => "The alternates for that activity are already full.".into(),
+            MemberAlreadyInAlternate => {
+                format!("{} already an alternate for that activity.", subject)
+            }
+            NotInActivity => format!("{} not in that activity.", subject),
+            InvalidRole => {
+                "That activity doesn't have that role. The roster lists its roles.".into()
+            }
+            RoleFull => "That role is already full for that activity.".into(),
+        }
+    }
+}
+
+impl fmt::Display for ActivityError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message("That user is"))
+    }
+}
+
+impl error::Error for ActivityError {}
+
+/// Where a user is on an activity's roster.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum RosterPosition {
+    Absent,
+    Member,
+    Alternate,
+}
+
+/// A change a user can make to their place on a roster.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum RosterChange {
+    /// Join the fireteam, moving out of the alternates if needed.
+    Join,
+    /// Join the alternates, moving out of the fireteam if needed.
+    JoinAlternate,
+    /// Leave the fireteam or the alternates, whichever the user is in.
+    Leave,
+}
+
+impl RosterPosition {
+    /// The position a user ends up in after `change`. The lists are only checked for space when
+    /// the user would be added to them, so moving between lists never needs a free slot in the
+    /// list being left.
+    pub fn apply(
+        self,
+        change: RosterChange,
+        members_full: bool,
+        alternates_full: bool,
+    ) -> Result<RosterPosition, ActivityError> {
+        use RosterChange::*;
+        use RosterPosition::*;
+
+        match (self, change) {
+            (Member, Join) => Err(ActivityError::MemberAlreadyInList),
+            (Absent, Join) | (Alternate, Join) if members_full => {
+                Err(ActivityError::MemberListFull)
+            }
+            (Absent, Join) | (Alternate, Join) => Ok(Member),
+            (Alternate, JoinAlternate) => Err(ActivityError::MemberAlreadyInAlternate),
+            (Absent, JoinAlternate) | (Member, JoinAlternate) if alternates_full => {
+                Err(ActivityError::AlternateFull)
+            }
+            (Absent, JoinAlternate) | (Member, JoinAlternate) => Ok(Alternate),
+            (Absent, Leave) => Err(ActivityError::NotInActivity),
+            (Member, Leave) | (Alternate, Leave) => Ok(Absent),
+        }
+    }
+}
+
+/// When an activity starts, along with the text the creator typed to describe that time.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct StartTime {
+    pub date_time: DateTime<FixedOffset>,
+    pub input: String,
+}
+
+impl StartTime {
+    /// Returns how long it is until the activity starts, or `None` if it has already started.
+    pub fn until(&self) -> Option<Duration> {
+        self.date_time
+            .signed_duration_since(Utc::now())
+            .to_std()
+            .ok()
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        self.date_time.timestamp()
+    }
+}
+
+impl fmt::Display for StartTime {
+    /// Formats the start time as Discord timestamps, which are shown in each user's own time
+    /// zone along with a relative "in 2 hours" style countdown.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "<t:{0}:F> (<t:{0}:R>)", self.timestamp())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Activity {
+    pub name: String,
+    pub description: String,
+    pub start: StartTime,
+    /// How long the activity is expected to last. Used to find scheduling conflicts.
+    pub duration: Option<Duration>,
+    pub id: u64,
+    pub size: u8,
+    pub creator: UserId,
+    pub embed_msg: Message,
+    pub cancel_token: ActivityToken,
+    pub members: HashSet<UserId>,
+    pub alternate: Vec<UserId>,
+    /// Named parts of the fireteam, each with its own number of spots. Empty for activities that
+    /// don't use roles.
+    pub roles: Vec<RoleSlot>,
+    /// The role that each member of the fireteam picked, if any.
+    pub member_roles: HashMap<UserId, String>,
+    /// Whether the fireteam gets a reminder before the activity starts. Each member still picks
+    /// how they're reminded with their own settings.
+    pub reminder: bool,
+}
+
+impl Activity {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new<S: ToString>(
+        name: S,
+        description: S,
+        start: StartTime,
+        id: u64,
+        size: u8,
+        creator: UserId,
+        embed_msg: Message,
+        cancel_token: ActivityToken,
+    ) -> Self {
+        let name = name.to_string();
+        let description = description.to_string();
+
+        Self {
+            name,
+            description,
+            start,
+            duration: None,
+            id,
+            size,
+            creator,
+            embed_msg,
+            cancel_token,
+            members: HashSet::with_capacity(size as usize),
+            alternate: Vec::with_capacity(size as usize),
+            roles: Vec::new(),
+            member_roles: HashMap::new(),
+            reminder: true,
+        }
+    }
+
+    /// The duration assumed for activities that don't have one set.
+    pub const DEFAULT_DURATION: Duration = Duration::from_secs(60 * 60);
+
+    pub fn end(&self) -> DateTime<FixedOffset> {
+        let duration = self.duration.unwrap_or(Self::DEFAULT_DURATION);
+
+        // Durations come from embeds.json in minutes, so they always fit in a chrono Duration.
+        self.start.date_time + chrono::Duration::from_std(duration).unwrap_or_default()
+    }
+
+    pub fn overlaps(&self, other: &Activity) -> bool {
+        self.start.date_time < other.end() && other.start.date_time < self.end()
+    }
+
+    pub fn position(&self, user: UserId) -> RosterPosition {
+        if self.members.contains(&user) {
+            RosterPosition::Member
+        } else if self.alternate.contains(&user) {
+            RosterPosition::Alternate
+        } else {
+            RosterPosition::Absent
+        }
+    }
+
+    /// Moves a user on the roster as described by `RosterPosition::apply`. The roster is left
+    /// unchanged if the move isn't allowed.
+    pub fn change_roster(
+        &mut self,
+        user: UserId,
+        change: RosterChange,
+    ) -> Result<RosterPosition, ActivityError> {
+        let size = self.size as usize;
+        let from = self.position(user);
+        let to = from.apply(
+            change,
+            self.members.len() >= size,
+            self.alternate.len() >= size,
+        )?;
+
+        match from {
+            RosterPosition::Member => {
+                self.members.remove(&user);
+                self.member_roles.remove(&user);
+            }
+            RosterPosition::Alternate => self.alternate.retain(|&other| other != user),
+            RosterPosition::Absent => (),
+        }
+
+        match to {
+            RosterPosition::Member => {
+                self.members.insert(user);
+            }
+            RosterPosition::Alternate => self.alternate.push(user),
+            RosterPosition::Absent => (),
+        }
+
+        Ok(to)
+    }
+
+    pub fn add_member(&mut self, member: UserId) -> Result<(), ActivityError> {
+        self.change_roster(member, RosterChange::Join).map(|_| ())
+    }
+
+    /// Finds a role by name, ignoring case.
+    pub fn role(&self, name: &str) -> Option<&RoleSlot> {
+        self.roles
+            .iter()
+            .find(|role| role.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The members of the fireteam who picked `role`.
+    pub fn role_members(&self, role: &str) -> Vec<UserId> {
+        let mut members = self
+            .member_roles
+            .iter()
+            .filter(|(_, member_role)| member_role.as_str() == role)
+            .map(|(&member, _)| member)
+            .collect::<Vec<_>>();
+
+        members.sort();
+        members
+    }
+
+    /// Adds a user to the fireteam in `role`, or without a role if it's `None`. Members who are
+    /// already in the fireteam can use this to switch roles.
+    pub fn join_role(&mut self, member: UserId, role: Option<&str>) -> Result<(), ActivityError> {
+        let role = match role {
+            Some(name) => {
+                let role = self.role(name).ok_or(ActivityError::InvalidRole)?;
+
+                if self.member_roles.get(&member) == Some(&role.name) {
+                    return Err(ActivityError::MemberAlreadyInList);
+                }
+
+                if self.role_members(&role.name).len() >= role.capacity as usize {
+                    return Err(ActivityError::RoleFull);
+                }
+
+                Some(role.name.clone())
+            }
+            None => None,
+        };
+
+        match (self.position(member), role) {
+            (RosterPosition::Member, Some(role)) => {
+                self.member_roles.insert(member, role);
+            }
+            (_, role) => {
+                self.add_member(member)?;
+
+                if let Some(role) = role {
+                    self.member_roles.insert(member, role);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn add_member_alt(&mut self, member: UserId) -> Result<(), ActivityError> {
+        self.change_roster(member, RosterChange::JoinAlternate)
+            .map(|_| ())
+    }
+
+    /// Removes a user from whichever list they're in.
+    pub fn leave(&mut self, member: UserId) -> Result<(), ActivityError> {
+        self.change_roster(member, RosterChange::Leave).map(|_| ())
+    }
+
+    /// The description to show in embeds. Discord rejects empty field values, and the
+    /// description is optional.
+    pub fn shown_description(&self) -> &str {
+        if self.description.trim().is_empty() {
+            "None"
+        } else {
+            &self.description
+        }
+    }
+
+    /// Builds the roster embed. `prefix` is the guild's command prefix, used in the joining
+    /// instructions.
+    pub fn as_create_embed(&self, color: u32, prefix: &str) -> CreateEmbed {
+        let mut embed = CreateEmbed::default();
+
+        embed
+            .color(color)
+            .title("Activity Roster")
+            .field("Activity:", &self.name, true)
+            .field("Time:", self.start.to_string(), true)
+            .field("Activity ID:", self.id, true)
+            .field("Description:", self.shown_description(), false);
+
+        if self.roles.is_empty() {
+            embed.field("Member List:", mention_string(self.members.iter()), false);
+        } else {
+            for role in self.roles.iter() {
+                let role_members = self.role_members(&role.name);
+
+                embed.field(
+                    format!("{} ({}/{}):", role.name, role_members.len(), role.capacity),
+                    mention_string(role_members.iter()),
+                    false,
+                );
+            }
+
+            let mut no_role = self
+                .members
+                .iter()
+                .filter(|member| !self.member_roles.contains_key(member))
+                .collect::<Vec<_>>();
+
+            if !no_role.is_empty() {
+                no_role.sort();
+                embed.field("No Role:", mention_string(no_role.into_iter()), false);
+            }
+        }
+
+        let joining = if self.roles.is_empty() {
+            format!(
+                "Use {1}activity join {0}, {1}activity alt {0}, or {1}activity leave {0} to \
+                 join, join as an alternate, or leave an activity.",
+                self.id, prefix
+            )
+        } else {
+            format!(
+                "Use {1}activity join {0} <role>, {1}activity alt {0}, or {1}activity leave {0} \
+                 to join in a role, join as an alternate, or leave an activity.",
+                self.id, prefix
+            )
+        };
+
+        embed
+            .field(
+                "Alternate Members:",
+                mention_string(self.alternate.iter()),
+                false,
+            )
+            .field("Joining And Leaving:", joining, false)
+            .field(
+                "Important Information:",
+                "Bingus will ping you in this channel when your activity is ready. \
+                 Please keep this channel unmuted.",
+                false,
+            )
+            .footer(|footer| {
+                footer.text("For command documentation, please see Bingus's GitHub page.")
+            });
+
+        embed
+    }
+}
+
+/// Lists users as comma separated mentions, or "None" if there aren't any.
+pub(crate) fn mention_string<'a, I: Iterator<Item = &'a UserId>>(users: I) -> String {
+    let mentions = users
+        .map(|&id| Mention::from(id).to_string())
+        .collect::<Vec<_>>();
+
+    if mentions.is_empty() {
+        String::from("None")
+    } else {
+        mentions.join(", ")
+    }
+}
+
+/// Narrows down the activities shown by `activity list`. Every filter that is set must match.
+#[derive(Clone, Debug, Default)]
+pub struct ActivityFilter {
+    /// Matches activities whose name contains this lowercase string.
+    pub name: Option<String>,
+    pub creator: Option<UserId>,
+    /// Matches activities that this user has joined, either as a member or an alternate.
+    pub member: Option<UserId>,
+    pub open_only: bool,
+}
+
+impl ActivityFilter {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.creator.is_none() && self.member.is_none() && !self.open_only
+    }
+
+    pub fn matches(&self, activity: &Activity) -> bool {
+        let name_matches = self
+            .name
+            .as_ref()
+            .is_none_or(|name| activity.name.to_lowercase().contains(name));
+
+        let creator_matches = self
+            .creator
+            .is_none_or(|creator| activity.creator == creator);
+
+        let member_matches = self.member.is_none_or(|member| {
+            activity.members.contains(&member) || activity.alternate.contains(&member)
+        });
+
+        let open_matches = !self.open_only || activity.members.len() < activity.size as usize;
+
+        name_matches && creator_matches && member_matches && open_matches
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EmbedError {
+    InvalidReaction,
+    RemovedReaction,
+    TimedOut,
+    /// An embed couldn't be found in `embeds.json`.
+    Missing(String),
+    Other,
+    Serenity(serenity::Error),
+}
+
+impl From<serenity::Error> for EmbedError {
+    fn from(e: serenity::Error) -> Self {
+        Self::Serenity(e)
+    }
+}
+
+impl fmt::Display for EmbedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use EmbedError::*;
+
+        let display = match self {
+            InvalidReaction => "Invalid reaction".into(),
+            RemovedReaction => "Removed reaction".into(),
+            TimedOut => "Timed out".into(),
+            Missing(name) => format!("Missing embed `{}`", name),
+            Other => "Other".into(),
+            Serenity(e) => format!("Serenity error: {}", e),
+        };
+
+        write!(f, "{}", display)
+    }
+}
+
+impl error::Error for EmbedError {}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EmbedWithMeta {
+    pub embed: Embed,
+    pub meta: Option<Vec<SelectionInfo>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SelectionInfo {
+    pub name: String,
+    pub kind: RosterKind,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum RosterKind {
+    SelectNext(String),
+    Finished {
+        activity_name: String,
+        size: u8,
+        /// How long the activity usually takes, in minutes.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration: Option<u64>,
+        /// Named parts of the fireteam, like a raid's sword bearers.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        roles: Vec<RoleSlot>,
+    },
+}
+
+/// Whether a user showed up to an activity.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Attendance {
+    Present,
+    Absent,
+}
+
+impl FromStr for Attendance {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "present" => Ok(Attendance::Present),
+            "absent" => Ok(Attendance::Absent),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Attendance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Attendance::Present => "present",
+            Attendance::Absent => "absent",
+        })
+    }
+}
+
+/// The final roster of an activity that has started, and who showed up to it.
+#[derive(Clone, Debug)]
+pub struct ArchivedActivity {
+    pub id: u64,
+    pub name: String,
+    pub start: StartTime,
+    pub creator: UserId,
+    /// The main fireteam, sorted by ID.
+    pub members: Vec<UserId>,
+    pub alternates: Vec<UserId>,
+    pub member_roles: HashMap<UserId, String>,
+    /// Set by admins after the activity. Users who haven't been marked aren't counted either way.
+    pub attendance: HashMap<UserId, Attendance>,
+}
+
+impl ArchivedActivity {
+    pub fn from_activity(activity: &Activity) -> Self {
+        Self {
+            id: activity.id,
+            name: activity.name.clone(),
+            start: activity.start.clone(),
+            creator: activity.creator,
+            members: activity.members.iter().copied().sorted().collect(),
+            alternates: activity.alternate.clone(),
+            member_roles: activity.member_roles.clone(),
+            attendance: HashMap::new(),
+        }
+    }
+
+    /// Marks whether a member or alternate showed up.
+    pub fn mark(&mut self, user: UserId, attendance: Attendance) -> Result<(), ActivityError> {
+        if !self.members.contains(&user) && !self.alternates.contains(&user) {
+            return Err(ActivityError::NotInActivity);
+        }
+
+        self.attendance.insert(user, attendance);
+        Ok(())
+    }
+
+    pub fn as_attendance_embed(&self, color: u32) -> CreateEmbed {
+        let mut embed = CreateEmbed::default();
+
+        let describe = |users: &[UserId]| {
+            if users.is_empty() {
+                return String::from("None");
+            }
+
+            users
+                .iter()
+                .map(|user| {
+                    let role = match self.member_roles.get(user) {
+                        Some(role) => format!(" ({})", role),
+                        None => String::new(),
+                    };
+                    let attendance = match self.attendance.get(user) {
+                        Some(Attendance::Present) => "✅ ",
+                        Some(Attendance::Absent) => "❌ ",
+                        None => "❔ ",
+                    };
+
+                    format!("{}{}{}", attendance, Mention::from(*user), role)
+                })
+                .join("\n")
+        };
+
+        embed
+            .color(color)
+            .title(format!("{} (ID: {})", self.name, self.id))
+            .description(format!("Started {}", self.start))
+            .field("Fireteam:", describe(&self.members), false)
+            .field("Alternates:", describe(&self.alternates), false)
+            .footer(|footer| footer.text("✅ present, ❌ absent, ❔ not marked"));
+
+        embed
+    }
+}
+
+/// How a user took part in a guild's started activities.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct UserStats {
+    /// Activities they were in the main fireteam of when it started, plus the ones they were an
+    /// alternate for and had their attendance marked.
+    pub joined: usize,
+    pub attended: usize,
+    pub no_shows: usize,
+    pub hosted: usize,
+}
+
+impl UserStats {
+    /// The share of their marked activities that they showed up to, or `None` if none of their
+    /// activities were marked.
+    pub fn reliability(&self) -> Option<f64> {
+        let marked = self.attended + self.no_shows;
+
+        if marked == 0 {
+            None
+        } else {
+            Some(self.attended as f64 / marked as f64)
+        }
+    }
+}
+
+/// An activity that can be picked with the setup embeds in `embeds.json`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KnownActivity {
+    pub name: String,
+    pub size: u8,
+    pub duration: Option<Duration>,
+    pub roles: Vec<RoleSlot>,
+}
+
+impl KnownActivity {
+    /// Lists every activity that the setup embeds end in, ordered by name.
+    pub fn from_embed_map(embed_map: &HashMap<String, EmbedWithMeta>) -> Vec<Self> {
+        embed_map
+            .values()
+            .flat_map(|embed_with_meta| embed_with_meta.meta.iter().flatten())
+            .filter_map(|selection| match &selection.kind {
+                RosterKind::Finished {
+                    activity_name,
+                    size,
+                    duration,
+                    roles,
+                } => Some(Self {
+                    name: activity_name.clone(),
+                    size: *size,
+                    duration: duration.map(|minutes| Duration::from_secs(minutes * 60)),
+                    roles: roles.clone(),
+                }),
+                RosterKind::SelectNext(_) => None,
+            })
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+            .dedup_by(|a, b| a.name == b.name)
+            .collect()
+    }
+
+    /// The name without a trailing part in parentheses, like a raid's difficulty.
+    pub fn base_name(&self) -> &str {
+        match self.name.rfind(" (") {
+            Some(idx) if self.name.ends_with(')') => &self.name[..idx],
+            _ => &self.name,
+        }
+    }
+}
+
+/// A named part of a fireteam with its own number of spots.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
+pub struct RoleSlot {
+    pub name: String,
+    pub capacity: u8,
+}
+
+/// The parts of an activity that can be reused to create the same kind of activity again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActivityTemplate {
+    pub name: String,
+    pub activity_name: String,
+    pub size: u8,
+    pub description: String,
+    pub duration: Option<Duration>,
+    pub roles: Vec<RoleSlot>,
+    /// Whether activities created from the template send a reminder before they start.
+    pub reminder: bool,
+    /// The user who saved the template, or `None` if they asked to be forgotten. Templates
+    /// without a creator can only be changed by admins.
+    pub creator: Option<UserId>,
+}
+
+impl ActivityTemplate {
+    pub fn from_activity<S: ToString>(name: S, activity: &Activity, creator: UserId) -> Self {
+        Self {
+            name: name.to_string(),
+            activity_name: activity.name.clone(),
+            size: activity.size,
+            description: activity.description.clone(),
+            duration: activity.duration,
+            roles: activity.roles.clone(),
+            reminder: activity.reminder,
+            creator: Some(creator),
+        }
+    }
+
+    /// Creates an activity from the template. The ID is assigned when the activity is
+    /// registered.
+    pub fn to_activity(&self, start: StartTime, creator: UserId, embed_msg: Message) -> Activity {
+        let mut activity = Activity::new(
+            self.activity_name.as_str(),
+            self.description.as_str(),
+            start,
+            0,
+            self.size,
+            creator,
+            embed_msg,
+            ActivityToken::new(),
+        );
+
+        activity.duration = self.duration;
+        activity.roles = self.roles.clone();
+        activity.reminder = self.reminder;
+        activity
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RosterData {
+    pub activity_name: String,
+    pub size: u8,
+    pub duration: Option<Duration>,
+    pub roles: Vec<RoleSlot>,
+    pub message: Message,
+}
+
+#[derive(Debug)]
+pub struct MarkovInfo {
+    id: UserId,
+    chain: Chain<String>,
+}
+
+impl MarkovInfo {
+    pub fn new(id: UserId, order: usize) -> Self {
+        Self {
+            id,
+            chain: Chain::of_order(order),
+        }
+    }
+
+    /// The user whose messages this chain was built from.
+    pub fn user(&self) -> UserId {
+        self.id
+    }
+
+    pub fn feed_str(&mut self, s: &str) {
+        self.chain.feed_str(s);
+    }
+
+    pub fn gen_string(&self) -> String {
+        self.chain.generate_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::testing;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    const ALICE: UserId = UserId(3);
+    const BOB: UserId = UserId(4);
+    const CAROL: UserId = UserId(5);
+
+    fn activity(size: u8) -> Activity {
+        let start = imp::parse_date_time("12/25/2099 8:00 pm").unwrap();
+
+        Activity::new(
+            "Vault of Glass",
+            "",
+            start,
+            0,
+            size,
+            ALICE,
+            testing::message(100, ChannelId(10), testing::BOT, ""),
+            ActivityToken::new(),
+        )
+    }
+
+    #[test]
+    fn members_are_limited_to_the_activity_size() {
+        let mut activity = activity(2);
+
+        activity.add_member(ALICE).unwrap();
+        assert!(matches!(
+            activity.add_member(ALICE),
+            Err(ActivityError::MemberAlreadyInList)
+        ));
+
+        activity.add_member(BOB).unwrap();
+        assert!(matches!(
+            activity.add_member(CAROL),
+            Err(ActivityError::MemberListFull)
+        ));
+        assert_eq!(activity.members.len(), 2);
+    }
+
+    #[test]
+    fn empty_descriptions_are_shown_as_none() {
+        let mut activity = activity(2);
+        assert_eq!(activity.shown_description(), "None");
+
+        activity.description = String::from("Flawless run");
+        assert_eq!(activity.shown_description(), "Flawless run");
+    }
+
+    #[test]
+    fn rosters_use_the_guild_prefix() {
+        let joining = |activity: &Activity| {
+            testing::embed_json(activity.as_create_embed(0x212121, "!"))["fields"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|field| field["name"] == "Joining And Leaving:")
+                .unwrap()["value"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+
+        let mut activity = activity(6);
+        assert!(joining(&activity)
+            .starts_with("Use !activity join 0, !activity alt 0, or !activity leave 0"));
+
+        activity.roles = vec![RoleSlot {
+            name: String::from("Sword"),
+            capacity: 2,
+        }];
+        assert!(joining(&activity).starts_with("Use !activity join 0 <role>, !activity alt 0"));
+        assert!(!joining(&activity).contains('~'));
+    }
+
+    #[test]
+    fn alternates_are_limited_to_the_activity_size() {
+        let mut activity = activity(1);
+
+        activity.add_member_alt(ALICE).unwrap();
+        assert!(matches!(
+            activity.add_member_alt(BOB),
+            Err(ActivityError::AlternateFull)
+        ));
+        assert_eq!(activity.alternate, vec![ALICE]);
+    }
+
+    #[test]
+    fn joining_moves_users_between_lists() {
+        let mut activity = activity(2);
+
+        activity.add_member_alt(ALICE).unwrap();
+        activity.add_member(ALICE).unwrap();
+        assert!(activity.members.contains(&ALICE));
+        assert!(activity.alternate.is_empty());
+
+        activity.add_member_alt(ALICE).unwrap();
+        assert!(!activity.members.contains(&ALICE));
+        assert_eq!(activity.alternate, vec![ALICE]);
+    }
+
+    #[test]
+    fn stats_add_up_attendance_and_hosting() {
+        let mut guild_data = GuildData::new(GuildId(1));
+
+        for (id, present) in [(0, true), (1, false)] {
+            let mut activity = activity(3);
+            activity.id = id;
+            activity.add_member(ALICE).unwrap();
+            activity.add_member(BOB).unwrap();
+            guild_data.archive(&activity);
+
+            let attendance = if present {
+                Attendance::Present
+            } else {
+                Attendance::Absent
+            };
+            guild_data
+                .archived_mut(id)
+                .unwrap()
+                .mark(BOB, attendance)
+                .unwrap();
+        }
+
+        let stats = guild_data.stats();
+        assert_eq!(
+            stats[&ALICE],
+            UserStats {
+                joined: 2,
+                attended: 0,
+                no_shows: 0,
+                hosted: 2,
+            }
+        );
+        assert_eq!(stats[&ALICE].reliability(), None);
+        assert_eq!(stats[&BOB].reliability(), Some(0.5));
+        assert!(!stats.contains_key(&CAROL));
+
+        assert_eq!(guild_data.leaderboard(10), vec![(BOB, stats[&BOB])]);
+
+        assert_eq!(guild_data.forget_history(BOB), 2);
+        assert!(!guild_data.stats().contains_key(&BOB));
+    }
+
+    #[test]
+    fn forgotten_users_are_removed_from_their_templates() {
+        let mut guild_data = GuildData::new(GuildId(1));
+        guild_data.save_template(ActivityTemplate::from_activity("vog", &activity(6), ALICE));
+        guild_data.save_template(ActivityTemplate::from_activity("dsc", &activity(6), BOB));
+
+        assert_eq!(guild_data.forget_templates(ALICE), 1);
+        assert_eq!(guild_data.template("vog").unwrap().creator, None);
+        assert_eq!(guild_data.template("dsc").unwrap().creator, Some(BOB));
+    }
+
+    #[test]
+    fn activity_errors_name_who_they_are_about() {
+        let e = ActivityError::NotInActivity;
+
+        assert_eq!(e.message_for_self(), "You are not in that activity.");
+        assert_eq!(e.to_string(), "That user is not in that activity.");
+    }
+
+    #[test]
+    fn marked_alternates_count_as_joined() {
+        let mut guild_data = GuildData::new(GuildId(1));
+
+        let mut activity = activity(3);
+        activity.add_member(ALICE).unwrap();
+        activity.add_member_alt(BOB).unwrap();
+        activity.add_member_alt(CAROL).unwrap();
+        guild_data.archive(&activity);
+
+        guild_data
+            .archived_mut(0)
+            .unwrap()
+            .mark(BOB, Attendance::Present)
+            .unwrap();
+
+        let stats = guild_data.stats();
+        assert_eq!((stats[&BOB].joined, stats[&BOB].attended), (1, 1));
+        assert!(!stats.contains_key(&CAROL));
+    }
+
+    #[test]
+    fn history_keeps_the_most_recent_activities() {
+        let mut guild_data = GuildData::new(GuildId(1));
+        let mut activity = activity(1);
+
+        for id in 0..GuildData::HISTORY_LIMIT as u64 + 5 {
+            activity.id = id;
+            guild_data.archive(&activity);
+        }
+
+        assert_eq!(guild_data.history().len(), GuildData::HISTORY_LIMIT);
+        assert_eq!(guild_data.history()[0].id, 5);
+        assert!(guild_data.archived_mut(4).is_none());
+    }
+
+    #[test]
+    fn alternates_stay_alternates_when_the_fireteam_is_full() {
+        let mut activity = activity(1);
+
+        activity.add_member(BOB).unwrap();
+        activity.add_member_alt(ALICE).unwrap();
+
+        assert!(matches!(
+            activity.add_member(ALICE),
+            Err(ActivityError::MemberListFull)
+        ));
+        assert_eq!(activity.alternate, vec![ALICE]);
+        assert!(!activity.members.contains(&ALICE));
+    }
+
+    #[test]
+    fn duplicates_are_reported_before_full_lists() {
+        let mut activity = activity(1);
+
+        activity.add_member(ALICE).unwrap();
+        assert!(matches!(
+            activity.add_member(ALICE),
+            Err(ActivityError::MemberAlreadyInList)
+        ));
+
+        activity.add_member_alt(BOB).unwrap();
+        assert!(matches!(
+            activity.add_member_alt(BOB),
+            Err(ActivityError::MemberAlreadyInAlternate)
+        ));
+    }
+
+    #[test]
+    fn leaving_removes_users_from_either_list() {
+        let mut activity = activity(2);
+
+        assert!(matches!(
+            activity.leave(ALICE),
+            Err(ActivityError::NotInActivity)
+        ));
+
+        activity.add_member(ALICE).unwrap();
+        activity.add_member_alt(BOB).unwrap();
+        activity.leave(ALICE).unwrap();
+        activity.leave(BOB).unwrap();
+
+        assert_eq!(activity.position(ALICE), RosterPosition::Absent);
+        assert_eq!(activity.position(BOB), RosterPosition::Absent);
+        assert!(activity.members.is_empty());
+        assert!(activity.alternate.is_empty());
+    }
+
+    #[test]
+    fn user_settings_are_set_from_strings() {
+        let mut settings = UserSettings::default();
+
+        settings.set("starts", "DM").unwrap();
+        settings.set("quiet_hours", "23:00-07:00").unwrap();
+        assert_eq!(settings.starts, Delivery::Dm);
+        assert_eq!(
+            settings.get("quiet_hours").unwrap(),
+            "23:00-07:00 (UTC-05:00)"
+        );
+
+        settings.set("quiet_hours", "none").unwrap();
+        assert_eq!(settings.quiet_hours, None);
+        assert!(matches!(
+            settings.set("reminders", "sometimes"),
+            Err(SettingsError::InvalidValue(_))
+        ));
+        assert_eq!(
+            settings.set("prefix", "!").unwrap_err().to_string(),
+            "Invalid setting. Valid settings are `starts`, `reminders`, `quiet_hours`."
+        );
+    }
+
+    #[test]
+    fn forgetting_a_user_only_removes_their_data() {
+        let mut guild_data = GuildData::new(GuildId(1));
+
+        guild_data
+            .messages_mut()
+            .insert(ALICE, vec![String::from("hi"), String::from("hello")]);
+        guild_data
+            .messages_mut()
+            .insert(BOB, vec![String::from("hey")]);
+        guild_data
+            .markov_mut()
+            .insert(String::from("alice"), MarkovInfo::new(ALICE, 1));
+        guild_data
+            .markov_mut()
+            .insert(String::from("bob"), MarkovInfo::new(BOB, 1));
+
+        assert_eq!(guild_data.forget_user(ALICE), (2, 1));
+        assert_eq!(guild_data.forget_user(ALICE), (0, 0));
+        assert!(guild_data.messages().contains_key(&BOB));
+        assert!(guild_data.markov().contains_key("bob"));
+    }
+
+    #[test]
+    fn transitions_only_check_the_list_being_joined() {
+        use RosterChange::*;
+        use RosterPosition::*;
+
+        assert_eq!(Member.apply(JoinAlternate, true, false), Ok(Alternate));
+        assert_eq!(Alternate.apply(Join, false, true), Ok(Member));
+        assert_eq!(
+            Member.apply(JoinAlternate, false, true),
+            Err(ActivityError::AlternateFull)
+        );
+        assert_eq!(
+            Alternate.apply(Join, true, false),
+            Err(ActivityError::MemberListFull)
+        );
+        assert_eq!(Member.apply(Leave, true, true), Ok(Absent));
+        assert_eq!(Alternate.apply(Leave, true, true), Ok(Absent));
+    }
+
+    fn roster_change() -> impl Strategy<Value = RosterChange> {
+        prop_oneof![
+            Just(RosterChange::Join),
+            Just(RosterChange::JoinAlternate),
+            Just(RosterChange::Leave),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn roster_invariants_hold_after_any_changes(
+            size in 0u8..4,
+            changes in vec((3u64..8, roster_change()), 0..64),
+        ) {
+            let mut activity = activity(size);
+            let users = (3u64..8).map(UserId).collect::<Vec<_>>();
+
+            for (user, change) in changes {
+                let user = UserId(user);
+                let before = users
+                    .iter()
+                    .map(|&other| activity.position(other))
+                    .collect::<Vec<_>>();
+
+                match activity.change_roster(user, change) {
+                    Ok(to) => prop_assert_eq!(activity.position(user), to),
+                    Err(_) => prop_assert_eq!(
+                        activity.position(user),
+                        before[(user.0 - 3) as usize]
+                    ),
+                }
+
+                // Nobody else is moved by someone else's change.
+                for (idx, &other) in users.iter().enumerate() {
+                    if other != user {
+                        prop_assert_eq!(activity.position(other), before[idx]);
+                    }
+                }
+
+                prop_assert!(activity.members.len() <= size as usize);
+                prop_assert!(activity.alternate.len() <= size as usize);
+                prop_assert!(activity
+                    .members
+                    .iter()
+                    .all(|member| !activity.alternate.contains(member)));
+                prop_assert_eq!(
+                    activity.alternate.iter().collect::<HashSet<_>>().len(),
+                    activity.alternate.len()
+                );
+            }
         }
     }
 }
@@ -0,0 +1,197 @@
+//! The Discord operations used by the interactive commands.
+//!
+//! Commands that walk a user through several steps, like `activity create`, talk to Discord
+//! through the `Discord` trait instead of calling serenity directly, so that the same code can be
+//! run against the in-memory fake in `testing` with scripted users.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::builder::CreateEmbed;
+use serenity::collector::ReactionAction;
+use serenity::model::channel::{Message, ReactionType};
+use serenity::model::id::{ChannelId, UserId};
+use serenity::prelude::Context;
+
+use crate::metrics;
+
+#[serenity::async_trait]
+pub trait Discord: Send + Sync {
+    async fn say(&self, channel: ChannelId, content: String) -> serenity::Result<Message>;
+
+    /// Replies to a message. Unlike `say`, this references the original message.
+    async fn reply(&self, msg: &Message, content: String) -> serenity::Result<Message>;
+
+    async fn send_embed(&self, channel: ChannelId, embed: CreateEmbed)
+        -> serenity::Result<Message>;
+
+    /// Replaces the embed of one of the bot's messages.
+    async fn edit_embed(&self, msg: &mut Message, embed: CreateEmbed) -> serenity::Result<()>;
+
+    async fn react(&self, msg: &Message, reaction: ReactionType) -> serenity::Result<()>;
+
+    async fn delete_reactions(&self, msg: &Message) -> serenity::Result<()>;
+
+    async fn delete(&self, msg: &Message) -> serenity::Result<()>;
+
+    /// Waits for the next message from `author` in `channel`. Returns `None` if nothing was sent
+    /// before the timeout.
+    async fn await_reply(
+        &self,
+        channel: ChannelId,
+        author: UserId,
+        timeout: Option<Duration>,
+    ) -> Option<Message>;
+
+    /// Waits for a reaction to `msg`, from `author` if one is given. Returns `None` if nobody
+    /// reacted before the timeout.
+    async fn await_reaction(
+        &self,
+        msg: &Message,
+        author: Option<UserId>,
+        timeout: Option<Duration>,
+    ) -> Option<Arc<ReactionAction>>;
+
+    /// Waits for either the next message from `author` in the channel of `msg` or a reaction
+    /// from `author` to `msg`, whichever comes first. Returns `None` if neither happened before
+    /// the timeout.
+    async fn await_reply_or_reaction(
+        &self,
+        msg: &Message,
+        author: UserId,
+        timeout: Option<Duration>,
+    ) -> Option<Input>;
+
+    /// The content of a message with mentions replaced by names, so that it can be repeated
+    /// without pinging anyone.
+    async fn content_safe(&self, msg: &Message) -> String;
+}
+
+/// A message or reaction from a user, for steps that accept either.
+#[derive(Clone, Debug)]
+pub enum Input {
+    Reply(Box<Message>),
+    Reaction(Arc<ReactionAction>),
+}
+
+/// Sends everything to Discord through a serenity `Context`.
+pub struct SerenityDiscord<'a> {
+    ctx: &'a Context,
+}
+
+impl<'a> SerenityDiscord<'a> {
+    pub fn new(ctx: &'a Context) -> Self {
+        Self { ctx }
+    }
+}
+
+#[serenity::async_trait]
+impl Discord for SerenityDiscord<'_> {
+    async fn say(&self, channel: ChannelId, content: String) -> serenity::Result<Message> {
+        record("say", channel.say(self.ctx, content).await)
+    }
+
+    async fn reply(&self, msg: &Message, content: String) -> serenity::Result<Message> {
+        record("reply", msg.reply(self.ctx, content).await)
+    }
+
+    async fn send_embed(
+        &self,
+        channel: ChannelId,
+        embed: CreateEmbed,
+    ) -> serenity::Result<Message> {
+        let result = channel
+            .send_message(self.ctx, |msg| msg.set_embed(embed))
+            .await;
+
+        record("send_embed", result)
+    }
+
+    async fn edit_embed(&self, msg: &mut Message, embed: CreateEmbed) -> serenity::Result<()> {
+        let result = msg
+            .edit(self.ctx, |edit| {
+                edit.embed(|edited_embed| {
+                    *edited_embed = embed;
+                    edited_embed
+                })
+            })
+            .await;
+
+        record("edit_embed", result)
+    }
+
+    async fn react(&self, msg: &Message, reaction: ReactionType) -> serenity::Result<()> {
+        record("react", msg.react(self.ctx, reaction).await.map(|_| ()))
+    }
+
+    async fn delete_reactions(&self, msg: &Message) -> serenity::Result<()> {
+        record("delete_reactions", msg.delete_reactions(self.ctx).await)
+    }
+
+    async fn delete(&self, msg: &Message) -> serenity::Result<()> {
+        record("delete", msg.delete(self.ctx).await)
+    }
+
+    async fn await_reply(
+        &self,
+        channel: ChannelId,
+        author: UserId,
+        timeout: Option<Duration>,
+    ) -> Option<Message> {
+        let mut collector = channel.await_reply(self.ctx).author_id(author);
+
+        if let Some(duration) = timeout {
+            collector = collector.timeout(duration);
+        }
+
+        collector.await.map(|msg| (*msg).clone())
+    }
+
+    async fn await_reaction(
+        &self,
+        msg: &Message,
+        author: Option<UserId>,
+        timeout: Option<Duration>,
+    ) -> Option<Arc<ReactionAction>> {
+        let mut collector = msg.await_reaction(self.ctx);
+
+        if let Some(id) = author {
+            collector = collector.author_id(id);
+        }
+
+        if let Some(duration) = timeout {
+            collector = collector.timeout(duration);
+        }
+
+        collector.await
+    }
+
+    async fn await_reply_or_reaction(
+        &self,
+        msg: &Message,
+        author: UserId,
+        timeout: Option<Duration>,
+    ) -> Option<Input> {
+        tokio::select! {
+            reply = self.await_reply(msg.channel_id, author, timeout) => {
+                reply.map(|reply| Input::Reply(Box::new(reply)))
+            }
+            reaction = self.await_reaction(msg, Some(author), timeout) => {
+                reaction.map(Input::Reaction)
+            }
+        }
+    }
+
+    async fn content_safe(&self, msg: &Message) -> String {
+        msg.content_safe(self.ctx).await
+    }
+}
+
+/// Counts failed requests in the metrics.
+fn record<T>(operation: &str, result: serenity::Result<T>) -> serenity::Result<T> {
+    if result.is_err() {
+        metrics::discord_failure(operation);
+    }
+
+    result
+}
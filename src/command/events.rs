@@ -0,0 +1,383 @@
+//! Activity events and the subscribers that react to them.
+//!
+//! The functions in `imp` that change activities only update `GuildData`, and then publish an
+//! `ActivityEvent`. Everything else that has to happen afterwards, like refreshing rosters,
+//! scheduling starts, reminding members and sending webhooks, is done by a `Subscriber`, so new
+//! side effects can be added by registering another subscriber instead of changing every command.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::model::id::{GuildId, UserId};
+use serenity::prelude::Context;
+use tracing::{error, info};
+
+use crate::command::data::{Activity, Notification};
+use crate::command::imp::{self, data_keys};
+use crate::command::notify;
+use crate::webhook::{WebhookEvent, Webhooks};
+
+/// Something that happened to an activity. Each event holds a copy of the activity as it was
+/// right after the change.
+#[derive(Clone, Debug)]
+pub enum ActivityEvent {
+    Created {
+        guild_id: GuildId,
+        activity: Activity,
+    },
+    Edited {
+        guild_id: GuildId,
+        activity: Activity,
+    },
+    Joined {
+        guild_id: GuildId,
+        activity: Activity,
+        user_id: UserId,
+        alternate: bool,
+    },
+    Left {
+        guild_id: GuildId,
+        activity: Activity,
+        user_id: UserId,
+    },
+    Started {
+        guild_id: GuildId,
+        activity: Activity,
+    },
+    Deleted {
+        guild_id: GuildId,
+        activity: Activity,
+    },
+}
+
+impl ActivityEvent {
+    pub fn guild_id(&self) -> GuildId {
+        use ActivityEvent::*;
+
+        match self {
+            Created { guild_id, .. }
+            | Edited { guild_id, .. }
+            | Joined { guild_id, .. }
+            | Left { guild_id, .. }
+            | Started { guild_id, .. }
+            | Deleted { guild_id, .. } => *guild_id,
+        }
+    }
+
+    pub fn activity(&self) -> &Activity {
+        use ActivityEvent::*;
+
+        match self {
+            Created { activity, .. }
+            | Edited { activity, .. }
+            | Joined { activity, .. }
+            | Left { activity, .. }
+            | Started { activity, .. }
+            | Deleted { activity, .. } => activity,
+        }
+    }
+}
+
+#[serenity::async_trait]
+pub trait Subscriber: Send + Sync {
+    /// Reacts to an event. This is called without any locks held.
+    async fn handle(&self, ctx: &Context, event: &ActivityEvent) -> anyhow::Result<()>;
+}
+
+/// Passes events to each of its subscribers in the order they were added. Cloning this is cheap.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Vec<Arc<dyn Subscriber>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A bus with the subscribers that every bot needs. Activities are only kept in memory, so
+    /// there is no persistence subscriber yet.
+    pub fn with_defaults() -> Self {
+        Self::new()
+            .subscribe(RosterSubscriber)
+            .subscribe(ScheduleSubscriber)
+            .subscribe(ReminderSubscriber)
+            .subscribe(LogSubscriber)
+    }
+
+    pub fn subscribe<S: Subscriber + 'static>(mut self, subscriber: S) -> Self {
+        self.subscribers.push(Arc::new(subscriber));
+        self
+    }
+
+    /// Sends an event to every subscriber. A subscriber failing doesn't stop the others from
+    /// seeing the event.
+    pub async fn publish(&self, ctx: &Context, event: &ActivityEvent) {
+        for subscriber in self.subscribers.iter() {
+            if let Err(e) = subscriber.handle(ctx, event).await {
+                let activity = event.activity();
+
+                error!(
+                    guild_id = %event.guild_id(),
+                    activity_id = activity.id,
+                    activity_name = %activity.name,
+                    error = %e,
+                    "Failed to handle activity event"
+                );
+            }
+        }
+    }
+}
+
+/// Publishes an event on the bus registered in the TypeMap. The TypeMap must not be locked by
+/// the caller.
+pub async fn publish(ctx: &Context, event: ActivityEvent) {
+    let bus = ctx
+        .data
+        .read()
+        .await
+        .get::<data_keys::GetEventBus>()
+        .cloned();
+
+    if let Some(bus) = bus {
+        bus.publish(ctx, &event).await;
+    }
+}
+
+/// Keeps roster messages and the roster board in line with the activities.
+pub struct RosterSubscriber;
+
+#[serenity::async_trait]
+impl Subscriber for RosterSubscriber {
+    async fn handle(&self, ctx: &Context, event: &ActivityEvent) -> anyhow::Result<()> {
+        let guild_id = event.guild_id();
+        let event_activity = event.activity();
+
+        // The roster is built from the current activity rather than the one in the event, in
+        // case it was changed again before this event was handled. The message ID is compared
+        // since activity IDs are reused. It's copied out so that the lock isn't held while the
+        // roster is edited.
+        let (current_activity, prefix) = {
+            let type_map = ctx.data.read().await;
+            let current_activity = type_map
+                .get::<data_keys::GetGuildData>()
+                .and_then(|guild_data_map| guild_data_map.get(&guild_id.0))
+                .and_then(|guild_data| guild_data.activity(event_activity.id))
+                .filter(|activity| activity.embed_msg.id == event_activity.embed_msg.id)
+                .cloned();
+
+            (
+                current_activity,
+                imp::resolve_prefix(&type_map, Some(guild_id)).unwrap_or_default(),
+            )
+        };
+
+        let roster_result = match (event, current_activity) {
+            (ActivityEvent::Deleted { activity, .. }, _) => activity.embed_msg.delete(ctx).await,
+            (ActivityEvent::Started { .. }, _) => Ok(()),
+            (_, Some(mut activity)) => {
+                let result = imp::refresh_roster(ctx, &mut activity, &prefix).await;
+
+                if result.is_ok() {
+                    save_roster_message(ctx, guild_id, activity).await;
+                }
+
+                result
+            }
+            (_, None) => Ok(()),
+        };
+
+        imp::update_roster_board(ctx, guild_id).await?;
+
+        Ok(roster_result?)
+    }
+}
+
+/// Stores the edited roster message of `activity`, unless the activity was removed while the
+/// roster was being edited.
+async fn save_roster_message(ctx: &Context, guild_id: GuildId, activity: Activity) {
+    let mut type_map = ctx.data.write().await;
+
+    if let Some(stored) = type_map
+        .get_mut::<data_keys::GetGuildData>()
+        .and_then(|guild_data_map| guild_data_map.get_mut(&guild_id.0))
+        .and_then(|guild_data| guild_data.activity_mut(activity.id))
+        .filter(|stored| stored.embed_msg.id == activity.embed_msg.id)
+    {
+        stored.embed_msg = activity.embed_msg;
+    }
+}
+
+/// Schedules activities to start when they are created or their start time changes.
+pub struct ScheduleSubscriber;
+
+#[serenity::async_trait]
+impl Subscriber for ScheduleSubscriber {
+    async fn handle(&self, ctx: &Context, event: &ActivityEvent) -> anyhow::Result<()> {
+        if let ActivityEvent::Created { guild_id, activity }
+        | ActivityEvent::Edited { guild_id, activity } = event
+        {
+            if let Some(until_start) = activity.start.until() {
+                imp::schedule_start(
+                    ctx,
+                    *guild_id,
+                    activity.id,
+                    until_start,
+                    activity.cancel_token.cancelled(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Tells the fireteam about their activity, following each member's settings: a reminder
+/// `REMINDER_LEAD` before it starts, and a notification once it starts. Activities created less
+/// than `REMINDER_LEAD` before their start don't get a reminder.
+pub struct ReminderSubscriber;
+
+#[serenity::async_trait]
+impl Subscriber for ReminderSubscriber {
+    async fn handle(&self, ctx: &Context, event: &ActivityEvent) -> anyhow::Result<()> {
+        match event {
+            ActivityEvent::Created { guild_id, activity }
+            | ActivityEvent::Edited { guild_id, activity } => {
+                if let Some(until_reminder) = activity
+                    .start
+                    .until()
+                    .and_then(|until_start| until_start.checked_sub(imp::REMINDER_LEAD))
+                {
+                    schedule_reminder(ctx, *guild_id, activity, until_reminder);
+                }
+
+                Ok(())
+            }
+            ActivityEvent::Started { guild_id, activity } => {
+                Ok(notify::notify_members(ctx, *guild_id, activity, Notification::Start).await?)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reminds the fireteam after `until_reminder`. Edits replace the activity's token and deletes
+/// and starts cancel it, so a cancelled token means this reminder is out of date.
+fn schedule_reminder(
+    ctx: &Context,
+    guild_id: GuildId,
+    activity: &Activity,
+    until_reminder: Duration,
+) {
+    let ctx = ctx.clone();
+    let activity_id = activity.id;
+    let token = activity.cancel_token.clone();
+
+    tokio::spawn(async move {
+        tokio::time::sleep(until_reminder).await;
+
+        if token.is_cancelled() {
+            return;
+        }
+
+        let activity = ctx
+            .data
+            .read()
+            .await
+            .get::<data_keys::GetGuildData>()
+            .and_then(|guild_map| guild_map.get(&guild_id.0))
+            .and_then(|guild_data| guild_data.activity(activity_id))
+            .cloned();
+
+        // The reminder can be turned off after it was scheduled.
+        if let Some(activity) = activity.filter(|activity| activity.reminder) {
+            if let Err(e) =
+                notify::notify_members(&ctx, guild_id, &activity, Notification::Reminder).await
+            {
+                error!(
+                    guild_id = %guild_id,
+                    activity_id,
+                    error = %e,
+                    "Failed to send activity reminders"
+                );
+            }
+        }
+    });
+}
+
+/// Logs each event.
+pub struct LogSubscriber;
+
+#[serenity::async_trait]
+impl Subscriber for LogSubscriber {
+    async fn handle(&self, _ctx: &Context, event: &ActivityEvent) -> anyhow::Result<()> {
+        let activity = event.activity();
+        let (kind, user_id, alternate) = match event {
+            ActivityEvent::Created { activity, .. } => ("created", Some(activity.creator), None),
+            ActivityEvent::Edited { .. } => ("edited", None, None),
+            ActivityEvent::Joined {
+                user_id, alternate, ..
+            } => ("joined", Some(*user_id), Some(*alternate)),
+            ActivityEvent::Left { user_id, .. } => ("left", Some(*user_id), None),
+            ActivityEvent::Started { .. } => ("started", None, None),
+            ActivityEvent::Deleted { .. } => ("deleted", None, None),
+        };
+
+        info!(
+            event = kind,
+            guild_id = %event.guild_id(),
+            activity_id = activity.id,
+            activity_name = %activity.name,
+            user_id = user_id.map(|id| id.0),
+            alternate,
+            "Activity {}",
+            kind
+        );
+
+        Ok(())
+    }
+}
+
+/// Sends events to the configured webhook.
+pub struct WebhookSubscriber(pub Webhooks);
+
+#[serenity::async_trait]
+impl Subscriber for WebhookSubscriber {
+    async fn handle(&self, _ctx: &Context, event: &ActivityEvent) -> anyhow::Result<()> {
+        let guild_id = event.guild_id();
+
+        match event {
+            ActivityEvent::Created { activity, .. } => {
+                self.0.notify(WebhookEvent::Created, guild_id, activity)
+            }
+            ActivityEvent::Edited { activity, .. } => {
+                self.0.notify(WebhookEvent::Edited, guild_id, activity)
+            }
+            ActivityEvent::Joined {
+                activity,
+                user_id,
+                alternate,
+                ..
+            } => self.0.notify_member(
+                WebhookEvent::Joined,
+                guild_id,
+                activity,
+                *user_id,
+                Some(*alternate),
+            ),
+            ActivityEvent::Left {
+                activity, user_id, ..
+            } => self
+                .0
+                .notify_member(WebhookEvent::Left, guild_id, activity, *user_id, None),
+            ActivityEvent::Started { activity, .. } => {
+                self.0.notify(WebhookEvent::Started, guild_id, activity)
+            }
+            ActivityEvent::Deleted { activity, .. } => {
+                self.0.notify(WebhookEvent::Deleted, guild_id, activity)
+            }
+        }
+
+        Ok(())
+    }
+}
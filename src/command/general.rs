@@ -1,12 +1,18 @@
-use crate::command::data::{Activity, GuildData};
-use crate::command::data::{ActivityError, MarkovInfo};
+use crate::command::data::MarkovInfo;
+use crate::command::data::{
+    mention_string, Activity, ActivityFilter, GuildData, GuildSettings, KnownActivity,
+    SettingsError, UserSettings, UserStats,
+};
+use crate::command::discord::SerenityDiscord;
+use crate::command::ical;
 use crate::command::imp;
 use crate::command::imp::data_keys;
-use crate::util::CancelActivity;
-use chrono::{FixedOffset, TimeZone, Utc};
-use itertools::Itertools;
+use crate::command::imp::{ActivityUpdateError, NewActivity};
+use crate::command::service::Actor;
+use crate::util::ActivityToken;
 use serenity::builder::CreateEmbed;
-use serenity::model::id::UserId;
+use serenity::http::AttachmentType;
+use serenity::model::id::{GuildId, UserId};
 use serenity::model::misc::Mention;
 use serenity::{
     framework::standard::{
@@ -16,16 +22,16 @@ use serenity::{
     model::channel::Message,
     prelude::Context,
 };
-use std::time::Duration;
+use std::borrow::Cow;
 
 #[group]
 #[description = "General, everyday commands."]
-#[commands(activity, markov)]
+#[commands(activity, markov, settings, stats)]
 pub struct General;
 
 #[command]
 #[description = "Create, edit, or delete an activity roster. Subcommands are create, edit, delete."]
-async fn activity(ctx: &Context, original_msg: &Message, args: Args) -> CommandResult {
+async fn activity(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
     let subcommand = match args.current() {
         Some(arg) => arg,
         None => {
@@ -38,18 +44,26 @@ async fn activity(ctx: &Context, original_msg: &Message, args: Args) -> CommandR
     };
 
     match subcommand {
-        "create" => activity_create(ctx, original_msg).await,
+        "quick" => activity_quick(ctx, original_msg, args).await,
+        "create" => match args.advance().current() {
+            Some("from") => activity_create_from(ctx, original_msg, args).await,
+            _ => activity_create(ctx, original_msg).await,
+        },
         "join" => activity_join(ctx, original_msg, args).await,
         "alt" => activity_alt(ctx, original_msg, args).await,
         "leave" => activity_leave(ctx, original_msg, args).await,
         "edit" => activity_edit(ctx, original_msg, args).await,
         "delete" => activity_delete(ctx, original_msg, args).await,
         "list" => activity_list(ctx, original_msg, args).await,
+        "mine" => activity_mine(ctx, original_msg).await,
+        "ical" => activity_ical(ctx, original_msg, args).await,
+        "template" => activity_template(ctx, original_msg, args).await,
+        "reminders" => activity_reminders(ctx, original_msg, args).await,
         _ => {
             imp::send_error_message(
                 ctx,
                 original_msg,
-                "Invalid subcommand. Valid subcommands are `create`, `join`, `alt`, `leave`, `edit`, `delete`, and `list`.",
+                "Invalid subcommand. Valid subcommands are `create`, `quick`, `join`, `alt`, `leave`, `edit`, `delete`, `list`, `mine`, `ical`, `template`, and `reminders`.",
             )
             .await?;
             Ok(())
@@ -58,7 +72,14 @@ async fn activity(ctx: &Context, original_msg: &Message, args: Args) -> CommandR
 }
 
 async fn activity_create(ctx: &Context, original_msg: &Message) -> CommandResult {
-    let guild_id = original_msg.guild_id;
+    // Activities belong to a guild, so there would be nowhere to keep one made in a DM.
+    let guild_id = match imp::command_guild(original_msg) {
+        Ok(id) => id,
+        Err(e) => {
+            imp::send_error_message(ctx, original_msg, e).await?;
+            return Ok(());
+        }
+    };
 
     let type_map = ctx.data.read().await;
 
@@ -73,223 +94,420 @@ async fn activity_create(ctx: &Context, original_msg: &Message) -> CommandResult
         }
     };
 
+    let settings = type_map
+        .get::<data_keys::GetGuildData>()
+        .and_then(|guild_map| guild_map.get(&guild_id.0))
+        .map(|guild_data| guild_data.settings().clone())
+        .unwrap_or_default();
+
     // Unlock read lock so that other contexts can use it.
     drop(type_map);
 
-    match embed_map.get("activity_roster_start") {
-        Some(embed_with_meta) => {
-            let mut data = embed_with_meta
-                .clone()
-                .send_embed_chain(
-                    ctx,
-                    &embed_map,
-                    original_msg.channel_id,
-                    Some(Duration::from_secs(120)),
-                    Some(original_msg.author.id),
-                )
+    let channel_id = settings.activity_channel.unwrap_or(original_msg.channel_id);
+
+    if channel_id != original_msg.channel_id {
+        original_msg
+            .channel_id
+            .say(
+                ctx,
+                format!(
+                    "Activities are created in {}. Please continue the setup there.",
+                    Mention::from(channel_id)
+                ),
+            )
+            .await?;
+    }
+
+    let new_activity = imp::prompt_new_activity(
+        &SerenityDiscord::new(ctx),
+        &embed_map,
+        "activity_roster_start",
+        channel_id,
+        original_msg.author.id,
+        settings.timeout(),
+    )
+    .await?;
+
+    let NewActivity {
+        roster: data,
+        start: start_time,
+        description,
+    } = match new_activity {
+        Some(new_activity) => new_activity,
+        None => return Ok(()),
+    };
+
+    if start_time.until().is_none() {
+        imp::send_error_message(
+            ctx,
+            &data.message,
+            "Invalid date and time. Please enter a valid date and time that is in the future.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    // The ID is assigned when the activity is registered.
+    let mut activity = Activity::new(
+        data.activity_name.to_string(),
+        description,
+        start_time,
+        0,
+        data.size,
+        original_msg.author.id,
+        data.message.clone(),
+        ActivityToken::new(),
+    );
+
+    activity.duration = data.duration;
+    activity.roles = data.roles.clone();
+
+    let actor = Actor::Member(original_msg.author.id);
+
+    imp::register_activity(ctx, guild_id, actor, activity).await?;
+
+    Ok(())
+}
+
+async fn activity_create_from(
+    ctx: &Context,
+    original_msg: &Message,
+    mut args: Args,
+) -> CommandResult {
+    let guild_id = match original_msg.guild_id {
+        Some(id) => id,
+        None => {
+            imp::send_error_message(ctx, original_msg, "This command is not supported in DMs.")
                 .await?;
+            return Ok(());
+        }
+    };
 
-            data.message.delete_reactions(ctx).await?;
+    let template_name = match args.advance().current() {
+        Some(name) => name.to_string(),
+        None => {
+            imp::send_error_message(
+                ctx,
+                original_msg,
+                "Please provide a template name, e.g. `activity create from vog 12/25/2099 8:00 pm`.",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
 
-            let time_embed = match embed_map.get("activity_roster_time") {
-                Some(embed) => embed,
-                None => {
-                    imp::send_error_message(ctx, &data.message, "An error has occurred getting embed `activity_roster_time`. Please contact Factorial about this.").await?;
-                    return Ok(());
-                }
-            };
+    args.advance();
 
-            data.message
-                .edit(ctx, |msg| {
-                    msg.embed(|embed| {
-                        *embed = imp::create_embed(&time_embed.embed);
-                        embed
-                    })
-                })
+    let start = match imp::parse_start(args.rest().trim()) {
+        Some(start) => start,
+        None => {
+            imp::send_error_message(
+                ctx,
+                original_msg,
+                "Please enter a valid date and time after the template name, like `tue 8pm` or `12/25/2099 8:00 pm`.",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let channel_id = {
+        let type_map = ctx.data.read().await;
+
+        type_map
+            .get::<data_keys::GetGuildData>()
+            .and_then(|guild_map| guild_map.get(&guild_id.0))
+            .and_then(|guild_data| guild_data.settings().activity_channel)
+            .unwrap_or(original_msg.channel_id)
+    };
+
+    let result = imp::create_from_template(
+        ctx,
+        guild_id,
+        original_msg.author.id,
+        channel_id,
+        &template_name,
+        start,
+    )
+    .await;
+
+    let error = match result {
+        Ok(_) => None,
+        Err(ActivityUpdateError::InvalidTemplate) => Some(format!(
+            "There's no template named `{}`. Use `activity template list` to see the saved templates.",
+            template_name
+        )),
+        Err(ActivityUpdateError::InvalidStart) => Some(String::from(
+            "Invalid date and time. Please enter a valid date and time that is in the future.",
+        )),
+        Err(ActivityUpdateError::Serenity(e)) => return Err(e.into()),
+        Err(e) => Some(format!("{}.", e)),
+    };
+
+    if let Some(msg) = error {
+        imp::send_error_message(ctx, original_msg, msg).await?;
+    }
+
+    Ok(())
+}
+
+async fn activity_quick(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
+    const USAGE: &str =
+        "Usage: `activity quick \"<activity name>\" <size> \"<date and time>\" [description]`, \
+         e.g. `activity quick \"Vault of Glass\" 6 \"tue 8pm\" Master run, bring relics`.";
+
+    let guild_id = match original_msg.guild_id {
+        Some(id) => id,
+        None => {
+            imp::send_error_message(ctx, original_msg, "This command is not supported in DMs.")
                 .await?;
+            return Ok(());
+        }
+    };
 
-            let (date_time, date_time_str) = loop {
-                let mut collector = data
-                    .message
-                    .channel_id
-                    .await_reply(ctx)
-                    .author_id(original_msg.author.id);
+    args.advance();
 
-                if let Some(duration) = data.timeout {
-                    collector = collector.timeout(duration);
-                }
+    let imp::QuickArgs {
+        name,
+        size,
+        start,
+        description,
+    } = match imp::parse_quick_args(args) {
+        Some(quick) => quick,
+        None => {
+            imp::send_error_message(ctx, original_msg, USAGE).await?;
+            return Ok(());
+        }
+    };
 
-                let time_message = match collector.await {
-                    Some(message) => message,
-                    None => {
-                        let error = match data.timeout {
-                            Some(duration) => format!("You did not send a reply in time. Please reply within {} minutes", duration.as_secs() / 60),
-                            None => "Some other error occurred getting a reply. Please contact Factorial about this.".into(),
-                        };
-
-                        imp::send_error_message(ctx, &data.message, error).await?;
-                        return Ok(());
-                    }
-                };
-
-                match imp::parse_date_time(&time_message.content) {
-                    Some((date_time, date_time_str)) => {
-                        time_message.delete(ctx).await?;
-                        break (date_time, date_time_str);
-                    }
-                    None => {
-                        imp::send_error_message(ctx, &time_message, "Please enter a valid date and time in the format `mm/dd/yyyy hh:mm am|pm`").await?;
-                    }
-                }
-            };
+    let start = match imp::parse_start(&start) {
+        Some(start) if start.until().is_some() => start,
+        _ => {
+            imp::send_error_message(
+                ctx,
+                original_msg,
+                "Invalid date and time. Please enter a date and time in the future, like `tue 8pm` or `12/25/2099 8:00 pm`.",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
 
-            data.message.delete_reactions(ctx).await?;
+    if description.len() > 1024 {
+        imp::send_error_message(
+            ctx,
+            original_msg,
+            "The description can be at most 1024 characters.",
+        )
+        .await?;
+        return Ok(());
+    }
 
-            let description_embed = match embed_map.get("activity_roster_description") {
-                Some(embed) => embed,
-                None => {
-                    imp::send_error_message(ctx, &data.message, "An error has occurred getting embed `activity_roster_description`. Please contact Factorial about this.").await?;
-                    return Ok(());
-                }
-            };
+    let (known, channel_id) = {
+        let type_map = ctx.data.read().await;
 
-            data.message
-                .edit(ctx, |msg| {
-                    msg.embed(|embed| {
-                        *embed = imp::create_embed(&description_embed.embed);
-                        embed
-                    })
-                })
+        let known = type_map
+            .get::<data_keys::GetEmbedMap>()
+            .map(KnownActivity::from_embed_map)
+            .unwrap_or_default();
+        let channel_id = type_map
+            .get::<data_keys::GetGuildData>()
+            .and_then(|guild_map| guild_map.get(&guild_id.0))
+            .and_then(|guild_data| guild_data.settings().activity_channel)
+            .unwrap_or(original_msg.channel_id);
+
+        (known, channel_id)
+    };
+
+    let known = match imp::resolve_known_activity(&known, &name) {
+        Ok(known) => known,
+        Err(e) => {
+            imp::send_error_message(ctx, original_msg, e).await?;
+            return Ok(());
+        }
+    };
+
+    if let Err(e) = imp::check_quick_size(&known, size) {
+        imp::send_error_message(ctx, original_msg, e).await?;
+        return Ok(());
+    }
+
+    let embed_msg = imp::post_placeholder_roster(ctx, channel_id, &known.name).await?;
+
+    // The ID is assigned when the activity is registered.
+    let mut activity = Activity::new(
+        known.name,
+        description,
+        start,
+        0,
+        size,
+        original_msg.author.id,
+        embed_msg,
+        ActivityToken::new(),
+    );
+
+    activity.duration = known.duration;
+    activity.roles = known.roles;
+
+    let actor = Actor::Member(original_msg.author.id);
+
+    match imp::register_activity(ctx, guild_id, actor, activity).await {
+        Ok(_) => Ok(()),
+        Err(ActivityUpdateError::Serenity(e)) => Err(e.into()),
+        Err(e) => {
+            imp::send_error_message(ctx, original_msg, format!("{}.", e)).await?;
+            Ok(())
+        }
+    }
+}
+
+async fn activity_template(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match original_msg.guild_id {
+        Some(id) => id,
+        None => {
+            imp::send_error_message(ctx, original_msg, "This command is not supported in DMs.")
                 .await?;
+            return Ok(());
+        }
+    };
 
-            let description = loop {
-                let mut collector = data
-                    .message
-                    .channel_id
-                    .await_reply(ctx)
-                    .author_id(original_msg.author.id);
+    let actor = Actor::Member(original_msg.author.id);
 
-                if let Some(duration) = data.timeout {
-                    collector = collector.timeout(duration);
+    let content = match args.advance().current() {
+        Some("save") => {
+            let activity_id = match args
+                .advance()
+                .current()
+                .and_then(|string| string.parse::<u64>().ok())
+            {
+                Some(id) => id,
+                None => {
+                    imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
+                    return Ok(());
                 }
+            };
+
+            let is_last = args.advance().remaining() == 1;
 
-                let description_message = match collector.await {
-                    Some(message) => message,
-                    None => {
-                        let error = match data.timeout {
-                            Some(duration) => format!("You did not send a reply in time. Please reply within {} minutes", duration.as_secs() / 60),
-                            None => "Some other error occurred getting a reply. Please contact Factorial about this.".into(),
-                        };
-
-                        imp::send_error_message(ctx, &data.message, error).await?;
-                        return Ok(());
-                    }
-                };
-
-                let safe_content = description_message.content_safe(ctx).await;
-
-                if safe_content.len() <= 1024 {
-                    description_message.delete(ctx).await?;
-                    break safe_content;
-                } else {
+            let name = match args.current() {
+                Some(name) if is_last => name.to_string(),
+                _ => {
                     imp::send_error_message(
                         ctx,
-                        &description_message,
-                        "Please enter a description that is less than or equal to 1024 characters.",
+                        original_msg,
+                        "Please provide a one-word template name after the activity ID.",
                     )
                     .await?;
+                    return Ok(());
                 }
             };
 
-            let date_time_now =
-                FixedOffset::west(3 * 3600).from_utc_datetime(&Utc::now().naive_utc());
-            let duration_until_start = match (date_time - date_time_now).to_std() {
-                Ok(duration) => duration,
-                Err(_) => {
+            let saved = imp::with_service(ctx, guild_id, |service| {
+                service.save_template(actor, activity_id, &name)
+            })
+            .await;
+
+            match saved {
+                Ok(template) => format!(
+                    "Saved template `{0}` for {1}. Use `activity create from {0} <date and time>` to create it.",
+                    template.name, template.activity_name
+                ),
+                Err(ActivityUpdateError::NotAllowed) => {
                     imp::send_error_message(
                         ctx,
-                        &data.message,
-                        "Invalid date and time. Please enter a valid date and time that is in the future.",
+                        original_msg,
+                        "Someone else already saved a template with that name.",
                     )
                     .await?;
                     return Ok(());
                 }
+                Err(e) => {
+                    imp::send_error_message(ctx, original_msg, format!("{}.", e)).await?;
+                    return Ok(());
+                }
+            }
+        }
+        Some("delete") => {
+            let name = match args.advance().current() {
+                Some(name) => name.to_string(),
+                None => {
+                    imp::send_error_message(ctx, original_msg, "Please provide a template name.")
+                        .await?;
+                    return Ok(());
+                }
             };
 
-            if let Some(guild_id_val) = guild_id {
-                let mut type_map = ctx.data.write().await;
-                let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-                let guild_data = guild_data_map
-                    .entry(guild_id_val.0)
-                    .or_insert_with(|| GuildData::new(guild_id_val));
-
-                let activity_id = guild_data.activity_id();
-
-                let (cancel_future, cancel_token) = CancelActivity::new_pair();
-                tokio::pin!(cancel_future);
-
-                let activity = Activity::new(
-                    data.activity_name.to_string(),
-                    description,
-                    date_time_str,
-                    activity_id,
-                    data.size,
-                    original_msg.author.id,
-                    data.message.clone(),
-                    cancel_token,
-                );
-
-                match guild_data.add_activity(activity) {
-                    Ok(_) => (),
-                    Err(_) => {
-                        imp::send_error_message(ctx, &data.message, "Error: that activity already exists. Please contact Factorial about this.").await?;
-                        return Ok(());
-                    }
-                };
-
-                let activity_embed = guild_data
-                    .activity(activity_id)
-                    .unwrap()
-                    .as_create_embed(0x212121);
-
-                data.message
-                    .edit(ctx, |msg| {
-                        msg.embed(|embed| {
-                            *embed = activity_embed;
-                            embed
-                        })
-                    })
-                    .await?;
-
-                drop(type_map);
-
-                let sleep = tokio::time::sleep(duration_until_start);
-                tokio::pin!(sleep);
+            let deleted = imp::with_service(ctx, guild_id, |service| {
+                service.delete_template(actor, &name)
+            })
+            .await;
 
-                tokio::select! {
-                    _ = &mut cancel_future => (),
-                    _ = &mut sleep => {
-                        let mut type_map = ctx.data.write().await;
-                        imp::start_activity(ctx, &mut type_map, guild_id_val, activity_id).await?;
-                    }
+            match deleted {
+                Ok(template) => format!("Deleted template `{}`.", template.name),
+                Err(ActivityUpdateError::NotAllowed) => {
+                    imp::send_error_message(ctx, original_msg, "You cannot delete that template.")
+                        .await?;
+                    return Ok(());
+                }
+                Err(e) => {
+                    imp::send_error_message(ctx, original_msg, format!("{}.", e)).await?;
+                    return Ok(());
                 }
             }
+        }
+        Some("list") => {
+            let type_map = ctx.data.read().await;
+
+            let templates = type_map
+                .get::<data_keys::GetGuildData>()
+                .and_then(|guild_map| guild_map.get(&guild_id.0))
+                .map(|guild_data| {
+                    guild_data
+                        .templates()
+                        .into_iter()
+                        .map(|template| {
+                            format!(
+                                "`{}` - {} ({} players){}",
+                                template.name,
+                                template.activity_name,
+                                template.size,
+                                template
+                                    .creator
+                                    .map(|creator| format!(", saved by {}", Mention::from(creator)))
+                                    .unwrap_or_default()
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
 
-            Ok(())
+            if templates.is_empty() {
+                String::from("No templates have been saved in this server.")
+            } else {
+                format!("Saved templates:\n{}", templates.join("\n"))
+            }
         }
-        None => {
-            original_msg.channel_id.say(ctx, "Embed not found.").await?;
-            Ok(())
+        _ => {
+            imp::send_error_message(
+                ctx,
+                original_msg,
+                "Invalid subcommand. Valid subcommands are `save`, `delete`, and `list`.",
+            )
+            .await?;
+            return Ok(());
         }
-    }
+    };
+
+    original_msg.channel_id.say(ctx, content).await?;
+
+    Ok(())
 }
 
 async fn activity_join(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
     let activity_id_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let activity_id = match activity_id_opt {
         Some(id) => id,
@@ -299,38 +517,69 @@ async fn activity_join(ctx: &Context, original_msg: &Message, mut args: Args) ->
         }
     };
 
-    let guild_id = match original_msg.guild_id {
-        Some(id) => id,
-        None => {
-            imp::send_error_message(ctx, original_msg, "This command is not supported in DMs.")
-                .await?;
-            return Ok(());
-        }
+    // In DMs, a server ID can come before the role to pick which server the activity is in.
+    args.advance();
+    let selector = match original_msg.guild_id {
+        None => args
+            .current()
+            .filter(|arg| imp::is_snowflake(arg))
+            .map(str::to_string),
+        Some(_) => None,
     };
 
-    let mut type_map = ctx.data.write().await;
+    if selector.is_some() {
+        args.advance();
+    }
 
-    let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-    let guild_data = guild_data_map
-        .entry(guild_id.0)
-        .or_insert_with(|| GuildData::new(guild_id));
+    let role = Some(args.rest().trim()).filter(|role| !role.is_empty());
 
-    let activity = match guild_data.activity_mut(activity_id) {
-        Some(activity) => activity,
-        None => {
-            imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
-            return Ok(());
-        }
-    };
+    let guild_id =
+        match imp::activity_guild(ctx, original_msg, activity_id, selector.as_deref()).await {
+            Ok(id) => id,
+            Err(e) => {
+                imp::send_error_message(ctx, original_msg, e).await?;
+                return Ok(());
+            }
+        };
+
+    let user_id = original_msg.author.id;
+    let result = imp::join_activity(
+        ctx,
+        guild_id,
+        Actor::Member(user_id),
+        activity_id,
+        user_id,
+        false,
+        role,
+    )
+    .await;
+
+    let error = match result {
+        Ok(conflicts) => {
+            if !conflicts.is_empty() {
+                original_msg
+                    .channel_id
+                    .say(
+                        ctx,
+                        format!(
+                            "{}, heads up! That activity overlaps with activities you're already in:\n{}",
+                            Mention::from(original_msg.author.id),
+                            conflicts
+                        ),
+                    )
+                    .await?;
+            }
 
-    let error = match activity.add_member(original_msg.author.id) {
-        Err(ActivityError::MemberAlreadyInList) => Some("You are already in that member list."),
-        Err(ActivityError::MemberListFull) => Some("The member list for that activity is already full."),
-        Err(ActivityError::MemberNotInAlternate) => {
-            Some("Attempted to move you to the member list, but you were not an alternate. Please try again.")
+            None
         }
-        Err(_) => Some("Some other error occurred adding you to the member list."),
-        Ok(()) => None,
+        Err(ActivityUpdateError::Conflict(conflicts)) => Some(format!(
+            "You can't join that activity because it overlaps with activities you're already in:\n{}",
+            conflicts
+        )),
+        Err(ActivityUpdateError::InvalidActivity) => Some("Invalid activity ID.".into()),
+        Err(ActivityUpdateError::Activity(e)) => Some(e.message_for_self()),
+        Err(ActivityUpdateError::Serenity(e)) => return Err(e.into()),
+        Err(_) => Some("Some other error occurred adding you to the member list.".into()),
     };
 
     if let Some(msg) = error {
@@ -338,19 +587,7 @@ async fn activity_join(ctx: &Context, original_msg: &Message, mut args: Args) ->
         return Ok(());
     }
 
-    let activity_embed = activity.as_create_embed(0x212121);
-
-    activity
-        .embed_msg
-        .edit(ctx, |msg| {
-            msg.embed(|embed| {
-                *embed = activity_embed;
-                embed
-            })
-        })
-        .await?;
-
-    original_msg.delete(ctx).await?;
+    imp::acknowledge(ctx, original_msg).await?;
 
     Ok(())
 }
@@ -359,8 +596,7 @@ async fn activity_alt(ctx: &Context, original_msg: &Message, mut args: Args) ->
     let activity_id_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let activity_id = match activity_id_opt {
         Some(id) => id,
@@ -370,39 +606,87 @@ async fn activity_alt(ctx: &Context, original_msg: &Message, mut args: Args) ->
         }
     };
 
-    let guild_id = match original_msg.guild_id {
-        Some(id) => id,
-        None => {
-            imp::send_error_message(ctx, original_msg, "This command is not supported in DMs.")
-                .await?;
+    // In DMs, a server ID can come after the activity ID to pick which server it's in.
+    let selector = args
+        .advance()
+        .current()
+        .filter(|arg| imp::is_snowflake(arg));
+
+    let guild_id = match imp::activity_guild(ctx, original_msg, activity_id, selector).await {
+        Ok(id) => id,
+        Err(e) => {
+            imp::send_error_message(ctx, original_msg, e).await?;
             return Ok(());
         }
     };
 
-    let mut type_map = ctx.data.write().await;
+    let user_id = original_msg.author.id;
+    let result = imp::join_activity(
+        ctx,
+        guild_id,
+        Actor::Member(user_id),
+        activity_id,
+        user_id,
+        true,
+        None,
+    )
+    .await;
+
+    let error = match result {
+        Ok(_) => None,
+        Err(ActivityUpdateError::InvalidActivity) => Some("Invalid activity ID.".into()),
+        Err(ActivityUpdateError::Activity(e)) => Some(e.message_for_self()),
+        Err(ActivityUpdateError::Serenity(e)) => return Err(e.into()),
+        Err(_) => Some("Some other error occurred adding you to the member list.".into()),
+    };
+
+    if let Some(msg) = error {
+        imp::send_error_message(ctx, original_msg, msg).await?;
+        return Ok(());
+    }
+
+    imp::acknowledge(ctx, original_msg).await?;
 
-    let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-    let guild_data = guild_data_map
-        .entry(guild_id.0)
-        .or_insert_with(|| GuildData::new(guild_id));
+    Ok(())
+}
+
+async fn activity_leave(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
+    let activity_id_opt = args
+        .advance()
+        .current()
+        .and_then(|string| string.parse::<u64>().ok());
 
-    let activity = match guild_data.activity_mut(activity_id) {
-        Some(activity) => activity,
+    let activity_id = match activity_id_opt {
+        Some(id) => id,
         None => {
             imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
             return Ok(());
         }
     };
 
-    let error = match activity.add_member_alt(original_msg.author.id) {
-        Err(ActivityError::MemberAlreadyInAlternate) => {
-            Some("You are already in that alternate member list.")
-        }
-        Err(ActivityError::AlternateFull) => {
-            Some("The alternate member list for that activity is already full.")
+    let selector = args
+        .advance()
+        .current()
+        .filter(|arg| imp::is_snowflake(arg));
+
+    let guild_id = match imp::activity_guild(ctx, original_msg, activity_id, selector).await {
+        Ok(id) => id,
+        Err(e) => {
+            imp::send_error_message(ctx, original_msg, e).await?;
+            return Ok(());
         }
-        Err(_) => Some("Some other error occurred adding you to the member list."),
+    };
+
+    let user_id = original_msg.author.id;
+    let result =
+        imp::leave_activity(ctx, guild_id, Actor::Member(user_id), activity_id, user_id).await;
+
+    let error = match result {
         Ok(()) => None,
+        Err(ActivityUpdateError::InvalidActivity) => Some("Invalid activity ID.".into()),
+        Err(ActivityUpdateError::Activity(e)) => Some(e.message_for_self()),
+        Err(ActivityUpdateError::Serenity(e)) => return Err(e.into()),
+        Err(_) => Some("Some other error occurred removing you from the member list.".into()),
     };
 
     if let Some(msg) = error {
@@ -410,29 +694,16 @@ async fn activity_alt(ctx: &Context, original_msg: &Message, mut args: Args) ->
         return Ok(());
     }
 
-    let activity_embed = activity.as_create_embed(0x212121);
-
-    activity
-        .embed_msg
-        .edit(ctx, |msg| {
-            msg.embed(|embed| {
-                *embed = activity_embed;
-                embed
-            })
-        })
-        .await?;
-
-    original_msg.delete(ctx).await?;
+    imp::acknowledge(ctx, original_msg).await?;
 
     Ok(())
 }
 
-async fn activity_leave(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
+async fn activity_edit(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
     let activity_id_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let activity_id = match activity_id_opt {
         Some(id) => id,
@@ -451,60 +722,75 @@ async fn activity_leave(ctx: &Context, original_msg: &Message, mut args: Args) -
         }
     };
 
-    let mut type_map = ctx.data.write().await;
+    let actor = Actor::Member(original_msg.author.id);
 
-    let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-    let guild_data = guild_data_map
-        .entry(guild_id.0)
-        .or_insert_with(|| GuildData::new(guild_id));
+    match args.advance().current() {
+        Some("reminder") => {
+            activity_edit_reminder(ctx, original_msg, guild_id, activity_id, args).await
+        }
+        _ => imp::prompt_activity_edit(ctx, original_msg, guild_id, actor, activity_id).await,
+    }
+}
 
-    let activity = match guild_data.activity_mut(activity_id) {
-        Some(activity) => activity,
-        None => {
-            imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
+async fn activity_edit_reminder(
+    ctx: &Context,
+    original_msg: &Message,
+    guild_id: GuildId,
+    activity_id: u64,
+    mut args: Args,
+) -> CommandResult {
+    let reminder = match args
+        .advance()
+        .current()
+        .map(|arg| arg.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("on") => true,
+        Some("off") => false,
+        _ => {
+            imp::send_error_message(
+                ctx,
+                original_msg,
+                "Please use `on` or `off` to choose whether the activity sends a reminder.",
+            )
+            .await?;
             return Ok(());
         }
     };
 
-    let error = match activity.remove_member(original_msg.author.id) {
-        Err(_) => match activity.remove_member_alt(original_msg.author.id) {
-            Err(ActivityError::MemberNotInAlternate) => {
-                Some("You are not in that activity's member list.")
-            }
-            Err(_) => Some("Some other error occurred removing you from the member list."),
-            Ok(()) => None,
-        },
-        Ok(()) => None,
-    };
-
-    if let Some(msg) = error {
-        imp::send_error_message(ctx, original_msg, msg).await?;
-        return Ok(());
-    }
+    let actor = Actor::Member(original_msg.author.id);
 
-    let activity_embed = activity.as_create_embed(0x212121);
+    match imp::set_activity_reminder(ctx, guild_id, actor, activity_id, reminder).await {
+        Ok(activity) => {
+            let state = if reminder { "on" } else { "off" };
 
-    activity
-        .embed_msg
-        .edit(ctx, |msg| {
-            msg.embed(|embed| {
-                *embed = activity_embed;
-                embed
-            })
-        })
-        .await?;
-
-    original_msg.delete(ctx).await?;
+            original_msg
+                .channel_id
+                .say(
+                    ctx,
+                    format!(
+                        "Turned the reminder for activity {} ({}) {}.",
+                        activity.id, activity.name, state
+                    ),
+                )
+                .await?;
+        }
+        Err(ActivityUpdateError::NotAllowed) => {
+            imp::send_error_message(ctx, original_msg, "You cannot edit that activity.").await?;
+        }
+        Err(_) => {
+            imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
+        }
+    }
 
     Ok(())
 }
 
-async fn activity_edit(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
+async fn activity_delete(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
     let activity_id_opt = args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+        .and_then(|string| string.parse::<u64>().ok());
 
     let activity_id = match activity_id_opt {
         Some(id) => id,
@@ -523,228 +809,371 @@ async fn activity_edit(ctx: &Context, original_msg: &Message, mut args: Args) ->
         }
     };
 
-    let mut type_map = ctx.data.write().await;
+    let actor = Actor::Member(original_msg.author.id);
 
-    let embed_map = match type_map.get::<data_keys::GetEmbedMap>() {
-        Some(map) => map.clone(),
-        None => {
+    match imp::delete_activity(ctx, guild_id, actor, activity_id).await {
+        Ok(activity) => {
             original_msg
                 .channel_id
-                .say(ctx, "Embed map was not registered.")
+                .say(
+                    ctx,
+                    format!("Deleted activity {}: {}.", activity.id, activity.name),
+                )
+                .await?;
+        }
+        Err(ActivityUpdateError::NotAllowed) => {
+            imp::send_error_message(ctx, original_msg, "You cannot delete that activity.").await?;
+        }
+        Err(ActivityUpdateError::Serenity(e)) => return Err(e.into()),
+        Err(_) => {
+            imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn activity_list(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match original_msg.guild_id {
+        Some(id) => id,
+        None => {
+            imp::send_error_message(ctx, original_msg, "This command is not supported in DMs.")
                 .await?;
             return Ok(());
         }
     };
 
-    let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-    let guild_data = guild_data_map
-        .entry(guild_id.0)
-        .or_insert_with(|| GuildData::new(guild_id));
+    let mut filter = ActivityFilter::default();
+    let mut page = 0;
+
+    for arg in args
+        .advance()
+        .quoted()
+        .iter::<String>()
+        .filter_map(Result::ok)
+    {
+        let lowercase = arg.to_lowercase();
+
+        if let Ok(p) = lowercase.parse::<usize>() {
+            page = p.saturating_sub(1);
+        } else if lowercase == "mine" {
+            filter.member = Some(original_msg.author.id);
+        } else if lowercase == "open" {
+            filter.open_only = true;
+        } else if let Some(name) = lowercase.strip_prefix("name:") {
+            filter.name = Some(name.trim().to_string());
+        } else if let Some(user) = lowercase.strip_prefix("by:") {
+            match imp::parse_user_id(user) {
+                Some(id) => filter.creator = Some(id),
+                None => {
+                    imp::send_error_message(
+                        ctx,
+                        original_msg,
+                        "Please provide a valid user mention or ID after `by:`.",
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            }
+        } else {
+            imp::send_error_message(
+                ctx,
+                original_msg,
+                format!(
+                    "Invalid filter `{}`. Valid filters are a page number, `mine`, `open`, \
+                     `name:<activity name>`, and `by:<user>`.",
+                    arg
+                ),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
+
+    let type_map = ctx.data.read().await;
+
+    let guild_data_map = match type_map.get::<data_keys::GetGuildData>() {
+        Some(map) => map,
+        None => {
+            imp::send_error_message(
+                ctx,
+                original_msg,
+                "No activities have been created in this server. Be the first to make one!",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let guild_data = match guild_data_map.get(&guild_id.0) {
+        Some(data) => data,
+        None => {
+            imp::send_error_message(
+                ctx,
+                original_msg,
+                "No activities have been created in this server. Be the first to make one!",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let timeout = guild_data.settings().timeout();
+
+    let activities = guild_data
+        .activities_by_start()
+        .into_iter()
+        .filter(|activity| filter.matches(activity))
+        .collect::<Vec<_>>();
+
+    if activities.is_empty() {
+        drop(type_map);
+
+        let error = if filter.is_empty() {
+            "No activities are currently scheduled in this server."
+        } else {
+            "No scheduled activities match those filters."
+        };
+
+        imp::send_error_message(ctx, original_msg, error).await?;
+        return Ok(());
+    }
+
+    let chunks = activities.chunks(3).collect::<Vec<_>>();
+
+    if page >= chunks.len() {
+        let page_count = chunks.len();
+        drop(type_map);
+
+        imp::send_error_message(
+            ctx,
+            original_msg,
+            format!(
+                "That page number is out of range. The maximum page number is {}",
+                page_count
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let pages = chunks
+        .iter()
+        .map(|activities| {
+            let mut list_embed = CreateEmbed::default();
+
+            for &activity in activities.iter() {
+                let members_string = mention_string(activity.members.iter());
+
+                let alternate_string = mention_string(activity.alternate.iter());
+
+                list_embed
+                    .field("Activity:", &activity.name, true)
+                    .field("Time:", activity.start.to_string(), true)
+                    .field("Activity ID:", activity.id, true)
+                    .field("Description:", activity.shown_description(), false)
+                    .field(
+                        format!(
+                            "Member List ({}/{}):",
+                            activity.members.len(),
+                            activity.size
+                        ),
+                        members_string,
+                        false,
+                    )
+                    .field("Alternate Members:", alternate_string, false);
+            }
+
+            list_embed.title("Activity List");
+            list_embed
+        })
+        .collect::<Vec<_>>();
+
+    drop(type_map);
+
+    imp::send_paginated(
+        ctx,
+        original_msg.channel_id,
+        original_msg.author.id,
+        pages,
+        page,
+        timeout,
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn activity_mine(ctx: &Context, original_msg: &Message) -> CommandResult {
+    let user_id = original_msg.author.id;
+    let type_map = ctx.data.read().await;
+    let guild_map = type_map.get::<data_keys::GetGuildData>();
+
+    let timeout = original_msg
+        .guild_id
+        .and_then(|guild_id| guild_map?.get(&guild_id.0))
+        .map_or(GuildSettings::DEFAULT_TIMEOUT, |guild_data| {
+            guild_data.settings().timeout()
+        });
+
+    // In a DM, the activities from every guild are listed.
+    let mut commitments = guild_map
+        .into_iter()
+        .flatten()
+        .filter(|(&guild_id, _)| {
+            original_msg
+                .guild_id
+                .is_none_or(|msg_guild_id| msg_guild_id.0 == guild_id)
+        })
+        .flat_map(|(&guild_id, guild_data)| {
+            guild_data
+                .activities_by_start()
+                .into_iter()
+                .filter_map(move |activity| {
+                    let (name, value) = commitment(guild_data, activity, user_id)?;
+                    let order = (activity.start.date_time, guild_id, activity.id);
+
+                    Some((order, GuildId(guild_id), name, value))
+                })
+        })
+        .collect::<Vec<_>>();
+
+    drop(type_map);
 
-    match guild_data.activity(activity_id) {
-        Some(activity) => {
-            let mut activity = activity.clone();
+    if commitments.is_empty() {
+        let message = if original_msg.guild_id.is_some() {
+            "You haven't joined any activities in this server."
+        } else {
+            "You haven't joined any activities."
+        };
 
-            drop(type_map);
+        imp::send_error_message(ctx, original_msg, message).await?;
+        return Ok(());
+    }
 
-            let timeout = Duration::from_secs(120);
+    commitments.sort_by_key(|(order, ..)| *order);
 
-            let creator_id = activity.creator;
+    let mut fields = Vec::with_capacity(commitments.len());
 
-            if original_msg.author.id == creator_id {
-                let time_embed = match embed_map.get("activity_roster_time") {
-                    Some(embed) => embed,
-                    None => {
-                        imp::send_error_message(ctx, original_msg, "An error has occurred getting embed `activity_roster_time`. Please contact Factorial about this.").await?;
-                        return Ok(());
-                    }
-                };
+    for (_, guild_id, name, mut value) in commitments {
+        if original_msg.guild_id.is_none() {
+            let guild_name = guild_id
+                .name(ctx)
+                .await
+                .unwrap_or_else(|| String::from("Unknown server"));
 
-                let mut embed_msg = original_msg
-                    .channel_id
-                    .send_message(ctx, |msg| {
-                        msg.embed(|embed| {
-                            *embed = imp::create_embed(&time_embed.embed);
-                            embed
-                        })
-                    })
-                    .await?;
+            value.push_str(&format!("\nServer: {} (ID: {})", guild_name, guild_id));
+        }
 
-                let (date_time, date_time_str) = loop {
-                    let collector = embed_msg
-                        .channel_id
-                        .await_reply(ctx)
-                        .author_id(original_msg.author.id)
-                        .timeout(timeout);
-
-                    let time_message = match collector.await {
-                        Some(message) => message,
-                        None => {
-                            imp::send_error_message(ctx, original_msg, format!("You did not send a reply in time. Please reply within {} minutes", timeout.as_secs() / 60)).await?;
-                            return Ok(());
-                        }
-                    };
-
-                    match imp::parse_date_time(&time_message.content) {
-                        Some((date_time, date_time_str)) => {
-                            time_message.delete(ctx).await?;
-                            break (date_time, date_time_str);
-                        }
-                        None => {
-                            imp::send_error_message(ctx, &time_message, "Please enter a valid date and time in the format `mm/dd/yyyy hh:mm am|pm`").await?;
-                        }
-                    }
-                };
-
-                let description_embed = match embed_map.get("activity_roster_description") {
-                    Some(embed) => embed,
-                    None => {
-                        imp::send_error_message(ctx, original_msg, "An error has occurred getting embed `activity_roster_description`. Please contact Factorial about this.").await?;
-                        return Ok(());
-                    }
-                };
-
-                embed_msg
-                    .edit(ctx, |msg| {
-                        msg.embed(|embed| {
-                            *embed = imp::create_embed(&description_embed.embed);
-                            embed
-                        })
-                    })
-                    .await?;
+        fields.push((name, value));
+    }
 
-                let description = loop {
-                    let collector = embed_msg
-                        .channel_id
-                        .await_reply(ctx)
-                        .author_id(original_msg.author.id)
-                        .timeout(timeout);
-
-                    let description_message = match collector.await {
-                        Some(message) => message,
-                        None => {
-                            imp::send_error_message(ctx, original_msg, format!("You did not send a reply in time. Please reply within {} minutes", timeout.as_secs() / 60)).await?;
-                            return Ok(());
-                        }
-                    };
-
-                    let safe_content = description_message.content_safe(ctx).await;
-
-                    if safe_content.len() <= 1024 {
-                        description_message.delete(ctx).await?;
-                        break safe_content;
-                    } else {
-                        imp::send_error_message(
-                            ctx,
-                            &description_message,
-                            "Please enter a description that is less than or equal to 1024 characters.",
-                        )
-                            .await?;
-                    }
-                };
-
-                let (cancel_future, cancel_token) = CancelActivity::new_pair();
-                tokio::pin!(cancel_future);
-
-                activity.date = date_time_str;
-                activity.description = description;
-                activity.cancel_token = cancel_token;
-
-                let activity_embed = activity.as_create_embed(0x212121);
-
-                activity
-                    .embed_msg
-                    .edit(ctx, |msg| {
-                        msg.embed(|embed| {
-                            *embed = activity_embed;
-                            embed
-                        })
-                    })
-                    .await?;
+    let pages = fields
+        .chunks(10)
+        .map(|chunk| {
+            let mut page_embed = CreateEmbed::default();
 
-                embed_msg.delete(ctx).await?;
+            page_embed.color(0x212121).title("Your Activities");
 
-                let mut type_map = ctx.data.write().await;
-                let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-                let guild_data = guild_data_map.entry(guild_id.0).or_default();
+            for (name, value) in chunk {
+                page_embed.field(name, value, false);
+            }
 
-                let old_activity = match guild_data.activity_mut(activity_id) {
-                    Some(activity) => activity,
-                    None => {
-                        imp::send_error_message(ctx, original_msg, "Could not get the old activity from GuildData. Please contact Factorial about this.").await?;
-                        return Ok(());
-                    }
-                };
+            page_embed
+        })
+        .collect::<Vec<_>>();
 
-                original_msg
-                    .channel_id
-                    .say(
-                        ctx,
-                        format!(
-                            "Activity {} ({}) updated successfully.",
-                            activity.id, activity.name
-                        ),
-                    )
-                    .await?;
+    imp::send_paginated(ctx, original_msg.channel_id, user_id, pages, 0, timeout).await?;
 
-                *old_activity = activity;
-
-                drop(type_map);
-
-                let date_time_now =
-                    FixedOffset::west(4 * 3600).from_utc_datetime(&Utc::now().naive_utc());
-                let duration_until_start = match (date_time - date_time_now).to_std() {
-                    Ok(duration) => duration,
-                    Err(_) => {
-                        imp::send_error_message(
-                            ctx,
-                            original_msg,
-                            "Invalid date and time. Please enter a valid date and time that is in the future.",
-                        )
-                            .await?;
-                        return Ok(());
-                    }
-                };
-
-                let sleep = tokio::time::sleep(duration_until_start);
-                tokio::pin!(sleep);
-
-                tokio::select! {
-                    _ = &mut cancel_future => (),
-                    _ = &mut sleep => {
-                        let mut type_map = ctx.data.write().await;
-                        imp::start_activity(ctx, &mut type_map, guild_id, activity_id).await?;
-                    }
-                }
-            } else {
-                imp::send_error_message(ctx, original_msg, "You cannot edit that activity.")
-                    .await?;
-            }
+    Ok(())
+}
 
-            Ok(())
-        }
-        None => {
-            imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
-            Ok(())
+/// Describes `user_id`'s place in an activity as an embed field, or `None` if they aren't in it.
+fn commitment(
+    guild_data: &GuildData,
+    activity: &Activity,
+    user_id: UserId,
+) -> Option<(String, String)> {
+    let role = if activity.members.contains(&user_id) {
+        match activity.member_roles.get(&user_id) {
+            Some(role) => format!("Member ({})", role),
+            None => String::from("Member"),
         }
+    } else if activity.alternate.contains(&user_id) {
+        String::from("Alternate")
+    } else {
+        return None;
+    };
+
+    let mut value = format!(
+        "{}\n{} ({}/{} members)",
+        activity.start,
+        role,
+        activity.members.len(),
+        activity.size
+    );
+
+    let conflicts = guild_data.conflicts(activity.id, user_id);
+
+    if activity.members.contains(&user_id) && !conflicts.is_empty() {
+        let conflict_ids = conflicts
+            .iter()
+            .map(|other| other.id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        value.push_str(&format!("\n⚠️ Overlaps with activity {}", conflict_ids));
     }
+
+    Some((format!("{} (ID: {})", activity.name, activity.id), value))
 }
 
-async fn activity_delete(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
-    let activity_id_opt = args
+/// The old way to get a DM when activities start, kept so that it still works. It's the same as
+/// setting `starts` to `both` or back to `channel`.
+async fn activity_reminders(
+    ctx: &Context,
+    original_msg: &Message,
+    mut args: Args,
+) -> CommandResult {
+    let (starts, reply) = match args
         .advance()
         .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
-
-    let activity_id = match activity_id_opt {
-        Some(id) => id,
-        None => {
-            imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
+        .map(|arg| arg.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("on") => ("both", "You'll get a DM when an activity you're in starts."),
+        Some("off") => (
+            "channel",
+            "You won't get a DM when your activities start anymore.",
+        ),
+        _ => {
+            imp::send_error_message(
+                ctx,
+                original_msg,
+                "Please use `on` or `off` to choose whether you get a DM when your activities start.",
+            )
+            .await?;
             return Ok(());
         }
     };
 
+    set_user_setting(ctx, original_msg.author.id, "starts", starts).await?;
+
+    original_msg
+        .channel_id
+        .say(
+            ctx,
+            format!(
+                "{} You can also use `settings set starts` for more choices.",
+                reply
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+async fn activity_ical(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
     let guild_id = match original_msg.guild_id {
         Some(id) => id,
         None => {
@@ -754,204 +1183,196 @@ async fn activity_delete(ctx: &Context, original_msg: &Message, mut args: Args)
         }
     };
 
-    let mut type_map = ctx.data.write().await;
-
-    let guild_data_map = type_map.entry::<data_keys::GetGuildData>().or_default();
-    let guild_data = guild_data_map
-        .entry(guild_id.0)
-        .or_insert_with(|| GuildData::new(guild_id));
-
-    let creator_id = match guild_data.activity(activity_id) {
-        Some(activity) => activity.creator,
+    let target = match args.advance().current() {
+        Some(target) => target.to_ascii_lowercase(),
         None => {
-            imp::send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
+            imp::send_error_message(
+                ctx,
+                original_msg,
+                "Please provide an activity ID, or `all` to export every activity.",
+            )
+            .await?;
             return Ok(());
         }
     };
 
-    if original_msg.author.id == creator_id {
-        let activity_opt = guild_data.remove_activity(activity_id);
+    let guild_name = guild_id
+        .name(ctx)
+        .await
+        .unwrap_or_else(|| String::from("Bingus"));
+
+    let type_map = ctx.data.read().await;
+
+    let guild_data = type_map
+        .get::<data_keys::GetGuildData>()
+        .and_then(|guild_map| guild_map.get(&guild_id.0));
 
-        if let Some(activity) = activity_opt {
-            if !activity.cancel_token.cancel() {
-                eprintln!("Error: Could not cancel Activity {}: {}. It may leak memory until the duration times out.", activity_id, activity.name);
+    let export = match (target.as_str(), guild_data) {
+        ("all", Some(guild_data)) if !guild_data.activities().is_empty() => Ok((
+            String::from("activities.ics"),
+            ical::calendar(
+                &format!("{} Activities", guild_name),
+                guild_data.activities_by_start(),
+            ),
+        )),
+        ("all", _) => Err("No activities are currently scheduled in this server."),
+        (id, guild_data) => {
+            let activity = id
+                .parse::<u64>()
+                .ok()
+                .and_then(|id| guild_data?.activity(id));
+
+            match activity {
+                Some(activity) => Ok((
+                    format!("activity-{}.ics", activity.id),
+                    ical::calendar(&activity.name, std::iter::once(activity)),
+                )),
+                None => Err("Invalid activity ID."),
             }
-            activity.embed_msg.delete(ctx).await?;
+        }
+    };
+
+    drop(type_map);
+
+    match export {
+        Ok((filename, calendar)) => {
+            let attachment = AttachmentType::Bytes {
+                data: Cow::Owned(calendar.into_bytes()),
+                filename,
+            };
+
             original_msg
                 .channel_id
-                .say(
-                    ctx,
-                    format!("Deleted activity {}: {}.", activity.id, activity.name),
-                )
+                .send_files(ctx, vec![attachment], |msg| {
+                    msg.content("Open this file to add it to your calendar.")
+                })
                 .await?;
         }
-    } else {
-        imp::send_error_message(ctx, original_msg, "You cannot delete that activity.").await?;
+        Err(error) => imp::send_error_message(ctx, original_msg, error).await?,
     }
 
     Ok(())
 }
 
-async fn activity_list(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
-    let guild_id = match original_msg.guild_id {
-        Some(id) => id,
-        None => {
-            imp::send_error_message(ctx, original_msg, "This command is not supported in DMs.")
-                .await?;
-            return Ok(());
+#[command]
+#[description = "Shows or changes how you're told about your activities. Subcommands are get and set."]
+async fn settings(ctx: &Context, original_msg: &Message, args: Args) -> CommandResult {
+    match args.current() {
+        Some("get") => settings_get(ctx, original_msg, args).await,
+        Some("set") => settings_set(ctx, original_msg, args).await,
+        _ => {
+            imp::send_error_message(
+                ctx,
+                original_msg,
+                "Invalid subcommand. Valid subcommands are `get` and `set`.",
+            )
+            .await?;
+            Ok(())
         }
-    };
-
-    let page_opt = args
-        .advance()
-        .current()
-        .map(|string| string.parse::<u64>().ok())
-        .flatten();
+    }
+}
 
-    let page = match page_opt {
-        Some(p) => {
-            if p == 0 {
-                0
-            } else {
-                p - 1
+async fn settings_get(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
+    let key = args.advance().current().map(str::to_ascii_lowercase);
+
+    let settings = ctx
+        .data
+        .read()
+        .await
+        .get::<data_keys::GetUserSettings>()
+        .and_then(|settings| settings.get(&original_msg.author.id))
+        .cloned()
+        .unwrap_or_default();
+
+    match key {
+        Some(key) => match settings.get(&key) {
+            Ok(value) => {
+                original_msg
+                    .channel_id
+                    .say(ctx, format!("`{}`: {}", key, value))
+                    .await?;
             }
-        }
+            Err(e) => imp::send_error_message(ctx, original_msg, e).await?,
+        },
         None => {
-            imp::send_error_message(ctx, original_msg, "Invalid page number.").await?;
-            return Ok(());
-        }
-    };
+            let mut settings_embed = CreateEmbed::default();
 
-    let type_map = ctx.data.read().await;
+            for &key in UserSettings::KEYS {
+                // Every key in `KEYS` is valid, so this never hits the error case.
+                if let Ok(value) = settings.get(key) {
+                    settings_embed.field(key, value, false);
+                }
+            }
 
-    let guild_data_map = match type_map.get::<data_keys::GetGuildData>() {
-        Some(map) => map,
-        None => {
-            imp::send_error_message(
-                ctx,
-                original_msg,
-                "No activities have been created in this server. Be the first to make one!",
-            )
-            .await?;
-            return Ok(());
+            settings_embed.color(0x212121).title("Your Settings");
+
+            original_msg
+                .channel_id
+                .send_message(ctx, |msg| {
+                    msg.embed(|embed| {
+                        *embed = settings_embed;
+                        embed
+                    })
+                })
+                .await?;
         }
-    };
+    }
 
-    let guild_data = match guild_data_map.get(&guild_id.0) {
-        Some(data) => data,
+    Ok(())
+}
+
+async fn settings_set(ctx: &Context, original_msg: &Message, mut args: Args) -> CommandResult {
+    let key = match args.advance().current() {
+        Some(key) => key.to_ascii_lowercase(),
         None => {
-            imp::send_error_message(
-                ctx,
-                original_msg,
-                "No activities have been created in this server. Be the first to make one!",
-            )
-            .await?;
+            imp::send_error_message(ctx, original_msg, "Please provide a setting to change.")
+                .await?;
             return Ok(());
         }
     };
 
-    let activities = guild_data
-        .activities()
-        .iter()
-        .sorted_by(|&(&a, _), &(&b, _)| a.cmp(&b))
-        .map(|(_, activity)| activity)
-        .collect::<Vec<_>>();
-
-    let chunks = activities.chunks(3).collect::<Vec<_>>();
+    let value = args.advance().rest().to_string();
 
-    if activities.is_empty() {
+    if value.is_empty() {
         imp::send_error_message(
             ctx,
             original_msg,
-            "No activities are currently scheduled in this server.",
+            "Please provide a value for that setting.",
         )
         .await?;
-    } else {
-        let list_page = chunks.get(page as usize);
-
-        match list_page {
-            Some(activities) => {
-                let mut list_embed = CreateEmbed::default();
-
-                for &activity in activities.iter() {
-                    let members_string = if !activity.members.is_empty() {
-                        activity
-                            .members
-                            .iter()
-                            .copied()
-                            .enumerate()
-                            .map(|(idx, id)| {
-                                if idx == 0 {
-                                    Mention::from(id).to_string()
-                                } else {
-                                    let mention = Mention::from(id).to_string();
-                                    format!(", {}", mention)
-                                }
-                            })
-                            .collect::<String>()
-                    } else {
-                        String::from("None")
-                    };
-
-                    let alternate_string = if !activity.alternate.is_empty() {
-                        activity
-                            .alternate
-                            .iter()
-                            .copied()
-                            .enumerate()
-                            .map(|(idx, id)| {
-                                if idx == 0 {
-                                    Mention::from(id).to_string()
-                                } else {
-                                    let mention = Mention::from(id).to_string();
-                                    format!(", {}", mention)
-                                }
-                            })
-                            .collect::<String>()
-                    } else {
-                        String::from("None")
-                    };
-
-                    list_embed
-                        .field("Activity:", &activity.name, true)
-                        .field("Time:", &activity.date, true)
-                        .field("Activity ID:", activity.id, true)
-                        .field("Description:", &activity.description, false)
-                        .field("Member List:", members_string, false)
-                        .field("Alternate Members:", alternate_string, false);
-                }
-
-                list_embed
-                    .title("Activity List")
-                    .footer(|footer| footer.text(format!("Page {}/{}", page + 1, chunks.len())));
+        return Ok(());
+    }
 
-                original_msg
-                    .channel_id
-                    .send_message(ctx, |msg| {
-                        msg.embed(|embed| {
-                            *embed = list_embed;
-                            embed
-                        })
-                    })
-                    .await?;
-            }
-            None => {
-                imp::send_error_message(
-                    ctx,
-                    original_msg,
-                    format!(
-                        "That page number is out of range. The maximum page number is {}",
-                        chunks.len()
-                    ),
-                )
+    match set_user_setting(ctx, original_msg.author.id, &key, &value).await {
+        Ok(new_value) => {
+            original_msg
+                .channel_id
+                .say(ctx, format!("Updated `{}` to {}.", key, new_value))
                 .await?;
-            }
         }
+        Err(e) => imp::send_error_message(ctx, original_msg, e).await?,
     }
 
     Ok(())
 }
 
+/// Changes one of a user's settings, returning how the new value is shown.
+async fn set_user_setting(
+    ctx: &Context,
+    user: UserId,
+    key: &str,
+    value: &str,
+) -> Result<String, SettingsError> {
+    let mut type_map = ctx.data.write().await;
+    let settings = type_map
+        .entry::<data_keys::GetUserSettings>()
+        .or_default()
+        .entry(user)
+        .or_default();
+
+    settings.set(key, value).and_then(|()| settings.get(key))
+}
+
 #[command]
 #[description = "Create, update a user's markov chain information. Used for sending messages that sound like the specified user."]
 async fn markov(ctx: &Context, original_msg: &Message, args: Args) -> CommandResult {
@@ -991,11 +1412,7 @@ async fn markov_create(ctx: &Context, original_msg: &Message, mut args: Args) ->
         }
     };
 
-    let user_id_opt = args
-        .advance()
-        .current()
-        .map(|s| s.parse::<u64>().ok())
-        .flatten();
+    let user_id_opt = args.advance().current().and_then(|s| s.parse::<u64>().ok());
 
     let user_id = match user_id_opt {
         Some(id) => id,
@@ -1031,7 +1448,7 @@ async fn markov_create(ctx: &Context, original_msg: &Message, mut args: Args) ->
         .entry(user_id.into())
         .or_default()
         .iter()
-        .for_each(|msg| info.feed_str(&msg));
+        .for_each(|msg| info.feed_str(msg));
 
     guild_data.markov_mut().insert(alias, info);
 
@@ -1090,3 +1507,134 @@ async fn markov_say(ctx: &Context, original_msg: &Message, mut args: Args) -> Co
 
     Ok(())
 }
+
+#[command]
+#[description = "Shows how often members show up to the activities they join. Subcommands are me and leaderboard."]
+async fn stats(ctx: &Context, original_msg: &Message, args: Args) -> CommandResult {
+    let guild_id = match original_msg.guild_id {
+        Some(id) => id,
+        None => {
+            imp::send_error_message(ctx, original_msg, "This command is not supported in DMs.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    match args.current() {
+        Some("me") => stats_me(ctx, original_msg, guild_id).await,
+        Some("leaderboard") => stats_leaderboard(ctx, original_msg, guild_id).await,
+        _ => {
+            imp::send_error_message(
+                ctx,
+                original_msg,
+                "Invalid subcommand. Valid subcommands are `me` and `leaderboard`.",
+            )
+            .await?;
+            Ok(())
+        }
+    }
+}
+
+/// How many members `stats leaderboard` shows.
+const LEADERBOARD_SIZE: usize = 10;
+
+fn describe_reliability(stats: &UserStats) -> String {
+    match stats.reliability() {
+        Some(reliability) => format!("{:.0}%", reliability * 100.0),
+        None => String::from("Not marked yet"),
+    }
+}
+
+async fn stats_me(ctx: &Context, original_msg: &Message, guild_id: GuildId) -> CommandResult {
+    let stats = ctx
+        .data
+        .read()
+        .await
+        .get::<data_keys::GetGuildData>()
+        .and_then(|guild_map| guild_map.get(&guild_id.0))
+        .and_then(|guild_data| guild_data.stats().remove(&original_msg.author.id))
+        .unwrap_or_default();
+
+    let mut stats_embed = CreateEmbed::default();
+
+    stats_embed
+        .color(0x212121)
+        .title("Your Activity Stats")
+        .field("Joined", stats.joined, true)
+        .field("Attended", stats.attended, true)
+        .field("No-shows", stats.no_shows, true)
+        .field("Reliability", describe_reliability(&stats), true)
+        .field("Hosted", stats.hosted, true);
+
+    original_msg
+        .channel_id
+        .send_message(ctx, |msg| {
+            msg.embed(|embed| {
+                *embed = stats_embed;
+                embed
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+async fn stats_leaderboard(
+    ctx: &Context,
+    original_msg: &Message,
+    guild_id: GuildId,
+) -> CommandResult {
+    let leaderboard = ctx
+        .data
+        .read()
+        .await
+        .get::<data_keys::GetGuildData>()
+        .and_then(|guild_map| guild_map.get(&guild_id.0))
+        .map(|guild_data| guild_data.leaderboard(LEADERBOARD_SIZE))
+        .unwrap_or_default();
+
+    if leaderboard.is_empty() {
+        original_msg
+            .channel_id
+            .say(
+                ctx,
+                "Nobody's attendance has been taken yet. Admins can mark it with `admin activity attendance`.",
+            )
+            .await?;
+        return Ok(());
+    }
+
+    let description = leaderboard
+        .iter()
+        .enumerate()
+        .map(|(idx, (user_id, stats))| {
+            format!(
+                "{}. {}: attended {}, {} reliable, hosted {}",
+                idx + 1,
+                Mention::from(*user_id),
+                stats.attended,
+                describe_reliability(stats),
+                stats.hosted
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut leaderboard_embed = CreateEmbed::default();
+    leaderboard_embed
+        .color(0x212121)
+        .title("Attendance Leaderboard")
+        .description(description);
+
+    original_msg
+        .channel_id
+        .send_message(ctx, |msg| {
+            msg.embed(|embed| {
+                *embed = leaderboard_embed;
+                embed
+            })
+        })
+        .await?;
+
+    Ok(())
+}
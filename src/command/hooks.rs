@@ -0,0 +1,270 @@
+use std::cell::RefCell;
+use std::time::Instant;
+
+use serenity::{
+    async_trait,
+    framework::{
+        standard::{macros::hook, CommandGroup, CommandResult, DispatchError},
+        Framework, StandardFramework,
+    },
+    model::channel::Message,
+    prelude::Context,
+};
+use tracing::{debug, field, info, info_span, Instrument, Span};
+
+use crate::command::imp::{self, data_keys};
+use crate::command::report;
+use crate::metrics;
+
+/// A command that is being run. It's stored by `before_command` and finished by `after_command`.
+struct CommandSpan {
+    group: &'static str,
+    started: Instant,
+}
+
+tokio::task_local! {
+    /// The command being run by the current dispatch. Each dispatch gets its own, so the hooks
+    /// don't need to lock the TypeMap to share it.
+    static RUNNING_COMMAND: RefCell<Option<CommandSpan>>;
+}
+
+/// Runs the `StandardFramework` inside a span for each message, so that everything a command
+/// logs while it runs is tied to it. The hooks fill in the command once it's known.
+#[derive(Default)]
+pub struct TracedFramework(pub StandardFramework);
+
+#[async_trait]
+impl Framework for TracedFramework {
+    async fn dispatch(&self, ctx: Context, msg: Message) {
+        let span = command_span(&msg);
+
+        RUNNING_COMMAND
+            .scope(
+                RefCell::new(None),
+                self.0.dispatch(ctx, msg).instrument(span),
+            )
+            .await;
+    }
+}
+
+/// Resolves the command prefix for a message. Guilds with a prefix set in their settings use
+/// that prefix, and everything else falls back to the prefix from the bot config.
+#[hook]
+pub async fn guild_prefix(ctx: &Context, msg: &Message) -> Option<String> {
+    imp::resolve_prefix(&*ctx.data.read().await, msg.guild_id)
+}
+
+/// Ignores commands sent in DMs when `allow_dm` is off or outside of a guild's allowed command
+/// channels, and records the command on the current span for the ones that are run.
+#[hook]
+pub async fn before_command(ctx: &Context, msg: &Message, command_name: &str) -> bool {
+    if msg.guild_id.is_none() && !allows_dm(ctx).await {
+        debug!(
+            command = command_name,
+            user_id = msg.author.id.0,
+            "Ignored a command sent in a DM"
+        );
+        return false;
+    }
+
+    if !check_command_channel(ctx, msg).await {
+        debug!(
+            command = command_name,
+            channel_id = msg.channel_id.0,
+            user_id = msg.author.id.0,
+            "Ignored a command outside of the command channels"
+        );
+        return false;
+    }
+
+    let group = ctx
+        .data
+        .read()
+        .await
+        .get::<data_keys::GetCommandGroups>()
+        .map_or("unknown", |groups| {
+            command_group(groups, &msg.content, command_name)
+        });
+
+    let span = Span::current();
+    span.record("command", command_name);
+    span.record("group", group);
+
+    let command_span = CommandSpan {
+        group,
+        started: Instant::now(),
+    };
+
+    // This is only missing when the framework is run without `TracedFramework`, in which case
+    // the command is logged without a duration.
+    let _ = RUNNING_COMMAND.try_with(|running| *running.borrow_mut() = Some(command_span));
+
+    true
+}
+
+/// Logs how a command went, along with how long it took. If the command failed, the user is told
+/// why.
+#[hook]
+pub async fn after_command(
+    ctx: &Context,
+    msg: &Message,
+    command_name: &str,
+    result: CommandResult,
+) {
+    let running = RUNNING_COMMAND
+        .try_with(|running| running.borrow_mut().take())
+        .ok()
+        .flatten();
+
+    let span = Span::current();
+    let (group, duration) = match running {
+        Some(CommandSpan { group, started }) => (group, Some(started.elapsed())),
+        None => {
+            span.record("command", command_name);
+            span.record("group", "unknown");
+            ("unknown", None)
+        }
+    };
+
+    if let Some(duration) = duration {
+        span.record("duration_ms", duration.as_millis() as u64);
+    }
+
+    metrics::command_finished(group, command_name, duration, result.as_ref().err());
+
+    match result {
+        Ok(()) => {
+            span.record("outcome", "ok");
+            info!("Command finished");
+        }
+        Err(e) => {
+            span.record("outcome", "error");
+            report::report_command_error(ctx, msg, command_name, &e).await;
+        }
+    }
+}
+
+/// Tells the user why a command they sent wasn't run.
+#[hook]
+pub async fn dispatch_error(ctx: &Context, msg: &Message, error: DispatchError) {
+    debug!(
+        channel_id = msg.channel_id.0,
+        user_id = msg.author.id.0,
+        error = ?error,
+        "Command was not dispatched"
+    );
+
+    report::report_dispatch_error(ctx, msg, &error).await;
+}
+
+fn command_span(msg: &Message) -> Span {
+    info_span!(
+        "command",
+        command = field::Empty,
+        group = field::Empty,
+        guild_id = msg.guild_id.map(|id| id.0),
+        channel_id = msg.channel_id.0,
+        user_id = msg.author.id.0,
+        duration_ms = field::Empty,
+        outcome = field::Empty,
+    )
+}
+
+/// Finds the name of the group that a command was run from. Commands in different groups can
+/// share a name, like `activity` and `admin activity`, so groups with a prefix are only picked if
+/// the prefix comes before the command in the message.
+fn command_group(
+    groups: &[&'static CommandGroup],
+    content: &str,
+    command_name: &str,
+) -> &'static str {
+    let content = content.to_lowercase();
+    let command_position = content.find(command_name).unwrap_or(content.len());
+
+    let candidates = groups.iter().filter(|group| {
+        group
+            .options
+            .commands
+            .iter()
+            .any(|command| command.options.names.contains(&command_name))
+    });
+
+    let mut unprefixed = None;
+
+    for group in candidates {
+        let prefixes = group.options.prefixes;
+
+        if prefixes.is_empty() {
+            unprefixed = unprefixed.or(Some(group.name));
+        } else if prefixes
+            .iter()
+            .any(|prefix| content[..command_position].contains(prefix))
+        {
+            return group.name;
+        }
+    }
+
+    unprefixed.unwrap_or("unknown")
+}
+
+async fn allows_dm(ctx: &Context) -> bool {
+    ctx.data
+        .read()
+        .await
+        .get::<data_keys::GetRuntimeConfig>()
+        .is_some_and(|config| config.allow_dm)
+}
+
+/// Whether commands are allowed in the channel a message was sent in. Admins are exempt so that
+/// they can always fix a misconfigured guild.
+async fn check_command_channel(ctx: &Context, msg: &Message) -> bool {
+    let guild_id = match msg.guild_id {
+        Some(id) => id,
+        None => return true,
+    };
+
+    let allowed = {
+        let type_map = ctx.data.read().await;
+
+        type_map
+            .get::<data_keys::GetGuildData>()
+            .and_then(|guild_map| guild_map.get(&guild_id.0))
+            .is_none_or(|guild_data| guild_data.settings().allows_commands_in(msg.channel_id))
+    };
+
+    allowed || imp::is_admin(ctx, msg.author.id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{admin::ADMINSONLY_GROUP, general::GENERAL_GROUP};
+
+    const GROUPS: &[&CommandGroup] = &[&GENERAL_GROUP, &ADMINSONLY_GROUP];
+
+    #[test]
+    fn commands_are_matched_to_their_group() {
+        assert_eq!(command_group(GROUPS, "~markov say me", "markov"), "General");
+        assert_eq!(
+            command_group(GROUPS, "~admin echo hi", "echo"),
+            "AdminsOnly"
+        );
+    }
+
+    #[test]
+    fn shared_command_names_use_the_group_prefix() {
+        assert_eq!(
+            command_group(GROUPS, "~activity list", "activity"),
+            "General"
+        );
+        assert_eq!(
+            command_group(GROUPS, "~Admin activity start 4", "activity"),
+            "AdminsOnly"
+        );
+    }
+
+    #[test]
+    fn unknown_commands_have_no_group() {
+        assert_eq!(command_group(GROUPS, "~nothing", "nothing"), "unknown");
+    }
+}
@@ -0,0 +1,179 @@
+//! Just enough of iCalendar (RFC 5545) to export activities to calendar apps.
+
+use chrono::{DateTime, TimeZone, Utc};
+use serenity::model::misc::Mention;
+use std::fmt::Write as _;
+
+use crate::command::data::Activity;
+
+/// Lines longer than this many bytes must be folded onto continuation lines.
+const MAX_LINE_LEN: usize = 75;
+
+/// Builds a calendar containing one event for each activity.
+pub fn calendar<'a, I>(name: &str, activities: I) -> String
+where
+    I: IntoIterator<Item = &'a Activity>,
+{
+    let stamp = format_date_time(&Utc::now());
+    let mut out = String::new();
+
+    write_line(&mut out, "BEGIN:VCALENDAR");
+    write_line(&mut out, "VERSION:2.0");
+    write_line(&mut out, "PRODID:-//Bingus Bot//Activity Rosters//EN");
+    write_line(&mut out, "CALSCALE:GREGORIAN");
+    write_line(&mut out, "METHOD:PUBLISH");
+    write_line(&mut out, &format!("X-WR-CALNAME:{}", escape_text(name)));
+
+    for activity in activities {
+        write_event(&mut out, activity, &stamp);
+    }
+
+    write_line(&mut out, "END:VCALENDAR");
+
+    out
+}
+
+fn write_event(out: &mut String, activity: &Activity, stamp: &str) {
+    let mut description = activity.description.clone();
+
+    let _ = write!(
+        description,
+        "\n\nMembers ({}/{}): {}",
+        activity.members.len(),
+        activity.size,
+        mention_list(activity.members.iter().copied())
+    );
+
+    if !activity.alternate.is_empty() {
+        let _ = write!(
+            description,
+            "\nAlternates: {}",
+            mention_list(activity.alternate.iter().copied())
+        );
+    }
+
+    let _ = write!(description, "\nRoster: {}", activity.embed_msg.link());
+
+    write_line(out, "BEGIN:VEVENT");
+    // The roster message ID is unique for the lifetime of the activity, unlike the activity ID,
+    // which is reused once an activity is deleted.
+    write_line(out, &format!("UID:{}@bingus-bot", activity.embed_msg.id));
+    write_line(out, &format!("DTSTAMP:{}", stamp));
+    write_line(
+        out,
+        &format!("DTSTART:{}", format_date_time(&activity.start.date_time)),
+    );
+    write_line(out, &format!("DTEND:{}", format_date_time(&activity.end())));
+    write_line(out, &format!("SUMMARY:{}", escape_text(&activity.name)));
+    write_line(out, &format!("DESCRIPTION:{}", escape_text(&description)));
+    write_line(out, &format!("URL:{}", activity.embed_msg.link()));
+
+    for &member in activity.members.iter() {
+        write_line(
+            out,
+            &format!(
+                "ATTENDEE;CN=\"{}\";ROLE=REQ-PARTICIPANT:urn:discord:user:{}",
+                Mention::from(member),
+                member
+            ),
+        );
+    }
+
+    for &alternate in activity.alternate.iter() {
+        write_line(
+            out,
+            &format!(
+                "ATTENDEE;CN=\"{}\";ROLE=OPT-PARTICIPANT:urn:discord:user:{}",
+                Mention::from(alternate),
+                alternate
+            ),
+        );
+    }
+
+    write_line(out, "END:VEVENT");
+}
+
+fn mention_list<I: Iterator<Item = serenity::model::id::UserId>>(users: I) -> String {
+    let mentions = users
+        .map(|user| Mention::from(user).to_string())
+        .collect::<Vec<_>>();
+
+    if mentions.is_empty() {
+        String::from("None")
+    } else {
+        mentions.join(", ")
+    }
+}
+
+fn format_date_time<Tz: TimeZone>(date_time: &DateTime<Tz>) -> String {
+    date_time
+        .with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Writes a content line, folding it so that no line is longer than `MAX_LINE_LEN` bytes.
+fn write_line(out: &mut String, line: &str) {
+    let mut line_len = 0;
+
+    for c in line.chars() {
+        // Continuation lines start with a space, which counts towards their length.
+        if line_len + c.len_utf8() > MAX_LINE_LEN {
+            out.push_str("\r\n ");
+            line_len = 1;
+        }
+
+        out.push(c);
+        line_len += c.len_utf8();
+    }
+
+    out.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert_eq!(
+            escape_text("Raid; bring relics, and a \\ backslash\r\nthanks"),
+            r"Raid\; bring relics\, and a \\ backslash\nthanks"
+        );
+    }
+
+    #[test]
+    fn long_lines_are_folded_without_splitting_characters() {
+        let mut out = String::new();
+        write_line(&mut out, &"a".repeat(80));
+
+        assert_eq!(
+            out,
+            format!("{}\r\n {}\r\n", "a".repeat(MAX_LINE_LEN), "a".repeat(5))
+        );
+
+        // 74 bytes of ASCII leaves one byte on the first line, which isn't enough for "é".
+        let mut out = String::new();
+        write_line(&mut out, &format!("{}éé", "a".repeat(74)));
+
+        assert_eq!(out, format!("{}\r\n éé\r\n", "a".repeat(74)));
+        assert!(out.split("\r\n").all(|line| line.len() <= MAX_LINE_LEN));
+    }
+}
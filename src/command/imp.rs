@@ -1,12 +1,27 @@
-use chrono::{DateTime, FixedOffset, TimeZone};
+use crate::command::data::{
+    Activity, ActivityError, EmbedError, EmbedWithMeta, GuildData, KnownActivity, RosterData,
+    StartTime,
+};
+use crate::command::discord::{Discord, SerenityDiscord};
+use crate::command::events::{self, ActivityEvent};
+use crate::command::service::{ActivityService, Actor};
+use crate::command::wizard::Wizard;
+use crate::metrics;
+use crate::util::CancelActivity;
+use chrono::{DateTime, Datelike, FixedOffset, TimeZone, Utc, Weekday};
 use serenity::builder::CreateEmbed;
-use serenity::framework::standard::CommandResult;
+use serenity::collector::ReactionAction;
+use serenity::framework::standard::{Args, CommandResult};
 use serenity::model::channel::Embed;
-use serenity::model::id::GuildId;
-use serenity::model::misc::Mention;
+use serenity::model::channel::ReactionType;
+use serenity::model::id::{ChannelId, GuildId};
 use serenity::model::prelude::{Message, UserId};
-use serenity::prelude::{Context, TypeMap};
-use std::fmt::Display;
+use serenity::prelude::{Context, RwLock, TypeMap};
+use std::collections::HashMap;
+use std::error;
+use std::fmt::{self, Display};
+use std::time::Duration;
+use tracing::{error, warn};
 
 pub(crate) fn create_embed(embed: &Embed) -> CreateEmbed {
     CreateEmbed::from(embed.clone())
@@ -33,6 +48,16 @@ pub(crate) async fn send_error_message<D: Display>(
 //     Ok(())
 // }
 
+pub(crate) async fn is_owner(ctx: &Context, user: UserId) -> bool {
+    let type_map = ctx.data.read().await;
+    let config = type_map.get::<data_keys::GetRuntimeConfig>();
+
+    match config {
+        Some(config) => config.owners.contains(&user),
+        None => false,
+    }
+}
+
 pub(crate) async fn is_admin(ctx: &Context, user: UserId) -> bool {
     let type_map = ctx.data.read().await;
     let admins = type_map.get::<data_keys::GetAdmins>();
@@ -43,23 +68,33 @@ pub(crate) async fn is_admin(ctx: &Context, user: UserId) -> bool {
     }
 }
 
-pub(crate) fn parse_date_time(date_time_str: &str) -> Option<(DateTime<FixedOffset>, String)> {
+/// Parses either a channel mention (`<#id>`) or a raw channel ID.
+pub(crate) fn parse_channel_id(s: &str) -> Option<ChannelId> {
+    serenity::utils::parse_channel(s)
+        .or_else(|| s.parse::<u64>().ok())
+        .map(ChannelId::from)
+}
+
+/// Parses either a user mention (`<@id>` or `<@!id>`) or a raw user ID.
+pub(crate) fn parse_user_id(s: &str) -> Option<UserId> {
+    serenity::utils::parse_username(s)
+        .or_else(|| s.parse::<u64>().ok())
+        .map(UserId::from)
+}
+
+pub(crate) fn parse_date_time(date_time_str: &str) -> Option<StartTime> {
     let mut split_by_space = date_time_str.split(" ");
 
-    let month_day_str = match split_by_space.next() {
-        Some(string) => string,
-        None => return None,
-    };
+    let month_day_str = split_by_space.next()?;
 
     let mut month_day_year_iter = month_day_str.split("/");
 
     let month_value = {
         let value = month_day_year_iter
             .next()
-            .map(|string| string.parse::<u32>().ok())
-            .flatten();
+            .and_then(|string| string.parse::<u32>().ok());
         match value {
-            Some(month) if month <= 12 => month,
+            Some(month) if (1..=12).contains(&month) => month,
             _ => return None,
         }
     };
@@ -67,8 +102,7 @@ pub(crate) fn parse_date_time(date_time_str: &str) -> Option<(DateTime<FixedOffs
     let day_value = {
         let value = month_day_year_iter
             .next()
-            .map(|string| string.parse::<u32>().ok())
-            .flatten();
+            .and_then(|string| string.parse::<u32>().ok());
         match value {
             Some(day) if day <= max_day_value(month_value) => day,
             _ => return None,
@@ -78,8 +112,7 @@ pub(crate) fn parse_date_time(date_time_str: &str) -> Option<(DateTime<FixedOffs
     let year_value = {
         let value = month_day_year_iter
             .next()
-            .map(|string| string.parse::<i32>().ok())
-            .flatten();
+            .and_then(|string| string.parse::<i32>().ok());
         match value {
             Some(year) if year > 2000 && year < 2100 => year,
             _ => return None,
@@ -90,18 +123,14 @@ pub(crate) fn parse_date_time(date_time_str: &str) -> Option<(DateTime<FixedOffs
         return None;
     }
 
-    let time_str = match split_by_space.next() {
-        Some(string) => string,
-        None => return None,
-    };
+    let time_str = split_by_space.next()?;
 
     let mut time_iter = time_str.split(":");
 
     let mut hour_value = {
         let value = time_iter
             .next()
-            .map(|string| string.parse::<u32>().ok())
-            .flatten();
+            .and_then(|string| string.parse::<u32>().ok());
         match value {
             Some(hour) if hour > 0 && hour <= 12 => hour,
             _ => return None,
@@ -111,8 +140,7 @@ pub(crate) fn parse_date_time(date_time_str: &str) -> Option<(DateTime<FixedOffs
     let minute_value = {
         let value = time_iter
             .next()
-            .map(|string| string.parse::<u32>().ok())
-            .flatten();
+            .and_then(|string| string.parse::<u32>().ok());
         match value {
             Some(min) if min <= 59 => min,
             _ => return None,
@@ -132,21 +160,31 @@ pub(crate) fn parse_date_time(date_time_str: &str) -> Option<(DateTime<FixedOffs
         _ => return None,
     };
 
-    if am_or_pm == "pm" {
-        if hour_value != 12 {
-            hour_value += 12
-        }
+    if am_or_pm == "pm" && hour_value != 12 {
+        hour_value += 12
+    } else if am_or_pm == "am" && hour_value == 12 {
+        hour_value = 0
     }
 
     if split_by_space.next().is_some() {
         return None;
     }
 
-    let date_time = FixedOffset::west(5 * 3600)
-        .ymd(year_value, month_value, day_value)
-        .and_hms(hour_value, minute_value, 0);
+    let date_time = FixedOffset::west_opt(5 * 3600)?
+        .with_ymd_and_hms(
+            year_value,
+            month_value,
+            day_value,
+            hour_value,
+            minute_value,
+            0,
+        )
+        .single()?;
 
-    Some((date_time, date_time_str.to_ascii_lowercase()))
+    Some(StartTime {
+        date_time,
+        input: date_time_str.to_ascii_lowercase(),
+    })
 }
 
 fn max_day_value(month: u32) -> u32 {
@@ -167,44 +205,1147 @@ fn max_day_value(month: u32) -> u32 {
     }
 }
 
-pub async fn start_activity(
+/// Parses a start time in either the full `mm/dd/yyyy hh:mm am|pm` format or a shorter one like
+/// `tue 8pm`.
+pub(crate) fn parse_start(input: &str) -> Option<StartTime> {
+    parse_date_time(input).or_else(|| parse_casual_date_time(input, Utc::now()))
+}
+
+/// Parses a start time like `tue 8pm`, `tomorrow 9:30 am` or `8pm`, relative to `now`. Weekdays
+/// are the next one to come, so `tue 8pm` on a Tuesday evening means the following week. Like
+/// `parse_date_time`, times are in UTC-5.
+pub(crate) fn parse_casual_date_time(input: &str, now: DateTime<Utc>) -> Option<StartTime> {
+    let offset = FixedOffset::west_opt(5 * 3600)?;
+    let now = now.with_timezone(&offset);
+    let lowercase = input.trim().to_ascii_lowercase();
+    let mut words = lowercase.split_whitespace().peekable();
+
+    let day = words.peek().and_then(|word| parse_day(word, now.weekday()));
+
+    if day.is_some() {
+        words.next();
+    }
+
+    // Allows both `8pm` and `8 pm`.
+    let time = words.collect::<String>();
+
+    let (time, is_pm) = if let Some(time) = time.strip_suffix("am") {
+        (time, false)
+    } else if let Some(time) = time.strip_suffix("pm") {
+        (time, true)
+    } else {
+        return None;
+    };
+
+    let (hour, minute) = match time.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => {
+            (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?)
+        }
+        Some(_) => return None,
+        None => (time.parse::<u32>().ok()?, 0),
+    };
+
+    if !(1..=12).contains(&hour) || minute > 59 {
+        return None;
+    }
+
+    let hour = match (hour, is_pm) {
+        (12, false) => 0,
+        (12, true) => 12,
+        (hour, true) => hour + 12,
+        (hour, false) => hour,
+    };
+
+    let (days, is_weekday) = day.unwrap_or((0, false));
+    let date = now.date_naive() + chrono::Duration::days(days);
+    let mut date_time = offset
+        .from_local_datetime(&date.and_hms_opt(hour, minute, 0)?)
+        .single()?;
+
+    if is_weekday && date_time <= now {
+        date_time += chrono::Duration::days(7);
+    }
+
+    Some(StartTime {
+        date_time,
+        input: lowercase,
+    })
+}
+
+/// Reads a day like `today`, `tomorrow` or `tue`. Returns how many days from `today` it is, and
+/// whether it was a weekday.
+fn parse_day(word: &str, today: Weekday) -> Option<(i64, bool)> {
+    match word {
+        "today" | "tonight" => Some((0, false)),
+        "tomorrow" | "tmrw" => Some((1, false)),
+        _ => {
+            let weekday = match word {
+                "tues" => Weekday::Tue,
+                "thur" | "thurs" => Weekday::Thu,
+                _ => word.parse::<Weekday>().ok()?,
+            };
+            let days = (weekday.num_days_from_monday() + 7 - today.num_days_from_monday()) % 7;
+
+            Some((i64::from(days), true))
+        }
+    }
+}
+
+/// The arguments of `activity quick`, before the activity and start time are looked up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct QuickArgs {
+    pub name: String,
+    pub size: u8,
+    pub start: String,
+    /// Empty if no description was given. Rosters show it as "None".
+    pub description: String,
+}
+
+/// Reads `"<activity name>" <size> "<date and time>" [description]` from the arguments after
+/// `quick`.
+pub(crate) fn parse_quick_args(mut args: Args) -> Option<QuickArgs> {
+    let name = args.single_quoted::<String>().ok()?;
+    let size = args.single_quoted::<u8>().ok().filter(|&size| size > 0)?;
+    let start = args.single_quoted::<String>().ok()?;
+    let description = args.rest().trim().to_string();
+
+    Some(QuickArgs {
+        name,
+        size,
+        start,
+        description,
+    })
+}
+
+/// Checks that a fireteam of `size` fits `known`: no bigger than the activity allows, and big
+/// enough for every spot in its roles.
+pub(crate) fn check_quick_size(known: &KnownActivity, size: u8) -> Result<(), String> {
+    let role_spots: u32 = known
+        .roles
+        .iter()
+        .map(|role| u32::from(role.capacity))
+        .sum();
+
+    if size > known.size {
+        Err(format!(
+            "{} is for at most {} players.",
+            known.name, known.size
+        ))
+    } else if u32::from(size) < role_spots {
+        Err(format!(
+            "{} needs at least {} players to fill its roles.",
+            known.name, role_spots
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Finds the activity in `embeds.json` that `name` refers to, ignoring case. A name without the
+/// part in parentheses, like `Vault of Glass`, matches every version of that activity as long as
+/// they're set up the same way.
+pub(crate) fn resolve_known_activity(
+    known: &[KnownActivity],
+    name: &str,
+) -> Result<KnownActivity, String> {
+    let name = name.trim();
+
+    if let Some(activity) = known
+        .iter()
+        .find(|activity| activity.name.eq_ignore_ascii_case(name))
+    {
+        return Ok(activity.clone());
+    }
+
+    let matches = known
+        .iter()
+        .filter(|activity| activity.base_name().eq_ignore_ascii_case(name))
+        .collect::<Vec<_>>();
+
+    match matches.as_slice() {
+        [] => Err(format!(
+            "There's no activity named `{}`. Known activities are: {}.",
+            name,
+            known
+                .iter()
+                .map(|activity| activity.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+        [first, rest @ ..]
+            if rest
+                .iter()
+                .all(|other| other.duration == first.duration && other.roles == first.roles) =>
+        {
+            Ok(KnownActivity {
+                name: first.base_name().to_string(),
+                size: matches
+                    .iter()
+                    .map(|activity| activity.size)
+                    .max()
+                    .unwrap_or(first.size),
+                ..KnownActivity::clone(first)
+            })
+        }
+        _ => Err(format!(
+            "`{}` could be any of: {}. Please use the full name.",
+            name,
+            matches
+                .iter()
+                .map(|activity| activity.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Formats a list of conflicting activities as one line per activity, or an empty string if
+/// there are no conflicts.
+pub(crate) fn describe_conflicts(conflicts: &[&Activity]) -> String {
+    conflicts
+        .iter()
+        .map(|activity| {
+            format!(
+                "- **{}** (ID: {}), {}",
+                activity.name, activity.id, activity.start
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Finds the guilds that have an activity with ID `activity_id`, for commands sent in a DM.
+/// Activity IDs are only unique within a guild, so `selector` can pick one guild out of several.
+pub(crate) fn guilds_with_activity(
+    guild_map: &HashMap<u64, GuildData>,
+    activity_id: u64,
+    selector: Option<GuildId>,
+) -> Vec<GuildId> {
+    let mut guilds = guild_map
+        .iter()
+        .filter(|(&id, _)| selector.is_none_or(|selector| selector.0 == id))
+        .filter(|(_, guild_data)| guild_data.activity(activity_id).is_some())
+        .map(|(&id, _)| GuildId(id))
+        .collect::<Vec<_>>();
+
+    guilds.sort();
+    guilds
+}
+
+/// Whether `s` looks like a Discord ID. Guild IDs are told apart from other numbers, like roles
+/// named `1`, by their length.
+pub(crate) fn is_snowflake(s: &str) -> bool {
+    (17..=20).contains(&s.len()) && s.bytes().all(|c| c.is_ascii_digit())
+}
+
+/// The guild a command was sent in, or the message to send back for commands that can't be run
+/// in DMs.
+pub(crate) fn command_guild(msg: &Message) -> Result<GuildId, &'static str> {
+    msg.guild_id.ok_or("This command is not supported in DMs.")
+}
+
+/// Works out which guild an activity command is about. Commands sent in a guild are always about
+/// that guild. In a DM, the guild is inferred from the guilds that the author is in which have
+/// the activity, and `selector` is an optional guild ID for when there's more than one.
+pub(crate) async fn activity_guild(
+    ctx: &Context,
+    msg: &Message,
+    activity_id: u64,
+    selector: Option<&str>,
+) -> Result<GuildId, String> {
+    if let Some(guild_id) = msg.guild_id {
+        return Ok(guild_id);
+    }
+
+    let selector = match selector {
+        Some(selector) => Some(selector.parse::<u64>().map(GuildId).map_err(|_| {
+            String::from("Please provide a valid server ID after the activity ID.")
+        })?),
+        None => None,
+    };
+
+    let candidates = ctx
+        .data
+        .read()
+        .await
+        .get::<data_keys::GetGuildData>()
+        .map(|guild_map| guilds_with_activity(guild_map, activity_id, selector))
+        .unwrap_or_default();
+
+    // Users can only change activities in guilds that they're a member of.
+    let mut guilds = Vec::with_capacity(candidates.len());
+
+    for guild_id in candidates {
+        if guild_id.member(ctx, msg.author.id).await.is_ok() {
+            guilds.push(guild_id);
+        }
+    }
+
+    match guilds.as_slice() {
+        [] => Err(String::from("Invalid activity ID.")),
+        [guild_id] => Ok(*guild_id),
+        guilds => {
+            let mut names = Vec::with_capacity(guilds.len());
+
+            for guild_id in guilds {
+                let name = guild_id
+                    .name(ctx)
+                    .await
+                    .unwrap_or_else(|| String::from("Unknown server"));
+
+                names.push(format!("- {} (ID: {})", name, guild_id));
+            }
+
+            Err(format!(
+                "More than one of your servers has an activity with ID {}. Please add the server ID after the activity ID:\n{}",
+                activity_id,
+                names.join("\n")
+            ))
+        }
+    }
+}
+
+/// Lets the author know that their command worked. Commands in a guild are deleted to keep
+/// channels tidy, but messages in a DM can't be deleted by the bot, so they're reacted to instead.
+pub(crate) async fn acknowledge(ctx: &Context, msg: &Message) -> serenity::Result<()> {
+    if msg.guild_id.is_some() {
+        msg.delete(ctx).await
+    } else {
+        msg.react(ctx, '👍').await.map(|_| ())
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub(crate) enum ActivityUpdateError {
+    InvalidActivity,
+    /// The actor isn't allowed to make the change.
+    NotAllowed,
+    /// The activity's start time has already passed.
+    InvalidStart,
+    /// There's no template with the given name in the guild.
+    InvalidTemplate,
+    Activity(ActivityError),
+    /// The user is already in overlapping activities, and the guild blocks conflicts.
+    Conflict(String),
+    Serenity(serenity::Error),
+}
+
+impl From<serenity::Error> for ActivityUpdateError {
+    fn from(e: serenity::Error) -> Self {
+        Self::Serenity(e)
+    }
+}
+
+impl From<ActivityError> for ActivityUpdateError {
+    fn from(e: ActivityError) -> Self {
+        Self::Activity(e)
+    }
+}
+
+impl fmt::Display for ActivityUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ActivityUpdateError::*;
+
+        match self {
+            InvalidActivity => f.write_str("Invalid activity ID"),
+            NotAllowed => f.write_str("Not allowed to change that activity"),
+            InvalidStart => f.write_str("The start time has already passed"),
+            InvalidTemplate => f.write_str("No template has that name"),
+            Activity(e) => e.fmt(f),
+            Conflict(conflicts) => write!(f, "Overlaps with other activities:\n{}", conflicts),
+            Serenity(e) => write!(f, "Serenity error: {}", e),
+        }
+    }
+}
+
+impl error::Error for ActivityUpdateError {}
+
+/// The command prefix in a guild: the guild's own prefix if it has one, or the prefix from the
+/// bot config. Takes the TypeMap so that it can be used while it's already locked.
+pub(crate) fn resolve_prefix(type_map: &TypeMap, guild_id: Option<GuildId>) -> Option<String> {
+    let guild_prefix = guild_id.and_then(|guild_id| {
+        type_map
+            .get::<data_keys::GetGuildData>()?
+            .get(&guild_id.0)?
+            .settings()
+            .prefix
+            .clone()
+    });
+
+    guild_prefix.or_else(|| {
+        type_map
+            .get::<data_keys::GetRuntimeConfig>()
+            .map(|config| config.prefix.clone())
+    })
+}
+
+/// Edits an activity's roster message to match the activity.
+pub(crate) async fn refresh_roster(
+    ctx: &Context,
+    activity: &mut Activity,
+    prefix: &str,
+) -> serenity::Result<()> {
+    let activity_embed = activity.as_create_embed(0x212121, prefix);
+
+    activity
+        .embed_msg
+        .edit(ctx, |msg| {
+            msg.embed(|embed| {
+                *embed = activity_embed;
+                embed
+            })
+        })
+        .await
+}
+
+/// Adds a new activity to a guild, giving it the next free ID. If the guild has a roster board,
+/// the roster is moved there from the activity's `embed_msg`.
+pub(crate) async fn register_activity(
+    ctx: &Context,
+    guild_id: GuildId,
+    actor: Actor,
+    activity: Activity,
+) -> Result<u64, ActivityUpdateError> {
+    let activity = store_activity(
+        &SerenityDiscord::new(ctx),
+        &ctx.data,
+        guild_id,
+        actor,
+        activity,
+    )
+    .await?;
+
+    let activity_id = activity.id;
+
+    events::publish(ctx, ActivityEvent::Created { guild_id, activity }).await;
+
+    Ok(activity_id)
+}
+
+/// Stores a new activity, moving its roster to the roster board if the guild has one so that it
+/// doesn't get buried under the messages in the setup channel. If the activity can't be stored or
+/// its roster can't be posted, the activity and its message are removed again, so a failed create
+/// leaves nothing behind. The TypeMap is only locked while the guild data is read or changed, not
+/// while messages are sent.
+async fn store_activity<D: Discord>(
+    discord: &D,
+    data: &RwLock<TypeMap>,
+    guild_id: GuildId,
+    actor: Actor,
+    activity: Activity,
+) -> Result<Activity, ActivityUpdateError> {
+    let placeholder = activity.embed_msg.clone();
+
+    let created = {
+        let mut type_map = data.write().await;
+        let prefix = resolve_prefix(&type_map, Some(guild_id)).unwrap_or_default();
+        let guild_data = guild_data_mut(&mut type_map, guild_id);
+
+        ActivityService::new(guild_data)
+            .create(actor, activity)
+            .map(|activity| (activity, guild_data.settings().roster_channel, prefix))
+    };
+
+    let (mut activity, roster_channel, prefix) = match created {
+        Ok(created) => created,
+        Err(e) => {
+            let _ = discord.delete(&placeholder).await;
+
+            return Err(e);
+        }
+    };
+
+    let roster_channel = match roster_channel {
+        Some(channel) if channel != activity.embed_msg.channel_id => channel,
+        _ => return Ok(activity),
+    };
+
+    let new_msg = match discord
+        .send_embed(roster_channel, activity.as_create_embed(0x212121, &prefix))
+        .await
+    {
+        Ok(msg) => msg,
+        Err(e) => {
+            let mut type_map = data.write().await;
+            let guild_data = guild_data_mut(&mut type_map, guild_id);
+            let still_stored = guild_data
+                .activity(activity.id)
+                .is_some_and(|stored| stored.embed_msg.id == placeholder.id);
+
+            if still_stored {
+                guild_data.remove_activity(activity.id);
+            }
+
+            drop(type_map);
+
+            let _ = discord.delete(&placeholder).await;
+
+            return Err(e.into());
+        }
+    };
+
+    let old_msg = std::mem::replace(&mut activity.embed_msg, new_msg.clone());
+
+    {
+        let mut type_map = data.write().await;
+        let guild_data = guild_data_mut(&mut type_map, guild_id);
+
+        if let Some(stored) = guild_data
+            .activity_mut(activity.id)
+            .filter(|stored| stored.embed_msg.id == old_msg.id)
+        {
+            stored.embed_msg = new_msg;
+        }
+    }
+
+    // The roster has already moved, so the activity works even if its old message stays.
+    if let Err(e) = discord.delete(&old_msg).await {
+        warn!(activity_id = activity.id, error = %e, "Failed to delete an old roster message");
+    }
+
+    Ok(activity)
+}
+
+/// Posts a placeholder for an activity created without the setup embeds. It's replaced by the
+/// roster once the activity is registered.
+pub(crate) async fn post_placeholder_roster(
+    ctx: &Context,
+    channel_id: ChannelId,
+    name: &str,
+) -> serenity::Result<Message> {
+    channel_id
+        .send_message(ctx, |msg| {
+            msg.embed(|embed| embed.title(name).description("Creating activity..."))
+        })
+        .await
+}
+
+/// Creates an activity from the guild's template named `template_name`, posting its roster in
+/// `channel_id`. Returns the new activity's ID.
+pub(crate) async fn create_from_template(
     ctx: &Context,
-    type_map: &mut TypeMap,
     guild_id: GuildId,
+    creator: UserId,
+    channel_id: ChannelId,
+    template_name: &str,
+    start: StartTime,
+) -> Result<u64, ActivityUpdateError> {
+    let template = ctx
+        .data
+        .read()
+        .await
+        .get::<data_keys::GetGuildData>()
+        .and_then(|guild_map| guild_map.get(&guild_id.0))
+        .and_then(|guild_data| guild_data.template(template_name))
+        .cloned()
+        .ok_or(ActivityUpdateError::InvalidTemplate)?;
+
+    if start.until().is_none() {
+        return Err(ActivityUpdateError::InvalidStart);
+    }
+
+    let embed_msg = post_placeholder_roster(ctx, channel_id, &template.activity_name).await?;
+    let activity = template.to_activity(start, creator, embed_msg);
+
+    register_activity(ctx, guild_id, Actor::Member(creator), activity).await
+}
+
+/// Turns the reminder before an activity starts on or off. Reminders that are already scheduled
+/// check this when they're due, so nothing has to be rescheduled.
+pub(crate) async fn set_activity_reminder(
+    ctx: &Context,
+    guild_id: GuildId,
+    actor: Actor,
+    activity_id: u64,
+    reminder: bool,
+) -> Result<Activity, ActivityUpdateError> {
+    with_service(ctx, guild_id, |service| {
+        service.set_reminder(actor, activity_id, reminder)
+    })
+    .await
+}
+
+/// Changes an activity's start time and description, and reschedules it.
+pub(crate) async fn edit_activity(
+    ctx: &Context,
+    guild_id: GuildId,
+    actor: Actor,
+    activity_id: u64,
+    start: StartTime,
+    description: String,
+) -> Result<Activity, ActivityUpdateError> {
+    let activity = with_service(ctx, guild_id, |service| {
+        service.edit(actor, activity_id, start, description)
+    })
+    .await?;
+
+    events::publish(
+        ctx,
+        ActivityEvent::Edited {
+            guild_id,
+            activity: activity.clone(),
+        },
+    )
+    .await;
+
+    Ok(activity)
+}
+
+/// Deletes a prompt that was given up on, like `Wizard::clean_up` does for the setup embeds.
+async fn discard_prompt<D: Discord>(discord: &D, prompt: &Message) {
+    if let Err(e) = discord.delete(prompt).await {
+        warn!(error = %e, "Failed to delete an unfinished activity edit");
+    }
+}
+
+/// Asks `original_msg`'s author for a new start time and description, then edits the activity.
+/// This is shared by `activity edit` and `admin activity edit`, which only differ in `actor`.
+pub(crate) async fn prompt_activity_edit(
+    ctx: &Context,
+    original_msg: &Message,
+    guild_id: GuildId,
+    actor: Actor,
     activity_id: u64,
+) -> CommandResult {
+    let embed_map = match ctx.data.read().await.get::<data_keys::GetEmbedMap>() {
+        Some(map) => map.clone(),
+        None => {
+            original_msg
+                .channel_id
+                .say(ctx, "Embed map was not registered.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let allowed = with_service(ctx, guild_id, |service| {
+        service
+            .managed(actor, activity_id)
+            .map(|_| service.settings().timeout())
+    })
+    .await;
+
+    let timeout = match allowed {
+        Ok(timeout) => timeout,
+        Err(ActivityUpdateError::NotAllowed) => {
+            send_error_message(ctx, original_msg, "You cannot edit that activity.").await?;
+            return Ok(());
+        }
+        Err(_) => {
+            send_error_message(ctx, original_msg, "Invalid activity ID.").await?;
+            return Ok(());
+        }
+    };
+
+    let discord = SerenityDiscord::new(ctx);
+    let channel = original_msg.channel_id;
+    let author = original_msg.author.id;
+
+    let time_embed = match embed_map.get("activity_roster_time") {
+        Some(embed) => embed,
+        None => {
+            return Err(EmbedError::Missing(String::from("activity_roster_time")).into());
+        }
+    };
+
+    let mut embed_msg = discord
+        .send_embed(channel, create_embed(&time_embed.embed))
+        .await?;
+
+    let start_time = match prompt_start_time(&discord, channel, author, Some(timeout)).await? {
+        Some(start_time) => start_time,
+        None => {
+            discard_prompt(&discord, &embed_msg).await;
+            return Ok(());
+        }
+    };
+
+    let description_embed = match embed_map.get("activity_roster_description") {
+        Some(embed) => embed,
+        None => {
+            return Err(EmbedError::Missing(String::from("activity_roster_description")).into());
+        }
+    };
+
+    discord
+        .edit_embed(&mut embed_msg, create_embed(&description_embed.embed))
+        .await?;
+
+    let description = match prompt_description(&discord, channel, author, Some(timeout)).await? {
+        Some(description) => description,
+        None => {
+            discard_prompt(&discord, &embed_msg).await;
+            return Ok(());
+        }
+    };
+
+    discord.delete(&embed_msg).await?;
+
+    let error =
+        match edit_activity(ctx, guild_id, actor, activity_id, start_time, description).await {
+            Ok(activity) => {
+                original_msg
+                    .channel_id
+                    .say(
+                        ctx,
+                        format!(
+                            "Activity {} ({}) updated successfully.",
+                            activity.id, activity.name
+                        ),
+                    )
+                    .await?;
+                return Ok(());
+            }
+            Err(ActivityUpdateError::InvalidStart) => {
+                "Invalid date and time. Please enter a valid date and time that is in the future."
+            }
+            Err(ActivityUpdateError::NotAllowed) => "You cannot edit that activity.",
+            Err(ActivityUpdateError::Serenity(e)) => return Err(e.into()),
+            Err(_) => "That activity was removed while it was being edited.",
+        };
+
+    send_error_message(ctx, original_msg, error).await
+}
+
+/// What a user picked while creating an activity.
+#[derive(Clone, Debug)]
+pub(crate) struct NewActivity {
+    pub roster: RosterData,
+    pub start: StartTime,
+    pub description: String,
+}
+
+/// Walks `author` through creating an activity in `channel` with the wizard, starting at the
+/// `start_menu` in `embed_map`. Returns `None` if the user cancelled or stopped replying, after
+/// telling them so.
+pub(crate) async fn prompt_new_activity(
+    discord: &dyn Discord,
+    embed_map: &HashMap<String, EmbedWithMeta>,
+    start_menu: &str,
+    channel: ChannelId,
+    author: UserId,
+    timeout: Duration,
+) -> CommandResult<Option<NewActivity>> {
+    Wizard::new(discord, embed_map, channel, author, Some(timeout))
+        .run(start_menu)
+        .await
+}
+
+/// Asks the user to try another start time.
+pub(crate) const START_TIME_HINT: &str =
+    "Please enter a valid date and time in the format `mm/dd/yyyy hh:mm am|pm`, or a shorter one like `tue 8pm`";
+
+/// Asks the user to try a shorter description.
+pub(crate) const DESCRIPTION_HINT: &str =
+    "Please enter a description that is less than or equal to 1024 characters.";
+
+/// Asks the user for a description when their reply had no text, like a reply with only an image.
+pub(crate) const EMPTY_DESCRIPTION_HINT: &str =
+    "Please enter a description for the activity, like what to bring or the plan for it.";
+
+/// Returns the hint to show if `description` can't be used for an activity.
+pub(crate) fn description_hint(description: &str) -> Option<&'static str> {
+    if description.trim().is_empty() {
+        Some(EMPTY_DESCRIPTION_HINT)
+    } else if description.len() > 1024 {
+        Some(DESCRIPTION_HINT)
+    } else {
+        None
+    }
+}
+
+/// Waits for `author` to send a valid date and time in `channel`, asking again until they do.
+/// Returns `None` if they stopped replying.
+pub(crate) async fn prompt_start_time(
+    discord: &dyn Discord,
+    channel: ChannelId,
+    author: UserId,
+    timeout: Option<Duration>,
+) -> CommandResult<Option<StartTime>> {
+    loop {
+        let time_message = match discord.await_reply(channel, author, timeout).await {
+            Some(message) => message,
+            None => {
+                send_timeout_message(discord, channel, timeout).await?;
+                return Ok(None);
+            }
+        };
+
+        match parse_start(&time_message.content) {
+            Some(start_time) => {
+                discord.delete(&time_message).await?;
+                return Ok(Some(start_time));
+            }
+            None => {
+                discord.say(channel, START_TIME_HINT.into()).await?;
+            }
+        }
+    }
+}
+
+/// Waits for `author` to send a description of at most 1024 characters in `channel`, asking
+/// again until they do. Empty replies are asked again too. Returns `None` if they stopped
+/// replying.
+pub(crate) async fn prompt_description(
+    discord: &dyn Discord,
+    channel: ChannelId,
+    author: UserId,
+    timeout: Option<Duration>,
+) -> CommandResult<Option<String>> {
+    loop {
+        let description_message = match discord.await_reply(channel, author, timeout).await {
+            Some(message) => message,
+            None => {
+                send_timeout_message(discord, channel, timeout).await?;
+                return Ok(None);
+            }
+        };
+
+        let safe_content = discord.content_safe(&description_message).await;
+
+        match description_hint(&safe_content) {
+            None => {
+                discord.delete(&description_message).await?;
+                return Ok(Some(safe_content));
+            }
+            Some(hint) => discord.say(channel, hint.into()).await?,
+        };
+    }
+}
+
+pub(crate) async fn send_timeout_message(
+    discord: &dyn Discord,
+    channel: ChannelId,
+    timeout: Option<Duration>,
 ) -> serenity::Result<()> {
-    if let Some(guild_map) = type_map.get_mut::<data_keys::GetGuildData>() {
-        if let Some(guild_data) = guild_map.get_mut(&guild_id.0) {
-            if let Some(activity) = guild_data.remove_activity(activity_id) {
-                if !activity.members.is_empty() {
-                    let member_count = activity.members.len();
-
-                    let mention_string = activity
-                        .members
-                        .into_iter()
-                        .enumerate()
-                        .map(|(idx, user)| {
-                            if idx == 0 {
-                                Mention::from(user).to_string()
-                            } else if idx == member_count - 1 {
-                                format!(", and {}", Mention::from(user))
-                            } else {
-                                format!(", {}", Mention::from(user))
-                            }
-                        })
-                        .collect::<String>();
+    let error = match timeout {
+        Some(duration) => format!(
+            "You did not send a reply in time. Please reply within {} minutes",
+            duration.as_secs() / 60
+        ),
+        None => "Stopped waiting for a reply. Please try again.".into(),
+    };
 
-                    let content = format!(
-                        "Hey {}! {} is starting now. Good luck and have fun!",
-                        mention_string, activity.name
-                    );
+    discord.say(channel, error).await.map(|_| ())
+}
 
-                    activity.embed_msg.channel_id.say(ctx, content).await?;
-                }
+/// How long before an activity starts that its members are reminded about it.
+pub(crate) const REMINDER_LEAD: Duration = Duration::from_secs(15 * 60);
+
+/// Starts the activity after `until_start` has passed, unless it is cancelled first.
+pub(crate) fn schedule_start(
+    ctx: &Context,
+    guild_id: GuildId,
+    activity_id: u64,
+    until_start: Duration,
+    cancel_future: CancelActivity,
+) {
+    let ctx = ctx.clone();
+
+    tokio::spawn(async move {
+        let _pending = metrics::PendingTimer::start();
+
+        let timer = async {
+            tokio::time::sleep(until_start).await;
+
+            if let Err(e) = start_activity(&ctx, guild_id, Actor::System, activity_id).await {
+                error!(guild_id = %guild_id, activity_id, error = %e, "Failed to start activity");
             }
+        };
+
+        tokio::select! {
+            _ = cancel_future => (),
+            _ = timer => (),
         }
+    });
+}
+
+/// Adds a user to an activity's main fireteam in `role`, or to its alternates if `alternate` is
+/// true. Returns a description of any overlapping activities that the user is already in.
+pub(crate) async fn join_activity(
+    ctx: &Context,
+    guild_id: GuildId,
+    actor: Actor,
+    activity_id: u64,
+    user_id: UserId,
+    alternate: bool,
+    role: Option<&str>,
+) -> Result<String, ActivityUpdateError> {
+    let joined = with_service(ctx, guild_id, |service| {
+        service.join(actor, activity_id, user_id, alternate, role)
+    })
+    .await?;
+
+    events::publish(
+        ctx,
+        ActivityEvent::Joined {
+            guild_id,
+            activity: joined.activity,
+            user_id,
+            alternate,
+        },
+    )
+    .await;
+
+    Ok(joined.conflicts)
+}
+
+/// Removes a user from either the main fireteam or the alternates of an activity.
+pub(crate) async fn leave_activity(
+    ctx: &Context,
+    guild_id: GuildId,
+    actor: Actor,
+    activity_id: u64,
+    user_id: UserId,
+) -> Result<(), ActivityUpdateError> {
+    let activity = with_service(ctx, guild_id, |service| {
+        service.leave(actor, activity_id, user_id)
+    })
+    .await?;
+
+    events::publish(
+        ctx,
+        ActivityEvent::Left {
+            guild_id,
+            activity,
+            user_id,
+        },
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Removes an activity and cancels its start.
+pub(crate) async fn delete_activity(
+    ctx: &Context,
+    guild_id: GuildId,
+    actor: Actor,
+    activity_id: u64,
+) -> Result<Activity, ActivityUpdateError> {
+    let activity =
+        with_service(ctx, guild_id, |service| service.delete(actor, activity_id)).await?;
+
+    events::publish(
+        ctx,
+        ActivityEvent::Deleted {
+            guild_id,
+            activity: activity.clone(),
+        },
+    )
+    .await;
+
+    Ok(activity)
+}
+
+/// Starts an activity now. This also stops the activity's start timer if it's being started
+/// early. The fireteam is told by `events::ReminderSubscriber`.
+pub(crate) async fn start_activity(
+    ctx: &Context,
+    guild_id: GuildId,
+    actor: Actor,
+    activity_id: u64,
+) -> Result<(), ActivityUpdateError> {
+    let activity = with_service(ctx, guild_id, |service| service.start(actor, activity_id)).await?;
+
+    events::publish(ctx, ActivityEvent::Started { guild_id, activity }).await;
+
+    Ok(())
+}
+
+/// Runs `f` with an `ActivityService` for the guild, holding the TypeMap lock only for as long
+/// as `f` runs.
+pub(crate) async fn with_service<T, F>(ctx: &Context, guild_id: GuildId, f: F) -> T
+where
+    F: FnOnce(&mut ActivityService<'_>) -> T,
+{
+    let mut type_map = ctx.data.write().await;
+
+    f(&mut ActivityService::new(guild_data_mut(
+        &mut type_map,
+        guild_id,
+    )))
+}
+
+/// A guild's data in a locked TypeMap, added if the guild doesn't have any yet.
+pub(crate) fn guild_data_mut(type_map: &mut TypeMap, guild_id: GuildId) -> &mut GuildData {
+    type_map
+        .entry::<data_keys::GetGuildData>()
+        .or_default()
+        .entry(guild_id.0)
+        .or_insert_with(|| GuildData::new(guild_id))
+}
+
+/// Brings a guild's roster board in line with its activities. The board message is edited in
+/// place when possible, and is re-sent if it was deleted or the roster channel has changed.
+/// Callers must not hold the TypeMap lock, since it's taken here to read and save the board.
+pub(crate) async fn update_roster_board(ctx: &Context, guild_id: GuildId) -> serenity::Result<()> {
+    let (old_board, roster_channel, board_embed) = {
+        let type_map = ctx.data.read().await;
+        let guild_data = match type_map
+            .get::<data_keys::GetGuildData>()
+            .and_then(|guild_data_map| guild_data_map.get(&guild_id.0))
+        {
+            Some(guild_data) => guild_data,
+            None => return Ok(()),
+        };
+
+        (
+            guild_data.roster_board().cloned(),
+            guild_data.settings().roster_channel,
+            guild_data.as_board_embed(0x212121),
+        )
+    };
+
+    let old_board_id = old_board.as_ref().map(|board| board.id);
+    let board = post_roster_board(ctx, old_board, roster_channel, board_embed).await;
+
+    let mut type_map = ctx.data.write().await;
+    let guild_data = guild_data_mut(&mut type_map, guild_id);
+
+    // Another update may have replaced the board while this one was being posted, in which
+    // case that board is kept and this one is removed.
+    if guild_data.roster_board().map(|board| board.id) != old_board_id {
+        drop(type_map);
+
+        if let Ok(Some(board)) = board {
+            if Some(board.id) != old_board_id {
+                let _ = board.delete(ctx).await;
+            }
+        }
+
+        return Ok(());
+    }
+
+    guild_data.set_roster_board(board?);
+
+    Ok(())
+}
+
+/// Edits, replaces or deletes `old_board` to match `board_embed`, returning the board that's
+/// now posted in `roster_channel`, if any.
+async fn post_roster_board(
+    ctx: &Context,
+    old_board: Option<Message>,
+    roster_channel: Option<ChannelId>,
+    board_embed: CreateEmbed,
+) -> serenity::Result<Option<Message>> {
+    let roster_channel = match roster_channel {
+        Some(channel) => channel,
+        None => {
+            if let Some(old_board) = old_board {
+                old_board.delete(ctx).await?;
+            }
+            return Ok(None);
+        }
+    };
+
+    if let Some(mut board) = old_board {
+        if board.channel_id == roster_channel {
+            let edit_board_embed = board_embed.clone();
+            let res = board
+                .edit(ctx, |msg| {
+                    msg.embed(|embed| {
+                        *embed = edit_board_embed;
+                        embed
+                    })
+                })
+                .await;
+
+            if res.is_ok() {
+                return Ok(Some(board));
+            }
+        }
+
+        // The old board is either in the wrong channel or could not be edited. Either way it
+        // will be replaced, so a failed delete isn't worth reporting.
+        let _ = board.delete(ctx).await;
+    }
+
+    let board = roster_channel
+        .send_message(ctx, |msg| msg.set_embed(board_embed))
+        .await?;
+
+    Ok(Some(board))
+}
+
+const PREVIOUS_PAGE: &str = "◀️";
+const NEXT_PAGE: &str = "▶️";
+
+/// Sends `pages` as a single message that `user` can flip through with reactions. The controls
+/// are removed once `timeout` passes without a reaction.
+pub(crate) async fn send_paginated(
+    ctx: &Context,
+    channel: ChannelId,
+    user: UserId,
+    mut pages: Vec<CreateEmbed>,
+    mut page: usize,
+    timeout: Duration,
+) -> serenity::Result<()> {
+    let page_count = pages.len();
+
+    if page_count == 0 {
+        return Ok(());
+    }
+
+    for (idx, page_embed) in pages.iter_mut().enumerate() {
+        page_embed.footer(|footer| footer.text(format!("Page {}/{}", idx + 1, page_count)));
     }
 
+    page = page.min(page_count - 1);
+
+    let first_page = pages[page].clone();
+    let mut message = channel
+        .send_message(ctx, |msg| msg.set_embed(first_page))
+        .await?;
+
+    if page_count == 1 {
+        return Ok(());
+    }
+
+    message
+        .react(ctx, ReactionType::Unicode(PREVIOUS_PAGE.into()))
+        .await?;
+    message
+        .react(ctx, ReactionType::Unicode(NEXT_PAGE.into()))
+        .await?;
+
+    while let Some(action) = message
+        .await_reaction(ctx)
+        .author_id(user)
+        .timeout(timeout)
+        .await
+    {
+        if let ReactionAction::Added(reaction) = &*action {
+            let new_page = match reaction.emoji.as_data().as_str() {
+                PREVIOUS_PAGE => Some((page + page_count - 1) % page_count),
+                NEXT_PAGE => Some((page + 1) % page_count),
+                _ => None,
+            };
+
+            // Removing the user's reaction lets them press the same control again. This needs
+            // the Manage Messages permission, so a failure here isn't treated as an error.
+            let _ = reaction.delete(ctx).await;
+
+            if let Some(new_page) = new_page {
+                page = new_page;
+                let page_embed = pages[page].clone();
+
+                message
+                    .edit(ctx, |msg| {
+                        msg.embed(|embed| {
+                            *embed = page_embed;
+                            embed
+                        })
+                    })
+                    .await?;
+            }
+        }
+    }
+
+    let _ = message.delete_reactions(ctx).await;
+
     Ok(())
 }
 
@@ -212,11 +1353,16 @@ pub mod data_keys {
     use std::collections::{HashMap, HashSet};
 
     use serenity::{
+        framework::standard::CommandGroup,
         model::prelude::{EmojiId, UserId},
         prelude::TypeMapKey,
     };
 
-    use crate::{client::JsonPaths, command::data::EmbedWithMeta, command::data::GuildData};
+    use crate::{
+        client::JsonPaths, command::data::EmbedWithMeta, command::data::GuildData,
+        command::data::UserSettings, command::events::EventBus, command::report::ReportConfig,
+        config::ConfigSource, reload::RuntimeConfig,
+    };
 
     #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
     pub struct GetEmojiMap;
@@ -252,4 +1398,745 @@ pub mod data_keys {
     impl TypeMapKey for GetAdmins {
         type Value = HashSet<UserId>;
     }
+
+    #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+    pub struct GetRuntimeConfig;
+
+    impl TypeMapKey for GetRuntimeConfig {
+        type Value = RuntimeConfig;
+    }
+
+    #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+    pub struct GetConfigSource;
+
+    impl TypeMapKey for GetConfigSource {
+        type Value = ConfigSource;
+    }
+
+    #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+    pub struct GetEventBus;
+
+    impl TypeMapKey for GetEventBus {
+        type Value = EventBus;
+    }
+
+    pub struct GetCommandGroups;
+
+    impl TypeMapKey for GetCommandGroups {
+        type Value = Vec<&'static CommandGroup>;
+    }
+
+    pub struct GetReportConfig;
+
+    impl TypeMapKey for GetReportConfig {
+        type Value = ReportConfig;
+    }
+
+    #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+    pub struct GetUserSettings;
+
+    impl TypeMapKey for GetUserSettings {
+        type Value = HashMap<UserId, UserSettings>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::data::RoleSlot;
+    use crate::command::testing::{self, FakeDiscord, Sent};
+    use crate::util::ActivityToken;
+    use chrono::Timelike;
+    use serenity::framework::standard::Delimiter;
+    use serenity::model::id::MessageId;
+
+    const CHANNEL: ChannelId = ChannelId(10);
+    const AUTHOR: UserId = UserId(2);
+    const OTHER: UserId = UserId(3);
+    const TIMEOUT: Duration = Duration::from_secs(300);
+
+    fn embed_map() -> HashMap<String, EmbedWithMeta> {
+        serde_json::from_str(include_str!("../../config/embeds.json")).expect("valid embeds.json")
+    }
+
+    async fn create(discord: &FakeDiscord) -> CommandResult<Option<NewActivity>> {
+        let embed_map = embed_map();
+
+        prompt_new_activity(
+            discord,
+            &embed_map,
+            "activity_roster_start",
+            CHANNEL,
+            AUTHOR,
+            TIMEOUT,
+        )
+        .await
+    }
+
+    #[test]
+    fn parse_date_time_reads_12_hour_times_in_eastern_time() {
+        let start = parse_date_time("12/25/2099 8:30 PM").unwrap();
+
+        assert_eq!(
+            (
+                start.date_time.year(),
+                start.date_time.month(),
+                start.date_time.day()
+            ),
+            (2099, 12, 25)
+        );
+        assert_eq!((start.date_time.hour(), start.date_time.minute()), (20, 30));
+        assert_eq!(start.date_time.offset().utc_minus_local(), 5 * 3600);
+        assert_eq!(start.input, "12/25/2099 8:30 pm");
+
+        assert_eq!(
+            parse_date_time("1/1/2099 12:00 am")
+                .unwrap()
+                .date_time
+                .hour(),
+            0
+        );
+        assert_eq!(
+            parse_date_time("1/1/2099 12:00 pm")
+                .unwrap()
+                .date_time
+                .hour(),
+            12
+        );
+    }
+
+    #[test]
+    fn parse_date_time_rejects_invalid_input() {
+        let invalid = [
+            "",
+            "12/25/2099",
+            "12/25/2099 8:30",
+            "12/25/2099 8:30 xm",
+            "12/25/2099 8:30 pm tomorrow",
+            "13/1/2099 8:30 pm",
+            "0/1/2099 8:30 pm",
+            "4/31/2099 8:30 pm",
+            "2/29/2099 8:30 pm",
+            "12/0/2099 8:30 pm",
+            "12/25/2000 8:30 pm",
+            "12/25/2100 8:30 pm",
+            "12/25/2099/1 8:30 pm",
+            "12/25/2099 0:30 am",
+            "12/25/2099 13:30 pm",
+            "12/25/2099 8:60 pm",
+            "12/25/2099 8:30:15 pm",
+            "tuesday 8:30 pm",
+        ];
+
+        for input in invalid.iter() {
+            assert!(parse_date_time(input).is_none(), "{:?} was accepted", input);
+        }
+    }
+
+    #[test]
+    fn casual_start_times_are_relative_to_now() {
+        // Tuesday, December 22nd 2099 at 6:00 PM in UTC-5.
+        let now = Utc.with_ymd_and_hms(2099, 12, 22, 23, 0, 0).unwrap();
+        let parse = |input: &str| {
+            parse_casual_date_time(input, now).map(|start| {
+                let date_time = start.date_time;
+
+                (
+                    date_time.day(),
+                    date_time.hour(),
+                    date_time.minute(),
+                    date_time.offset().utc_minus_local(),
+                )
+            })
+        };
+
+        assert_eq!(parse("tue 8pm"), Some((22, 20, 0, 5 * 3600)));
+        assert_eq!(parse("Tuesday 8:30 PM"), Some((22, 20, 30, 5 * 3600)));
+        // Tuesday at 9 AM has passed, so it's next week's.
+        assert_eq!(parse("tue 9am"), Some((29, 9, 0, 5 * 3600)));
+        assert_eq!(parse("thurs 12am"), Some((24, 0, 0, 5 * 3600)));
+        assert_eq!(parse("tomorrow 12pm"), Some((23, 12, 0, 5 * 3600)));
+        assert_eq!(parse("7pm"), Some((22, 19, 0, 5 * 3600)));
+
+        for invalid in [
+            "",
+            "tue",
+            "8",
+            "tue 8",
+            "tue 13pm",
+            "tue 0am",
+            "tue 8:5pm",
+            "fri 8pm sharp",
+            "someday 8pm",
+        ] {
+            assert_eq!(parse(invalid), None, "{:?} should be invalid", invalid);
+        }
+
+        assert_eq!(
+            parse_start("12/25/2099 8:00 pm").unwrap().input,
+            "12/25/2099 8:00 pm"
+        );
+    }
+
+    #[test]
+    fn quick_activities_must_be_in_embeds_json() {
+        let known = KnownActivity::from_embed_map(&embed_map());
+
+        let vog = resolve_known_activity(&known, "vault of glass (master)").unwrap();
+        assert_eq!(vog.name, "Vault of Glass (Master)");
+        assert_eq!(vog.roles.len(), 3);
+
+        // Both versions of Vault of Glass are set up the same way, so either is fine.
+        let vog = resolve_known_activity(&known, "Vault of Glass").unwrap();
+        assert_eq!(vog.name, "Vault of Glass");
+        assert_eq!(vog.duration, Some(Duration::from_secs(180 * 60)));
+
+        assert!(resolve_known_activity(&known, "Vault").is_err());
+        assert!(resolve_known_activity(&known, "").is_err());
+
+        // Versions that are set up differently have to be picked by their full name.
+        let timed = |name: &str, minutes: u64| KnownActivity {
+            name: name.to_string(),
+            size: 6,
+            duration: Some(Duration::from_secs(minutes * 60)),
+            roles: Vec::new(),
+        };
+        let versions = [timed("Raid (Normal)", 60), timed("Raid (Hard)", 90)];
+
+        assert!(resolve_known_activity(&versions, "Raid").is_err());
+        assert_eq!(
+            resolve_known_activity(&versions, "raid (hard)").unwrap(),
+            versions[1]
+        );
+        assert_eq!(
+            known
+                .iter()
+                .filter(|activity| activity.name == "Control")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn dm_commands_find_the_guilds_with_an_activity() {
+        let guild_with = |guild_id: u64, activity_ids: &[u64]| {
+            let mut guild_data = GuildData::new(GuildId(guild_id));
+
+            for &id in activity_ids {
+                let activity = Activity::new(
+                    "Vault of Glass",
+                    "",
+                    parse_date_time("12/25/2099 8:00 pm").unwrap(),
+                    id,
+                    6,
+                    AUTHOR,
+                    crate::command::testing::message(id, CHANNEL, AUTHOR, ""),
+                    crate::util::ActivityToken::new(),
+                );
+                guild_data.add_activity(activity).unwrap();
+            }
+
+            (guild_id, guild_data)
+        };
+
+        let guild_map = vec![
+            guild_with(1, &[0, 1]),
+            guild_with(2, &[0]),
+            guild_with(3, &[]),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            guilds_with_activity(&guild_map, 0, None),
+            vec![GuildId(1), GuildId(2)]
+        );
+        assert_eq!(guilds_with_activity(&guild_map, 1, None), vec![GuildId(1)]);
+        assert_eq!(
+            guilds_with_activity(&guild_map, 0, Some(GuildId(2))),
+            vec![GuildId(2)]
+        );
+        assert!(guilds_with_activity(&guild_map, 1, Some(GuildId(2))).is_empty());
+        assert!(guilds_with_activity(&guild_map, 5, None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn create_walks_the_reaction_chain_and_prompts() {
+        let discord = FakeDiscord::new();
+        discord
+            .user_reacts(OTHER, "3️⃣")
+            .user_reacts(AUTHOR, "1️⃣")
+            .user_reacts(AUTHOR, "4️⃣")
+            .user_reacts(AUTHOR, "2️⃣")
+            .user_reacts(AUTHOR, "1️⃣")
+            .user_says(OTHER, "12/24/2099 1:00 pm")
+            .user_says(AUTHOR, "tomorrow night")
+            .user_says(AUTHOR, "12/25/2099 8:00 pm")
+            .user_says(AUTHOR, "Bring your relics")
+            .user_reacts(AUTHOR, "✅");
+
+        let new_activity = create(&discord).await.unwrap().unwrap();
+
+        assert_eq!(new_activity.roster.activity_name, "Vault of Glass (Legend)");
+        assert_eq!(new_activity.roster.size, 6);
+        assert_eq!(
+            new_activity.roster.duration,
+            Some(Duration::from_secs(180 * 60))
+        );
+        assert_eq!(
+            new_activity
+                .roster
+                .roles
+                .iter()
+                .map(|role| (role.name.as_str(), role.capacity))
+                .collect::<Vec<_>>(),
+            vec![("Sword", 2), ("Well", 1), ("DPS", 3)]
+        );
+        assert_eq!(new_activity.start.input, "12/25/2099 8:00 pm");
+        assert_eq!(new_activity.description, "Bring your relics");
+        assert!(discord.script_finished());
+
+        let sent = discord.sent();
+        let roster_id = new_activity.roster.message.id;
+
+        // The setup happens in a single message, which is edited for each step.
+        assert!(
+            matches!(&sent[0], Sent::Embed { id, channel, .. } if *id == roster_id && *channel == CHANNEL)
+        );
+        assert_eq!(
+            sent.iter()
+                .filter(|sent| matches!(sent, Sent::Embed { .. }))
+                .count(),
+            1
+        );
+        assert_eq!(
+            sent.iter()
+                .filter(|sent| matches!(sent, Sent::Edit { id, .. } if *id == roster_id))
+                .count(),
+            6
+        );
+        assert!(!sent.contains(&Sent::Delete(roster_id)));
+        assert_eq!(discord.said(), vec![START_TIME_HINT]);
+    }
+
+    #[tokio::test]
+    async fn create_can_go_back_in_the_chain() {
+        let discord = FakeDiscord::new();
+        discord
+            .user_reacts(AUTHOR, "1️⃣")
+            .user_reacts(AUTHOR, "⬅️")
+            .user_reacts(AUTHOR, "2️⃣")
+            .user_says(AUTHOR, "12/25/2099 8:00 pm")
+            .user_says(AUTHOR, "Quiplash")
+            .user_reacts(AUTHOR, "✅");
+
+        let new_activity = create(&discord).await.unwrap().unwrap();
+
+        assert_eq!(new_activity.roster.activity_name, "Jackbox");
+        assert_eq!(new_activity.roster.size, 8);
+        assert_eq!(new_activity.roster.duration, None);
+    }
+
+    #[tokio::test]
+    async fn create_reacts_with_each_choice() {
+        let discord = FakeDiscord::new();
+        discord.user_reacts(AUTHOR, "2️⃣");
+
+        let _ = create(&discord).await;
+
+        let reactions = discord
+            .sent()
+            .into_iter()
+            .filter_map(|sent| match sent {
+                Sent::React { reaction, .. } => Some(reaction),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // The first menu can only be cancelled, and later steps can also go back.
+        assert_eq!(reactions, vec!["1️⃣", "2️⃣", "3️⃣", "❌", "⬅️", "❌"]);
+    }
+
+    #[tokio::test]
+    async fn create_stops_when_the_user_does_not_react() {
+        let discord = FakeDiscord::new();
+
+        assert!(create(&discord).await.is_err());
+        assert_eq!(
+            discord.said(),
+            vec!["You did not add a reaction in time. Please react within 300 seconds."]
+        );
+        assert_setup_deleted(&discord);
+    }
+
+    #[tokio::test]
+    async fn create_stops_when_a_reaction_is_removed() {
+        let discord = FakeDiscord::new();
+        discord.user_unreacts(AUTHOR, "1️⃣");
+
+        assert!(create(&discord).await.is_err());
+        assert_eq!(
+            discord.said(),
+            vec!["You removed a reaction before the bot was ready. Please try again."]
+        );
+        assert_setup_deleted(&discord);
+    }
+
+    #[tokio::test]
+    async fn create_stops_when_the_user_does_not_reply() {
+        let discord = FakeDiscord::new();
+        discord
+            .user_reacts(AUTHOR, "2️⃣")
+            .user_says(AUTHOR, "12/25/2099 8:00 pm");
+
+        assert!(create(&discord).await.unwrap().is_none());
+        assert_eq!(
+            discord.said(),
+            vec!["You did not send a reply in time. Please reply within 5 minutes"]
+        );
+        assert_setup_deleted(&discord);
+    }
+
+    /// Checks that the setup message, which is the first thing sent, was deleted last.
+    fn assert_setup_deleted(discord: &FakeDiscord) {
+        let sent = discord.sent();
+        let setup_id = match sent.first() {
+            Some(Sent::Embed { id, .. }) => *id,
+            other => panic!("expected the setup embed first, got {:?}", other),
+        };
+
+        assert_eq!(sent.last(), Some(&Sent::Delete(setup_id)));
+    }
+
+    #[tokio::test]
+    async fn create_can_be_cancelled_at_any_step() {
+        for steps in 0..4 {
+            let discord = FakeDiscord::new();
+            let script = [
+                (true, "2️⃣"),
+                (false, "12/25/2099 8:00 pm"),
+                (false, "Quiplash"),
+            ];
+
+            for &(is_reaction, input) in script.iter().take(steps) {
+                if is_reaction {
+                    discord.user_reacts(AUTHOR, input);
+                } else {
+                    discord.user_says(AUTHOR, input);
+                }
+            }
+
+            discord.user_reacts(AUTHOR, "❌");
+
+            assert!(create(&discord).await.unwrap().is_none());
+            assert_eq!(discord.said(), vec!["Cancelled creating the activity."]);
+            assert!(discord.script_finished());
+            assert_setup_deleted(&discord);
+        }
+    }
+
+    #[tokio::test]
+    async fn create_can_go_back_from_the_prompts_and_summary() {
+        let discord = FakeDiscord::new();
+        discord
+            .user_reacts(AUTHOR, "2️⃣")
+            .user_says(AUTHOR, "12/24/2099 8:00 pm")
+            // From the description back to the start time.
+            .user_reacts(AUTHOR, "⬅️")
+            .user_says(AUTHOR, "12/25/2099 8:00 pm")
+            .user_says(AUTHOR, "Quiplash")
+            // From the summary back to the description.
+            .user_reacts(AUTHOR, "⬅️")
+            .user_says(AUTHOR, "Drawful")
+            .user_reacts(AUTHOR, "✅");
+
+        let new_activity = create(&discord).await.unwrap().unwrap();
+
+        assert_eq!(new_activity.roster.activity_name, "Jackbox");
+        assert_eq!(new_activity.start.input, "12/25/2099 8:00 pm");
+        assert_eq!(new_activity.description, "Drawful");
+        assert!(discord.script_finished());
+    }
+
+    #[tokio::test]
+    async fn create_asks_for_a_start_time_in_the_future() {
+        let discord = FakeDiscord::new();
+        discord
+            .user_reacts(AUTHOR, "2️⃣")
+            .user_says(AUTHOR, "1/1/2001 8:00 pm")
+            .user_says(AUTHOR, "12/25/2099 8:00 pm")
+            .user_says(AUTHOR, "Drawful")
+            .user_reacts(AUTHOR, "✅");
+
+        let new_activity = create(&discord).await.unwrap().unwrap();
+
+        assert_eq!(new_activity.start.input, "12/25/2099 8:00 pm");
+        assert_eq!(
+            discord.said(),
+            vec!["Please enter a date and time that is in the future."]
+        );
+    }
+
+    #[tokio::test]
+    async fn create_reads_short_start_times() {
+        let discord = FakeDiscord::new();
+        discord
+            .user_reacts(AUTHOR, "2️⃣")
+            .user_says(AUTHOR, "tomorrow 8pm")
+            .user_says(AUTHOR, "Drawful")
+            .user_reacts(AUTHOR, "✅");
+
+        let new_activity = create(&discord).await.unwrap().unwrap();
+
+        assert_eq!(new_activity.start.input, "tomorrow 8pm");
+        assert!(discord.said().is_empty());
+    }
+
+    #[tokio::test]
+    async fn create_asks_again_for_an_empty_description() {
+        let discord = FakeDiscord::new();
+        discord
+            .user_reacts(AUTHOR, "2️⃣")
+            .user_says(AUTHOR, "12/25/2099 8:00 pm")
+            .user_says(AUTHOR, " ")
+            .user_says(AUTHOR, "Drawful")
+            .user_reacts(AUTHOR, "✅");
+
+        let new_activity = create(&discord).await.unwrap().unwrap();
+
+        assert_eq!(new_activity.description, "Drawful");
+        assert_eq!(discord.said(), vec![EMPTY_DESCRIPTION_HINT]);
+    }
+
+    fn quick_args(input: &str) -> Option<QuickArgs> {
+        parse_quick_args(Args::new(input, &[Delimiter::Single(' ')]))
+    }
+
+    #[test]
+    fn quick_activities_can_leave_out_the_description() {
+        let quick = quick_args(r#""Vault of Glass" 6 "tue 8pm""#).unwrap();
+
+        assert_eq!(quick.name, "Vault of Glass");
+        assert_eq!(quick.size, 6);
+        assert_eq!(quick.start, "tue 8pm");
+        assert_eq!(quick.description, "");
+
+        let activity = Activity::new(
+            quick.name,
+            quick.description,
+            parse_date_time("12/25/2099 8:00 PM").unwrap(),
+            0,
+            quick.size,
+            AUTHOR,
+            testing::message(50, CHANNEL, testing::BOT, ""),
+            ActivityToken::new(),
+        );
+        let embed = testing::embed_json(activity.as_create_embed(0x212121, "~"));
+
+        assert!(embed["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|field| field["name"] == "Description:" && field["value"] == "None"));
+
+        assert_eq!(
+            quick_args(r#""Vault of Glass" 6 "tue 8pm" Master run"#)
+                .unwrap()
+                .description,
+            "Master run"
+        );
+        assert_eq!(quick_args(r#""Vault of Glass" 0 "tue 8pm""#), None);
+    }
+
+    #[test]
+    fn quick_sizes_must_fit_the_activity() {
+        let known = KnownActivity {
+            name: String::from("Vault of Glass"),
+            size: 6,
+            duration: None,
+            roles: vec![RoleSlot {
+                name: String::from("Sword"),
+                capacity: 2,
+            }],
+        };
+
+        assert!(check_quick_size(&known, 6).is_ok());
+        assert!(check_quick_size(&known, 2).is_ok());
+        assert_eq!(
+            check_quick_size(&known, 7),
+            Err(String::from("Vault of Glass is for at most 6 players."))
+        );
+        assert_eq!(
+            check_quick_size(&known, 1),
+            Err(String::from(
+                "Vault of Glass needs at least 2 players to fill its roles."
+            ))
+        );
+    }
+
+    #[test]
+    fn guild_prefixes_override_the_default() {
+        let mut type_map = TypeMap::new();
+        type_map.insert::<data_keys::GetRuntimeConfig>(crate::reload::RuntimeConfig {
+            prefix: String::from("~"),
+            ..Default::default()
+        });
+
+        let mut guild_data = GuildData::new(GuildId(1));
+        guild_data.settings_mut().prefix = Some(String::from("!"));
+        type_map
+            .entry::<data_keys::GetGuildData>()
+            .or_default()
+            .insert(1, guild_data);
+
+        assert_eq!(
+            resolve_prefix(&type_map, Some(GuildId(1))).as_deref(),
+            Some("!")
+        );
+        assert_eq!(
+            resolve_prefix(&type_map, Some(GuildId(2))).as_deref(),
+            Some("~")
+        );
+        assert_eq!(resolve_prefix(&type_map, None).as_deref(), Some("~"));
+    }
+
+    #[test]
+    fn dm_commands_have_no_guild() {
+        let mut msg = testing::message(50, CHANNEL, AUTHOR, "~activity create");
+
+        assert_eq!(
+            command_guild(&msg),
+            Err("This command is not supported in DMs.")
+        );
+
+        msg.guild_id = Some(GuildId(1));
+
+        assert_eq!(command_guild(&msg), Ok(GuildId(1)));
+    }
+
+    #[test]
+    fn only_long_ids_are_snowflakes() {
+        assert!(is_snowflake("815012009993175090"));
+        assert!(!is_snowflake("1"));
+        assert!(!is_snowflake("2024"));
+        assert!(!is_snowflake("81501200999317509a"));
+        assert!(!is_snowflake("815012009993175090815"));
+    }
+
+    fn placeholder_activity() -> Activity {
+        let start = parse_date_time("12/25/2099 8:00 PM").unwrap();
+
+        Activity::new(
+            "Vault of Glass",
+            "",
+            start,
+            0,
+            6,
+            AUTHOR,
+            testing::message(50, CHANNEL, testing::BOT, ""),
+            ActivityToken::new(),
+        )
+    }
+
+    fn guild_type_map(roster_channel: Option<ChannelId>) -> RwLock<TypeMap> {
+        let mut guild_data = GuildData::new(GuildId(1));
+        guild_data.settings_mut().roster_channel = roster_channel;
+
+        let mut type_map = TypeMap::new();
+        type_map.insert::<data_keys::GetGuildData>(HashMap::from([(1, guild_data)]));
+
+        RwLock::new(type_map)
+    }
+
+    fn stored_guild_data(data: RwLock<TypeMap>) -> GuildData {
+        let mut type_map = data.into_inner();
+
+        type_map
+            .get_mut::<data_keys::GetGuildData>()
+            .unwrap()
+            .remove(&1)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn rosters_are_moved_to_the_roster_board() {
+        let discord = FakeDiscord::new();
+        let data = guild_type_map(Some(ChannelId(20)));
+
+        let activity = store_activity(
+            &discord,
+            &data,
+            GuildId(1),
+            Actor::Member(AUTHOR),
+            placeholder_activity(),
+        )
+        .await
+        .unwrap();
+
+        let guild_data = stored_guild_data(data);
+
+        assert_eq!(activity.embed_msg.channel_id, ChannelId(20));
+        assert_eq!(
+            guild_data.activity(activity.id).unwrap().embed_msg.id,
+            activity.embed_msg.id
+        );
+        assert!(discord.sent().contains(&Sent::Delete(MessageId(50))));
+    }
+
+    #[tokio::test]
+    async fn rejected_activities_delete_their_placeholder() {
+        let discord = FakeDiscord::new();
+        let data = guild_type_map(None);
+
+        let result = store_activity(
+            &discord,
+            &data,
+            GuildId(1),
+            Actor::Member(OTHER),
+            placeholder_activity(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(ActivityUpdateError::NotAllowed)));
+        assert!(stored_guild_data(data).activities().is_empty());
+        assert_eq!(discord.sent(), vec![Sent::Delete(MessageId(50))]);
+    }
+
+    #[tokio::test]
+    async fn failed_roster_posts_leave_nothing_behind() {
+        let discord = FakeDiscord::new();
+        discord.fail_sends();
+        let data = guild_type_map(Some(ChannelId(20)));
+
+        let result = store_activity(
+            &discord,
+            &data,
+            GuildId(1),
+            Actor::Member(AUTHOR),
+            placeholder_activity(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(ActivityUpdateError::Serenity(_))));
+        assert!(stored_guild_data(data).activities().is_empty());
+        assert_eq!(discord.sent(), vec![Sent::Delete(MessageId(50))]);
+    }
+
+    #[tokio::test]
+    async fn description_prompt_rejects_long_descriptions() {
+        let discord = FakeDiscord::new();
+        discord
+            .user_says(AUTHOR, "a".repeat(1025))
+            .user_says(AUTHOR, "a".repeat(1024));
+
+        let description = prompt_description(&discord, CHANNEL, AUTHOR, Some(TIMEOUT))
+            .await
+            .unwrap();
+
+        assert_eq!(description.map(|d| d.len()), Some(1024));
+        assert_eq!(
+            discord.said(),
+            vec!["Please enter a description that is less than or equal to 1024 characters."]
+        );
+    }
 }
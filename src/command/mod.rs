@@ -1,6 +1,18 @@
 pub mod admin;
 pub(crate) mod data;
 pub mod destiny;
+pub(crate) mod discord;
+pub(crate) mod events;
 pub mod general;
+pub(crate) mod hooks;
+pub(crate) mod ical;
 pub(crate) mod imp;
+pub(crate) mod notify;
 pub mod owner;
+pub mod privacy;
+pub(crate) mod report;
+pub(crate) mod service;
+#[cfg(test)]
+pub(crate) mod testing;
+pub(crate) mod triggers;
+pub(crate) mod wizard;
@@ -0,0 +1,256 @@
+//! Tells the members of an activity that it's starting or starts soon, following each member's
+//! `UserSettings`.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serenity::model::id::{GuildId, UserId};
+use serenity::model::misc::Mention;
+use serenity::prelude::Context;
+use tracing::warn;
+
+use crate::command::data::{Activity, Notification, UserSettings};
+use crate::command::imp::data_keys;
+
+/// The members to ping in the activity's channel and the members to DM.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Recipients {
+    pub channel: Vec<UserId>,
+    pub dm: Vec<UserId>,
+}
+
+/// Sorts `members` by how they want to get `notification` at `now`. Members without settings
+/// use the defaults.
+pub fn recipients<I>(
+    settings: Option<&HashMap<UserId, UserSettings>>,
+    members: I,
+    notification: Notification,
+    now: &DateTime<Utc>,
+) -> Recipients
+where
+    I: IntoIterator<Item = UserId>,
+{
+    let default_settings = UserSettings::default();
+    let mut recipients = Recipients::default();
+
+    for member in members {
+        let delivery = settings
+            .and_then(|settings| settings.get(&member))
+            .unwrap_or(&default_settings)
+            .delivery(notification, now);
+
+        if delivery.pings_channel() {
+            recipients.channel.push(member);
+        }
+
+        if delivery.sends_dm() {
+            recipients.dm.push(member);
+        }
+    }
+
+    recipients.channel.sort();
+    recipients.dm.sort();
+    recipients
+}
+
+/// Formats mentions as a list, like "@a, @b, and @c".
+fn mention_list(users: &[UserId]) -> String {
+    let user_count = users.len();
+
+    users
+        .iter()
+        .enumerate()
+        .map(|(idx, &user)| {
+            if idx == 0 {
+                Mention::from(user).to_string()
+            } else if idx == user_count - 1 {
+                format!(", and {}", Mention::from(user))
+            } else {
+                format!(", {}", Mention::from(user))
+            }
+        })
+        .collect()
+}
+
+/// Sends `notification` about `activity` to the members of its fireteam. Failing to DM a member
+/// is only logged, since users can have DMs turned off.
+pub async fn notify_members(
+    ctx: &Context,
+    guild_id: GuildId,
+    activity: &Activity,
+    notification: Notification,
+) -> serenity::Result<()> {
+    let recipients = {
+        let type_map = ctx.data.read().await;
+
+        recipients(
+            type_map.get::<data_keys::GetUserSettings>(),
+            activity.members.iter().copied(),
+            notification,
+            &Utc::now(),
+        )
+    };
+
+    if !recipients.channel.is_empty() {
+        let mentions = mention_list(&recipients.channel);
+        let content = match notification {
+            Notification::Start => format!(
+                "Hey {}! {} is starting now. Good luck and have fun!",
+                mentions, activity.name
+            ),
+            Notification::Reminder => format!(
+                "Heads up {}! {} starts {}.",
+                mentions, activity.name, activity.start
+            ),
+        };
+
+        activity.embed_msg.channel_id.say(ctx, content).await?;
+    }
+
+    if recipients.dm.is_empty() {
+        return Ok(());
+    }
+
+    let guild_name = guild_id
+        .name(ctx)
+        .await
+        .unwrap_or_else(|| String::from("your server"));
+
+    let content = match notification {
+        Notification::Start => format!(
+            "{} (ID: {}) in {} is starting now. Good luck and have fun!\n{}",
+            activity.name,
+            activity.id,
+            guild_name,
+            activity.embed_msg.link()
+        ),
+        Notification::Reminder => format!(
+            "{} (ID: {}) in {} starts {}.\n{}",
+            activity.name,
+            activity.id,
+            guild_name,
+            activity.start,
+            activity.embed_msg.link()
+        ),
+    };
+
+    for user in recipients.dm {
+        let sent = match user.create_dm_channel(ctx).await {
+            Ok(channel) => channel.say(ctx, &content).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = sent {
+            warn!(
+                user_id = %user,
+                activity_id = activity.id,
+                error = %e,
+                "Failed to send a DM notification"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::data::{Delivery, QuietHours};
+    use chrono::TimeZone;
+
+    const ALICE: UserId = UserId(3);
+    const BOB: UserId = UserId(4);
+    const CAROL: UserId = UserId(5);
+    const DAVE: UserId = UserId(6);
+
+    fn settings(starts: Delivery, quiet_hours: Option<&str>) -> UserSettings {
+        UserSettings {
+            starts,
+            quiet_hours: quiet_hours.map(|hours| hours.parse().unwrap()),
+            ..UserSettings::default()
+        }
+    }
+
+    #[test]
+    fn members_are_notified_how_they_chose() {
+        let settings = vec![
+            (BOB, settings(Delivery::Dm, None)),
+            (CAROL, settings(Delivery::Both, None)),
+            (DAVE, settings(Delivery::Off, None)),
+        ]
+        .into_iter()
+        .collect();
+        let now = Utc.with_ymd_and_hms(2099, 12, 25, 18, 0, 0).unwrap();
+
+        assert_eq!(
+            recipients(
+                Some(&settings),
+                vec![DAVE, CAROL, BOB, ALICE],
+                Notification::Start,
+                &now
+            ),
+            Recipients {
+                channel: vec![ALICE, CAROL],
+                dm: vec![BOB, CAROL],
+            }
+        );
+
+        // Reminders are off unless a member turns them on.
+        assert_eq!(
+            recipients(
+                Some(&settings),
+                vec![ALICE, BOB],
+                Notification::Reminder,
+                &now
+            ),
+            Recipients::default()
+        );
+    }
+
+    #[test]
+    fn nothing_is_sent_during_quiet_hours() {
+        let settings = vec![(ALICE, settings(Delivery::Both, Some("23:00-07:00 utc+0")))]
+            .into_iter()
+            .collect();
+        let night = Utc.with_ymd_and_hms(2099, 12, 25, 2, 0, 0).unwrap();
+        let day = Utc.with_ymd_and_hms(2099, 12, 25, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            recipients(Some(&settings), vec![ALICE], Notification::Start, &night),
+            Recipients::default()
+        );
+        assert_eq!(
+            recipients(Some(&settings), vec![ALICE], Notification::Start, &day),
+            Recipients {
+                channel: vec![ALICE],
+                dm: vec![ALICE],
+            }
+        );
+    }
+
+    #[test]
+    fn quiet_hours_use_their_offset() {
+        let quiet_hours = "22:00-23:30".parse::<QuietHours>().unwrap();
+
+        // 22:30 in UTC-5.
+        assert!(quiet_hours.contains(&Utc.with_ymd_and_hms(2099, 12, 26, 3, 30, 0).unwrap()));
+        assert!(!quiet_hours.contains(&Utc.with_ymd_and_hms(2099, 12, 25, 22, 30, 0).unwrap()));
+        assert!("22:00".parse::<QuietHours>().is_err());
+        assert!("22:00-22:00".parse::<QuietHours>().is_err());
+        assert!("22:00-07:00 utc+15".parse::<QuietHours>().is_err());
+        assert_eq!(
+            "22:00-07:00 UTC+5:30"
+                .parse::<QuietHours>()
+                .unwrap()
+                .to_string(),
+            "22:00-07:00 (UTC+05:30)"
+        );
+    }
+
+    #[test]
+    fn mentions_are_listed_in_a_sentence() {
+        assert_eq!(mention_list(&[ALICE]), "<@3>");
+        assert_eq!(mention_list(&[ALICE, BOB, CAROL]), "<@3>, <@4>, and <@5>");
+    }
+}
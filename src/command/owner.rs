@@ -1,42 +1,48 @@
 use serenity::{
     framework::standard::{
-        macros::{command, group},
-        Args, CommandResult,
+        macros::{check, command, group},
+        Args, CommandOptions, CommandResult, Reason,
     },
     model::channel::Message,
     prelude::Context,
 };
 
-use crate::client;
 use crate::command::imp::{self, data_keys};
+use crate::reload::{self, Source};
 
 #[group]
-#[owners_only]
+#[checks(Owner)]
 #[prefix("owner")]
 #[commands(reload_json, add_admins, remove_admins)]
 struct OwnersOnly;
 
+// Owners can change when the config is reloaded, so they're read from the runtime config instead
+// of using `#[owners_only]`.
+#[check]
+#[name = "Owner"]
+async fn owner_check(
+    ctx: &Context,
+    msg: &Message,
+    _args: &mut Args,
+    _options: &CommandOptions,
+) -> Result<(), Reason> {
+    if imp::is_owner(ctx, msg.author.id).await {
+        Ok(())
+    } else {
+        Err(Reason::Log(format!("{} is not an owner", msg.author.id)))
+    }
+}
+
 #[command]
+#[aliases(reload)]
 async fn reload_json(ctx: &Context, original_msg: &Message) -> CommandResult {
-    let mut type_map = ctx.data.write().await;
-
-    let paths = match type_map.get::<data_keys::GetJsonPaths>() {
-        Some(paths) => paths.clone(),
-        None => {
-            original_msg
-                .channel_id
-                .say(ctx, "JSON paths not initialized in type map.")
-                .await?;
-            return Ok(());
-        }
-    };
+    let results = reload::reload(ctx, Source::ALL).await;
 
-    client::initialize_embed_map(&paths, &mut type_map).await;
-    client::initialize_emoji_map(&paths, &mut type_map).await;
+    reload::log(&results);
 
     original_msg
         .channel_id
-        .say(ctx, "JSON values reloaded.")
+        .say(ctx, reload::summary(&results))
         .await?;
     Ok(())
 }
@@ -0,0 +1,145 @@
+use serenity::{
+    framework::standard::{
+        macros::{command, group},
+        CommandResult,
+    },
+    model::channel::Message,
+    prelude::Context,
+};
+
+use crate::command::data::UserSettings;
+use crate::command::imp::data_keys;
+
+#[group]
+#[only_in(dms)]
+#[prefix("privacy")]
+#[description = "See or delete what Bingus keeps about you. These only work in DMs."]
+#[commands(data, forget)]
+struct Privacy;
+
+#[command]
+#[description = "Lists what Bingus keeps about you."]
+async fn data(ctx: &Context, original_msg: &Message) -> CommandResult {
+    let user_id = original_msg.author.id;
+    let type_map = ctx.data.read().await;
+
+    let mut activities = 0;
+    let mut created = 0;
+    let mut messages = 0;
+    let mut chains = 0;
+    let mut templates = 0;
+    let mut past_activities = 0;
+
+    let guilds = type_map
+        .get::<data_keys::GetGuildData>()
+        .into_iter()
+        .flat_map(|guild_map| guild_map.values());
+
+    for guild_data in guilds {
+        for activity in guild_data.activities().values() {
+            if activity.members.contains(&user_id) || activity.alternate.contains(&user_id) {
+                activities += 1;
+            }
+
+            if activity.creator == user_id {
+                created += 1;
+            }
+        }
+
+        messages += guild_data
+            .messages()
+            .get(&user_id)
+            .map_or(0, |messages| messages.len());
+        chains += guild_data
+            .markov()
+            .values()
+            .filter(|info| info.user() == user_id)
+            .count();
+        templates += guild_data
+            .templates()
+            .iter()
+            .filter(|template| template.creator == Some(user_id))
+            .count();
+        past_activities += guild_data
+            .history()
+            .iter()
+            .filter(|archived| {
+                archived.members.contains(&user_id)
+                    || archived.alternates.contains(&user_id)
+                    || archived.attendance.contains_key(&user_id)
+            })
+            .count();
+    }
+
+    let settings = type_map
+        .get::<data_keys::GetUserSettings>()
+        .and_then(|settings| settings.get(&user_id))
+        .cloned()
+        .unwrap_or_default();
+
+    drop(type_map);
+
+    let settings = UserSettings::KEYS
+        .iter()
+        // Every key in `KEYS` is valid, so this never skips anything.
+        .filter_map(|&key| Some(format!("  - `{}`: {}", key, settings.get(key).ok()?)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let content = format!(
+        "Here's what I keep about you:\n\
+         - You're on the roster of {} upcoming activities, and you created {}.\n\
+         - You saved {} activity templates.\n\
+         - You're on the final roster of {} past activities, which is used for attendance stats.\n\
+         - {} of your messages are cached for markov chains.\n\
+         - {} markov chains were built from your messages.\n\
+         - Your settings:\n{}\n\
+         Use `privacy forget` to delete your cached messages, markov chains, settings and past \
+         rosters.",
+        activities, created, templates, past_activities, messages, chains, settings
+    );
+
+    original_msg.channel_id.say(ctx, content).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description = "Deletes your cached messages, the markov chains built from them, your settings, your place on past rosters and your name on saved templates."]
+async fn forget(ctx: &Context, original_msg: &Message) -> CommandResult {
+    let user_id = original_msg.author.id;
+    let mut type_map = ctx.data.write().await;
+
+    let mut messages = 0;
+    let mut chains = 0;
+    let mut past_activities = 0;
+    let mut templates = 0;
+
+    if let Some(guild_map) = type_map.get_mut::<data_keys::GetGuildData>() {
+        for guild_data in guild_map.values_mut() {
+            let (guild_messages, guild_chains) = guild_data.forget_user(user_id);
+
+            messages += guild_messages;
+            chains += guild_chains;
+            past_activities += guild_data.forget_history(user_id);
+            templates += guild_data.forget_templates(user_id);
+        }
+    }
+
+    if let Some(settings) = type_map.get_mut::<data_keys::GetUserSettings>() {
+        settings.remove(&user_id);
+    }
+
+    drop(type_map);
+
+    let content = format!(
+        "Deleted {} cached messages, {} markov chains and your settings, and removed you from \
+         the rosters of {} past activities and as the creator of {} templates. \
+         Activities you're in weren't changed; use `activity leave` to leave them.",
+        messages, chains, past_activities, templates
+    );
+
+    original_msg.channel_id.say(ctx, content).await?;
+
+    Ok(())
+}
@@ -0,0 +1,354 @@
+//! Turns command errors into messages for the user who ran the command.
+//!
+//! Mistakes, like joining a full activity, get a short explanation. Anything unexpected gets an
+//! incident ID, which is shown to the user and logged along with the full error so that it can be
+//! found later. Incidents can also be forwarded to the bot owners.
+
+use std::fmt::Write as _;
+
+use rand::Rng;
+use serenity::framework::standard::{CommandError, DispatchError};
+use serenity::http::error::Error as HttpError;
+use serenity::model::channel::Message;
+use serenity::model::error::Error as ModelError;
+use serenity::prelude::Context;
+use tracing::{error, info, warn};
+
+use crate::command::data::{ActivityError, EmbedError};
+use crate::command::imp::{data_keys, ActivityUpdateError};
+
+/// Characters used in incident IDs. Similar looking characters are left out so that IDs can be
+/// read back without confusion.
+const INCIDENT_ID_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const INCIDENT_ID_LEN: usize = 6;
+
+/// Discord's limit on message length.
+const MAX_MESSAGE_LEN: usize = 2000;
+
+#[derive(Clone, Debug, Default)]
+pub struct ReportConfig {
+    /// Whether incidents are sent to the owners in a DM.
+    pub forward_to_owners: bool,
+}
+
+/// What the user is told about an error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Report {
+    /// The user already knows what went wrong, so nothing is sent.
+    Silent,
+    /// Something the user can fix.
+    Mistake(String),
+    /// Something unexpected. These are logged with an incident ID.
+    Incident(String),
+}
+
+impl Report {
+    pub fn from_command_error(e: &CommandError) -> Self {
+        if let Some(e) = e.downcast_ref::<ActivityUpdateError>() {
+            Self::from_update_error(e)
+        } else if let Some(e) = e.downcast_ref::<ActivityError>() {
+            Report::Mistake(e.message_for_self())
+        } else if let Some(e) = e.downcast_ref::<EmbedError>() {
+            Self::from_embed_error(e)
+        } else if let Some(e) = e.downcast_ref::<serenity::Error>() {
+            Self::from_serenity_error(e)
+        } else {
+            Report::Incident(String::from("Something went wrong running that command."))
+        }
+    }
+
+    fn from_update_error(e: &ActivityUpdateError) -> Self {
+        match e {
+            ActivityUpdateError::Activity(e) => Report::Mistake(e.message_for_self()),
+            ActivityUpdateError::Serenity(e) => Self::from_serenity_error(e),
+            e => Report::Mistake(format!("{}.", e)),
+        }
+    }
+
+    fn from_embed_error(e: &EmbedError) -> Self {
+        match e {
+            // The user has already been told about these while the embed was being shown.
+            EmbedError::TimedOut | EmbedError::RemovedReaction => Report::Silent,
+            EmbedError::InvalidReaction => Report::Mistake(String::from(
+                "That reaction isn't one of the options. Please run the command again.",
+            )),
+            EmbedError::Serenity(e) => Self::from_serenity_error(e),
+            EmbedError::Missing(name) => {
+                error!(embed = %name, "An embed is missing from embeds.json");
+
+                Report::Incident(String::from(
+                    "The bot is misconfigured: one of its embeds is missing. Please let the bot \
+                     owners know.",
+                ))
+            }
+            _ => Report::Incident(String::from("Something went wrong waiting for a reaction.")),
+        }
+    }
+
+    fn from_serenity_error(e: &serenity::Error) -> Self {
+        let status = match e {
+            serenity::Error::Http(http_error) => match &**http_error {
+                HttpError::UnsuccessfulRequest(response) => Some(response.status_code.as_u16()),
+                _ => None,
+            },
+            serenity::Error::Model(ModelError::InvalidPermissions(_)) => Some(403),
+            serenity::Error::Model(ModelError::MessageTooLong(_)) => {
+                return Report::Mistake(String::from(
+                    "The reply to that was too long for Discord to send.",
+                ))
+            }
+            _ => None,
+        };
+
+        Report::Incident(String::from(match status {
+            Some(403) => "I don't have permission to do that here.",
+            Some(404) => "A message or channel that I needed was deleted.",
+            Some(429) => "Discord is rate limiting me. Please try again in a bit.",
+            _ => "Discord had a problem handling that. Please try again later.",
+        }))
+    }
+
+    pub fn from_dispatch_error(e: &DispatchError) -> Self {
+        let message = match e {
+            // Only the first attempt is answered, so that spamming a command isn't answered with
+            // spam.
+            DispatchError::Ratelimited(info) if !info.is_first_try => return Report::Silent,
+            DispatchError::Ratelimited(info) => format!(
+                "You're using that command too often. Please try again in {} seconds.",
+                info.rate_limit.as_secs().max(1)
+            ),
+            DispatchError::CommandDisabled(_) => String::from("That command is disabled."),
+            DispatchError::OnlyForDM => String::from("That command only works in DMs."),
+            DispatchError::OnlyForGuilds => String::from("That command only works in a server."),
+            DispatchError::OnlyForOwners => {
+                String::from("That command can only be used by the bot's owners.")
+            }
+            DispatchError::LackingRole | DispatchError::LackingPermissions(_) => {
+                String::from("You don't have permission to use that command.")
+            }
+            DispatchError::CheckFailed("Owner", _) => {
+                String::from("That command can only be used by the bot's owners.")
+            }
+            DispatchError::CheckFailed(_, _) => {
+                String::from("You aren't allowed to use that command here.")
+            }
+            DispatchError::NotEnoughArguments { min, given } => format!(
+                "That command needs at least {} argument(s), but {} were given.",
+                min, given
+            ),
+            DispatchError::TooManyArguments { max, given } => format!(
+                "That command takes at most {} argument(s), but {} were given.",
+                max, given
+            ),
+            // Blocked users, guilds and channels are ignored on purpose.
+            _ => return Report::Silent,
+        };
+
+        Report::Mistake(message)
+    }
+}
+
+/// Creates a short random ID for an incident.
+pub fn incident_id<R: Rng>(rng: &mut R) -> String {
+    (0..INCIDENT_ID_LEN)
+        .map(|_| INCIDENT_ID_CHARS[rng.gen_range(0..INCIDENT_ID_CHARS.len())] as char)
+        .collect()
+}
+
+/// Logs a failed command and tells the user what went wrong.
+pub async fn report_command_error(
+    ctx: &Context,
+    msg: &Message,
+    command_name: &str,
+    e: &CommandError,
+) {
+    let content = match Report::from_command_error(e) {
+        Report::Silent => {
+            info!(command = command_name, error = %e, "Command stopped");
+            return;
+        }
+        Report::Mistake(message) => {
+            warn!(command = command_name, error = %e, "Command failed");
+            message
+        }
+        Report::Incident(message) => {
+            let incident = incident_id(&mut rand::thread_rng());
+
+            error!(
+                command = command_name,
+                incident = %incident,
+                error = ?e,
+                "Command failed"
+            );
+
+            forward_to_owners(ctx, msg, command_name, &incident, e).await;
+
+            format!("{} (Error ID: `{}`)", message, incident)
+        }
+    };
+
+    if let Err(e) = msg.channel_id.say(ctx, content).await {
+        warn!(error = %e, "Failed to report a command error");
+    }
+}
+
+/// Tells the user why a command wasn't run.
+pub async fn report_dispatch_error(ctx: &Context, msg: &Message, e: &DispatchError) {
+    let content = match Report::from_dispatch_error(e) {
+        Report::Mistake(message) | Report::Incident(message) => message,
+        Report::Silent => return,
+    };
+
+    if let Err(e) = msg.channel_id.say(ctx, content).await {
+        warn!(error = %e, "Failed to report a dispatch error");
+    }
+}
+
+async fn forward_to_owners(
+    ctx: &Context,
+    msg: &Message,
+    command_name: &str,
+    incident: &str,
+    e: &CommandError,
+) {
+    let forward = ctx
+        .data
+        .read()
+        .await
+        .get::<data_keys::GetReportConfig>()
+        .is_some_and(|config| config.forward_to_owners);
+
+    if !forward {
+        return;
+    }
+
+    let mut content = String::new();
+    let _ = writeln!(content, "**Incident `{}`**", incident);
+    let _ = writeln!(
+        content,
+        "Command `{}` run by {} in channel {}{}",
+        command_name,
+        msg.author.tag(),
+        msg.channel_id,
+        msg.guild_id
+            .map(|id| format!(" of guild {}", id))
+            .unwrap_or_default()
+    );
+    let _ = write!(content, "```\n{:?}", e);
+
+    // Leave room for the closing code block.
+    truncate(&mut content, MAX_MESSAGE_LEN - 4);
+    content.push_str("\n```");
+
+    send_to_owners(ctx, &content).await;
+}
+
+/// Sends a DM to each of the bot's owners. Messages that are too long are cut off.
+pub async fn send_to_owners(ctx: &Context, content: &str) {
+    let owners = match ctx.data.read().await.get::<data_keys::GetRuntimeConfig>() {
+        Some(config) => config.owners.clone(),
+        None => return,
+    };
+
+    let mut content = content.to_string();
+    truncate(&mut content, MAX_MESSAGE_LEN);
+
+    for owner in owners {
+        let sent = match owner.create_dm_channel(ctx).await {
+            Ok(channel) => channel.say(ctx, &content).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = sent {
+            warn!(owner = %owner, error = %e, "Failed to send a message to an owner");
+        }
+    }
+}
+
+fn truncate(content: &mut String, max_len: usize) {
+    if content.len() > max_len {
+        let mut end = max_len;
+
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        content.truncate(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::mock::StepRng;
+
+    fn command_error<E: std::error::Error + Send + Sync + 'static>(e: E) -> CommandError {
+        Box::new(e)
+    }
+
+    #[test]
+    fn incident_ids_are_short_and_readable() {
+        let id = incident_id(&mut rand::thread_rng());
+
+        assert_eq!(id.len(), INCIDENT_ID_LEN);
+        assert!(id.bytes().all(|c| INCIDENT_ID_CHARS.contains(&c)));
+        assert_eq!(incident_id(&mut StepRng::new(0, 0)), "AAAAAA");
+    }
+
+    #[test]
+    fn activity_errors_are_mistakes() {
+        assert_eq!(
+            Report::from_command_error(&command_error(ActivityError::MemberListFull)),
+            Report::Mistake(ActivityError::MemberListFull.message_for_self())
+        );
+        assert_eq!(
+            Report::from_command_error(&command_error(ActivityUpdateError::InvalidActivity)),
+            Report::Mistake(String::from("Invalid activity ID."))
+        );
+    }
+
+    #[test]
+    fn embed_errors_the_user_has_seen_are_silent() {
+        assert_eq!(
+            Report::from_command_error(&command_error(EmbedError::TimedOut)),
+            Report::Silent
+        );
+        assert!(matches!(
+            Report::from_command_error(&command_error(EmbedError::Other)),
+            Report::Incident(_)
+        ));
+    }
+
+    #[test]
+    fn missing_embeds_are_config_incidents() {
+        let report = Report::from_command_error(&command_error(EmbedError::Missing(String::from(
+            "activity_roster_start",
+        ))));
+
+        assert!(matches!(report, Report::Incident(message) if message.contains("misconfigured")));
+    }
+
+    #[test]
+    fn unknown_errors_are_incidents() {
+        let e: CommandError = "Missing embed".into();
+
+        assert!(matches!(
+            Report::from_command_error(&e),
+            Report::Incident(_)
+        ));
+    }
+
+    #[test]
+    fn argument_counts_are_explained() {
+        assert_eq!(
+            Report::from_dispatch_error(&DispatchError::NotEnoughArguments { min: 2, given: 1 }),
+            Report::Mistake(String::from(
+                "That command needs at least 2 argument(s), but 1 were given."
+            ))
+        );
+        assert_eq!(
+            Report::from_dispatch_error(&DispatchError::BlockedUser),
+            Report::Silent
+        );
+    }
+}
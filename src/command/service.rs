@@ -0,0 +1,775 @@
+//! The rules for changing activities, shared by the user and admin commands and the HTTP API.
+//!
+//! `ActivityService` only works on `GuildData`, so it never talks to Discord. The functions in
+//! `imp` lock the TypeMap, call into the service, and publish the results as events.
+
+use serenity::model::id::UserId;
+
+use crate::command::data::{
+    Activity, ActivityTemplate, Attendance, GuildData, GuildSettings, RosterPosition, StartTime,
+};
+use crate::command::imp::{self, ActivityUpdateError};
+use crate::util::ActivityToken;
+
+/// Who is making a change. Members can only change their own place in an activity and the
+/// activities they created, while admins can change anything.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Actor {
+    Member(UserId),
+    Admin(UserId),
+    /// The bot itself, such as when an activity's start time is reached.
+    System,
+}
+
+impl Actor {
+    fn is_privileged(self) -> bool {
+        matches!(self, Actor::Admin(_) | Actor::System)
+    }
+
+    fn is(self, user_id: UserId) -> bool {
+        match self {
+            Actor::Member(id) | Actor::Admin(id) => id == user_id,
+            Actor::System => false,
+        }
+    }
+}
+
+/// The result of a successful join.
+#[derive(Clone, Debug)]
+pub struct Joined {
+    pub activity: Activity,
+    /// A description of the overlapping activities that the user is already in, or an empty
+    /// string if there are none.
+    pub conflicts: String,
+}
+
+pub struct ActivityService<'a> {
+    guild_data: &'a mut GuildData,
+}
+
+impl<'a> ActivityService<'a> {
+    pub fn new(guild_data: &'a mut GuildData) -> Self {
+        Self { guild_data }
+    }
+
+    pub fn settings(&self) -> &GuildSettings {
+        self.guild_data.settings()
+    }
+
+    /// Returns the activity if `actor` is allowed to edit, delete or start it.
+    pub fn managed(
+        &self,
+        actor: Actor,
+        activity_id: u64,
+    ) -> Result<&Activity, ActivityUpdateError> {
+        let activity = self
+            .guild_data
+            .activity(activity_id)
+            .ok_or(ActivityUpdateError::InvalidActivity)?;
+
+        if actor.is_privileged() || actor.is(activity.creator) {
+            Ok(activity)
+        } else {
+            Err(ActivityUpdateError::NotAllowed)
+        }
+    }
+
+    /// Adds a new activity, giving it the next free ID. Returns a copy of the added activity.
+    pub fn create(
+        &mut self,
+        actor: Actor,
+        mut activity: Activity,
+    ) -> Result<Activity, ActivityUpdateError> {
+        if !actor.is_privileged() && !actor.is(activity.creator) {
+            return Err(ActivityUpdateError::NotAllowed);
+        }
+
+        if activity.start.until().is_none() {
+            return Err(ActivityUpdateError::InvalidStart);
+        }
+
+        activity.id = self.guild_data.activity_id();
+
+        let snapshot = activity.clone();
+
+        self.guild_data
+            .add_activity(activity)
+            .map_err(|_| ActivityUpdateError::InvalidActivity)?;
+
+        Ok(snapshot)
+    }
+
+    /// Adds `user_id` to the main fireteam in `role`, or to the alternates if `alternate` is
+    /// true. Members can switch roles by joining again with another role. Admins can add users to
+    /// overlapping activities even when the guild blocks conflicts.
+    pub fn join(
+        &mut self,
+        actor: Actor,
+        activity_id: u64,
+        user_id: UserId,
+        alternate: bool,
+        role: Option<&str>,
+    ) -> Result<Joined, ActivityUpdateError> {
+        if !actor.is_privileged() && !actor.is(user_id) {
+            return Err(ActivityUpdateError::NotAllowed);
+        }
+
+        let already_member = self
+            .guild_data
+            .activity(activity_id)
+            .is_some_and(|activity| activity.position(user_id) == RosterPosition::Member);
+
+        // Alternates aren't committed to the activity, so they can't have conflicts. Members
+        // switching roles were already checked when they joined.
+        let conflicts = if alternate || already_member {
+            String::new()
+        } else {
+            imp::describe_conflicts(&self.guild_data.conflicts(activity_id, user_id))
+        };
+
+        if !conflicts.is_empty()
+            && !actor.is_privileged()
+            && self.guild_data.settings().block_conflicts
+        {
+            return Err(ActivityUpdateError::Conflict(conflicts));
+        }
+
+        let activity = self
+            .guild_data
+            .activity_mut(activity_id)
+            .ok_or(ActivityUpdateError::InvalidActivity)?;
+
+        if alternate {
+            activity.add_member_alt(user_id)?;
+        } else {
+            activity.join_role(user_id, role)?;
+        }
+
+        Ok(Joined {
+            activity: activity.clone(),
+            conflicts,
+        })
+    }
+
+    /// Removes `user_id` from either the main fireteam or the alternates.
+    pub fn leave(
+        &mut self,
+        actor: Actor,
+        activity_id: u64,
+        user_id: UserId,
+    ) -> Result<Activity, ActivityUpdateError> {
+        if !actor.is_privileged() && !actor.is(user_id) {
+            return Err(ActivityUpdateError::NotAllowed);
+        }
+
+        let activity = self
+            .guild_data
+            .activity_mut(activity_id)
+            .ok_or(ActivityUpdateError::InvalidActivity)?;
+
+        activity.leave(user_id)?;
+
+        Ok(activity.clone())
+    }
+
+    /// Changes an activity's start time and description. The old start is cancelled, and the
+    /// activity is given a new token so that it can be scheduled again.
+    pub fn edit(
+        &mut self,
+        actor: Actor,
+        activity_id: u64,
+        start: StartTime,
+        description: String,
+    ) -> Result<Activity, ActivityUpdateError> {
+        self.managed(actor, activity_id)?;
+
+        if start.until().is_none() {
+            return Err(ActivityUpdateError::InvalidStart);
+        }
+
+        let activity = self
+            .guild_data
+            .activity_mut(activity_id)
+            .ok_or(ActivityUpdateError::InvalidActivity)?;
+
+        activity.cancel_token.cancel();
+        activity.cancel_token = ActivityToken::new();
+        activity.start = start;
+        activity.description = description;
+
+        Ok(activity.clone())
+    }
+
+    /// Removes an activity and cancels its start.
+    pub fn delete(
+        &mut self,
+        actor: Actor,
+        activity_id: u64,
+    ) -> Result<Activity, ActivityUpdateError> {
+        self.managed(actor, activity_id)?;
+        self.remove(activity_id)
+    }
+
+    /// Removes an activity so that it can be started. Only admins can start activities early.
+    pub fn start(
+        &mut self,
+        actor: Actor,
+        activity_id: u64,
+    ) -> Result<Activity, ActivityUpdateError> {
+        if !actor.is_privileged() {
+            return Err(ActivityUpdateError::NotAllowed);
+        }
+
+        let activity = self.remove(activity_id)?;
+        self.guild_data.archive(&activity);
+
+        Ok(activity)
+    }
+
+    /// Marks whether `user` showed up to the most recent started activity with ID `activity_id`.
+    /// Only admins can take attendance.
+    pub fn mark_attendance(
+        &mut self,
+        actor: Actor,
+        activity_id: u64,
+        user: UserId,
+        attendance: Attendance,
+    ) -> Result<(), ActivityUpdateError> {
+        if !actor.is_privileged() {
+            return Err(ActivityUpdateError::NotAllowed);
+        }
+
+        self.guild_data
+            .archived_mut(activity_id)
+            .ok_or(ActivityUpdateError::InvalidActivity)?
+            .mark(user, attendance)
+            .map_err(ActivityUpdateError::Activity)
+    }
+
+    /// Turns the reminder before an activity starts on or off.
+    pub fn set_reminder(
+        &mut self,
+        actor: Actor,
+        activity_id: u64,
+        reminder: bool,
+    ) -> Result<Activity, ActivityUpdateError> {
+        self.managed(actor, activity_id)?;
+
+        let activity = self
+            .guild_data
+            .activity_mut(activity_id)
+            .ok_or(ActivityUpdateError::InvalidActivity)?;
+
+        activity.reminder = reminder;
+
+        Ok(activity.clone())
+    }
+
+    /// Saves an activity as a template named `name`. A template can only be replaced by the user
+    /// who saved it or by an admin.
+    pub fn save_template(
+        &mut self,
+        actor: Actor,
+        activity_id: u64,
+        name: &str,
+    ) -> Result<ActivityTemplate, ActivityUpdateError> {
+        let creator = match actor {
+            Actor::Member(id) | Actor::Admin(id) => id,
+            Actor::System => return Err(ActivityUpdateError::NotAllowed),
+        };
+
+        if let Some(existing) = self.guild_data.template(name) {
+            if !actor.is_privileged() && !existing.creator.is_some_and(|id| actor.is(id)) {
+                return Err(ActivityUpdateError::NotAllowed);
+            }
+        }
+
+        let activity = self
+            .guild_data
+            .activity(activity_id)
+            .ok_or(ActivityUpdateError::InvalidActivity)?;
+
+        let template = ActivityTemplate::from_activity(name, activity, creator);
+        self.guild_data.save_template(template.clone());
+
+        Ok(template)
+    }
+
+    /// Deletes a template. Only the user who saved it and admins can delete it.
+    pub fn delete_template(
+        &mut self,
+        actor: Actor,
+        name: &str,
+    ) -> Result<ActivityTemplate, ActivityUpdateError> {
+        let template = self
+            .guild_data
+            .template(name)
+            .ok_or(ActivityUpdateError::InvalidTemplate)?;
+
+        if !actor.is_privileged() && !template.creator.is_some_and(|id| actor.is(id)) {
+            return Err(ActivityUpdateError::NotAllowed);
+        }
+
+        self.guild_data
+            .remove_template(name)
+            .ok_or(ActivityUpdateError::InvalidTemplate)
+    }
+
+    fn remove(&mut self, activity_id: u64) -> Result<Activity, ActivityUpdateError> {
+        let activity = self
+            .guild_data
+            .remove_activity(activity_id)
+            .ok_or(ActivityUpdateError::InvalidActivity)?;
+
+        activity.cancel_token.cancel();
+
+        Ok(activity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::data::{ActivityError, RoleSlot};
+    use crate::command::testing;
+    use chrono::{Duration as ChronoDuration, Utc};
+    use serenity::model::id::{ChannelId, GuildId};
+    use std::time::Duration;
+
+    const CREATOR: UserId = UserId(1);
+    const ADMIN: UserId = UserId(2);
+    const ALICE: UserId = UserId(3);
+    const BOB: UserId = UserId(4);
+
+    fn start_in(hours: i64) -> StartTime {
+        let date_time = (Utc::now() + ChronoDuration::hours(hours)).into();
+
+        StartTime {
+            date_time,
+            input: String::new(),
+        }
+    }
+
+    fn activity(size: u8, hours: i64) -> Activity {
+        Activity::new(
+            "Vault of Glass",
+            "",
+            start_in(hours),
+            0,
+            size,
+            CREATOR,
+            testing::message((100 + hours) as u64, ChannelId(10), testing::BOT, ""),
+            ActivityToken::new(),
+        )
+    }
+
+    fn guild_with(activities: Vec<Activity>) -> (GuildData, Vec<u64>) {
+        let mut guild_data = GuildData::new(GuildId(1));
+        let mut service = ActivityService::new(&mut guild_data);
+
+        let ids = activities
+            .into_iter()
+            .map(|activity| {
+                service
+                    .create(Actor::Member(CREATOR), activity)
+                    .expect("activity can be created")
+                    .id
+            })
+            .collect();
+
+        (guild_data, ids)
+    }
+
+    #[test]
+    fn create_assigns_ids_and_reuses_freed_ones() {
+        let (mut guild_data, ids) = guild_with(vec![activity(6, 1), activity(6, 5)]);
+        assert_eq!(ids, vec![0, 1]);
+
+        let mut service = ActivityService::new(&mut guild_data);
+        service.delete(Actor::Member(CREATOR), 0).unwrap();
+
+        let created = service
+            .create(Actor::Member(CREATOR), activity(6, 9))
+            .unwrap();
+        assert_eq!(created.id, 0);
+    }
+
+    #[test]
+    fn create_rejects_past_starts_and_other_creators() {
+        let mut guild_data = GuildData::new(GuildId(1));
+        let mut service = ActivityService::new(&mut guild_data);
+
+        assert!(matches!(
+            service.create(Actor::Member(CREATOR), activity(6, -1)),
+            Err(ActivityUpdateError::InvalidStart)
+        ));
+        assert!(matches!(
+            service.create(Actor::Member(ALICE), activity(6, 1)),
+            Err(ActivityUpdateError::NotAllowed)
+        ));
+        assert!(service.create(Actor::Admin(ADMIN), activity(6, 1)).is_ok());
+    }
+
+    #[test]
+    fn members_can_only_join_and_leave_as_themselves() {
+        let (mut guild_data, ids) = guild_with(vec![activity(6, 1)]);
+        let mut service = ActivityService::new(&mut guild_data);
+
+        assert!(matches!(
+            service.join(Actor::Member(ALICE), ids[0], BOB, false, None),
+            Err(ActivityUpdateError::NotAllowed)
+        ));
+
+        let joined = service
+            .join(Actor::Member(ALICE), ids[0], ALICE, false, None)
+            .unwrap();
+        assert!(joined.activity.members.contains(&ALICE));
+        assert!(joined.conflicts.is_empty());
+
+        assert!(matches!(
+            service.leave(Actor::Member(BOB), ids[0], ALICE),
+            Err(ActivityUpdateError::NotAllowed)
+        ));
+
+        let left = service.leave(Actor::Member(ALICE), ids[0], ALICE).unwrap();
+        assert!(!left.members.contains(&ALICE));
+    }
+
+    #[test]
+    fn admins_can_add_and_remove_anyone() {
+        let (mut guild_data, ids) = guild_with(vec![activity(6, 1)]);
+        let mut service = ActivityService::new(&mut guild_data);
+
+        service
+            .join(Actor::Admin(ADMIN), ids[0], BOB, true, None)
+            .unwrap();
+        assert_eq!(
+            service
+                .managed(Actor::Admin(ADMIN), ids[0])
+                .unwrap()
+                .alternate,
+            vec![BOB]
+        );
+
+        service.leave(Actor::Admin(ADMIN), ids[0], BOB).unwrap();
+        assert!(service
+            .managed(Actor::Admin(ADMIN), ids[0])
+            .unwrap()
+            .alternate
+            .is_empty());
+    }
+
+    #[test]
+    fn join_reports_membership_errors() {
+        let (mut guild_data, ids) = guild_with(vec![activity(2, 1)]);
+        let mut service = ActivityService::new(&mut guild_data);
+
+        service
+            .join(Actor::Member(ALICE), ids[0], ALICE, false, None)
+            .unwrap();
+
+        assert!(matches!(
+            service.join(Actor::Member(ALICE), ids[0], ALICE, false, None),
+            Err(ActivityUpdateError::Activity(
+                ActivityError::MemberAlreadyInList
+            ))
+        ));
+
+        service
+            .join(Actor::Member(BOB), ids[0], BOB, false, None)
+            .unwrap();
+
+        assert!(matches!(
+            service.join(Actor::Member(CREATOR), ids[0], CREATOR, false, None),
+            Err(ActivityUpdateError::Activity(ActivityError::MemberListFull))
+        ));
+        assert!(matches!(
+            service.leave(Actor::Member(CREATOR), ids[0], CREATOR),
+            Err(ActivityUpdateError::Activity(ActivityError::NotInActivity))
+        ));
+        assert!(matches!(
+            service.join(Actor::Member(BOB), 42, BOB, false, None),
+            Err(ActivityUpdateError::InvalidActivity)
+        ));
+    }
+
+    #[test]
+    fn blocked_conflicts_only_stop_members() {
+        // Both activities last the default hour, so starts 30 minutes apart overlap.
+        let mut overlapping = activity(6, 1);
+        overlapping.start.date_time += ChronoDuration::minutes(30);
+
+        let (mut guild_data, ids) = guild_with(vec![activity(6, 1), overlapping]);
+        guild_data
+            .settings_mut()
+            .set("block_conflicts", "on")
+            .unwrap();
+
+        let mut service = ActivityService::new(&mut guild_data);
+        service
+            .join(Actor::Member(ALICE), ids[0], ALICE, false, None)
+            .unwrap();
+
+        assert!(matches!(
+            service.join(Actor::Member(ALICE), ids[1], ALICE, false, None),
+            Err(ActivityUpdateError::Conflict(_))
+        ));
+
+        // Alternates aren't committed, so they never conflict.
+        assert!(service
+            .join(Actor::Member(ALICE), ids[1], ALICE, true, None)
+            .is_ok());
+
+        let joined = service
+            .join(Actor::Admin(ADMIN), ids[1], ALICE, false, None)
+            .unwrap();
+        assert!(!joined.conflicts.is_empty());
+    }
+
+    #[test]
+    fn roles_have_their_own_capacity() {
+        let mut raid = activity(3, 1);
+        raid.roles = vec![
+            RoleSlot {
+                name: String::from("Sword"),
+                capacity: 1,
+            },
+            RoleSlot {
+                name: String::from("DPS"),
+                capacity: 2,
+            },
+        ];
+
+        let (mut guild_data, ids) = guild_with(vec![raid]);
+        let mut service = ActivityService::new(&mut guild_data);
+
+        let joined = service
+            .join(Actor::Member(ALICE), ids[0], ALICE, false, Some("sword"))
+            .unwrap();
+        assert_eq!(joined.activity.member_roles[&ALICE], "Sword");
+
+        assert!(matches!(
+            service.join(Actor::Member(BOB), ids[0], BOB, false, Some("Sword")),
+            Err(ActivityUpdateError::Activity(ActivityError::RoleFull))
+        ));
+        assert!(matches!(
+            service.join(Actor::Member(BOB), ids[0], BOB, false, Some("Well")),
+            Err(ActivityUpdateError::Activity(ActivityError::InvalidRole))
+        ));
+
+        // Switching roles frees up the old one.
+        let joined = service
+            .join(Actor::Member(ALICE), ids[0], ALICE, false, Some("dps"))
+            .unwrap();
+        assert_eq!(joined.activity.role_members("DPS"), vec![ALICE]);
+        assert!(joined.activity.role_members("Sword").is_empty());
+
+        // Leaving the fireteam gives up the role.
+        let activity = service
+            .join(Actor::Member(ALICE), ids[0], ALICE, true, None)
+            .unwrap()
+            .activity;
+        assert!(activity.member_roles.is_empty());
+    }
+
+    #[test]
+    fn templates_keep_the_activity_setup() {
+        let mut raid = activity(6, 1);
+        raid.description = String::from("Bring relics");
+        raid.reminder = false;
+        raid.duration = Some(Duration::from_secs(180 * 60));
+        raid.roles = vec![RoleSlot {
+            name: String::from("Sword"),
+            capacity: 2,
+        }];
+
+        let (mut guild_data, ids) = guild_with(vec![raid]);
+        let mut service = ActivityService::new(&mut guild_data);
+
+        service
+            .join(Actor::Member(ALICE), ids[0], ALICE, false, Some("Sword"))
+            .unwrap();
+        let template = service
+            .save_template(Actor::Member(ALICE), ids[0], "VoG")
+            .unwrap();
+        assert_eq!(template.creator, Some(ALICE));
+
+        let created = guild_data.template("vog").unwrap().to_activity(
+            start_in(24),
+            BOB,
+            testing::message(200, ChannelId(10), testing::BOT, ""),
+        );
+
+        assert_eq!(created.name, "Vault of Glass");
+        assert_eq!(created.size, 6);
+        assert_eq!(created.description, "Bring relics");
+        assert_eq!(created.duration, Some(Duration::from_secs(180 * 60)));
+        assert_eq!(created.roles, template.roles);
+        assert!(!created.reminder);
+        assert_eq!(created.creator, BOB);
+        // Only the setup is copied, not who signed up.
+        assert!(created.members.is_empty());
+        assert!(created.member_roles.is_empty());
+    }
+
+    #[test]
+    fn only_the_saver_and_admins_can_change_a_template() {
+        let (mut guild_data, ids) = guild_with(vec![activity(6, 1)]);
+        let mut service = ActivityService::new(&mut guild_data);
+
+        service
+            .save_template(Actor::Member(ALICE), ids[0], "raid")
+            .unwrap();
+
+        assert!(matches!(
+            service.save_template(Actor::Member(BOB), ids[0], "Raid"),
+            Err(ActivityUpdateError::NotAllowed)
+        ));
+        assert!(matches!(
+            service.delete_template(Actor::Member(BOB), "raid"),
+            Err(ActivityUpdateError::NotAllowed)
+        ));
+        assert!(matches!(
+            service.save_template(Actor::Member(ALICE), 99, "other"),
+            Err(ActivityUpdateError::InvalidActivity)
+        ));
+
+        service
+            .save_template(Actor::Admin(BOB), ids[0], "raid")
+            .unwrap();
+        service.delete_template(Actor::Member(BOB), "RAID").unwrap();
+        assert!(matches!(
+            service.delete_template(Actor::Admin(BOB), "raid"),
+            Err(ActivityUpdateError::InvalidTemplate)
+        ));
+    }
+
+    #[test]
+    fn only_creators_and_admins_can_edit_and_delete() {
+        let (mut guild_data, ids) = guild_with(vec![activity(6, 1), activity(6, 5)]);
+        let mut service = ActivityService::new(&mut guild_data);
+
+        assert!(matches!(
+            service.edit(
+                Actor::Member(ALICE),
+                ids[0],
+                start_in(2),
+                String::from("new")
+            ),
+            Err(ActivityUpdateError::NotAllowed)
+        ));
+        assert!(matches!(
+            service.delete(Actor::Member(ALICE), ids[0]),
+            Err(ActivityUpdateError::NotAllowed)
+        ));
+
+        let edited = service
+            .edit(
+                Actor::Member(CREATOR),
+                ids[0],
+                start_in(2),
+                String::from("new"),
+            )
+            .unwrap();
+        assert_eq!(edited.description, "new");
+
+        assert!(service.delete(Actor::Admin(ADMIN), ids[0]).is_ok());
+        assert!(service.delete(Actor::Member(CREATOR), ids[1]).is_ok());
+        assert!(matches!(
+            service.delete(Actor::Member(CREATOR), ids[1]),
+            Err(ActivityUpdateError::InvalidActivity)
+        ));
+    }
+
+    #[test]
+    fn edit_replaces_the_token_and_cancels_the_old_one() {
+        let (mut guild_data, ids) = guild_with(vec![activity(6, 1)]);
+        let mut service = ActivityService::new(&mut guild_data);
+
+        let old_token = service
+            .managed(Actor::System, ids[0])
+            .unwrap()
+            .cancel_token
+            .clone();
+
+        let edited = service
+            .edit(Actor::Member(CREATOR), ids[0], start_in(3), String::new())
+            .unwrap();
+
+        // Cancelling again reports whether it was already cancelled.
+        assert!(!old_token.cancel());
+        assert!(edited.cancel_token.cancel());
+
+        assert!(matches!(
+            service.edit(Actor::Member(CREATOR), ids[0], start_in(-3), String::new()),
+            Err(ActivityUpdateError::InvalidStart)
+        ));
+    }
+
+    #[test]
+    fn only_admins_and_the_bot_can_start_activities() {
+        let (mut guild_data, ids) = guild_with(vec![activity(6, 1), activity(6, 5)]);
+        let mut service = ActivityService::new(&mut guild_data);
+
+        assert!(matches!(
+            service.start(Actor::Member(CREATOR), ids[0]),
+            Err(ActivityUpdateError::NotAllowed)
+        ));
+
+        let started = service.start(Actor::System, ids[0]).unwrap();
+        assert!(!started.cancel_token.cancel());
+
+        assert!(service.start(Actor::Admin(ADMIN), ids[1]).is_ok());
+        assert!(guild_data.activities().is_empty());
+    }
+
+    #[test]
+    fn started_activities_are_archived_for_attendance() {
+        let (mut guild_data, ids) = guild_with(vec![activity(6, 1)]);
+        let mut service = ActivityService::new(&mut guild_data);
+
+        service
+            .join(Actor::Member(ALICE), ids[0], ALICE, false, None)
+            .unwrap();
+        service
+            .join(Actor::Member(BOB), ids[0], BOB, true, None)
+            .unwrap();
+
+        assert!(matches!(
+            service.start(Actor::Member(CREATOR), ids[0]),
+            Err(ActivityUpdateError::NotAllowed)
+        ));
+        service.start(Actor::System, ids[0]).unwrap();
+
+        assert!(matches!(
+            service.mark_attendance(Actor::Member(CREATOR), ids[0], ALICE, Attendance::Present),
+            Err(ActivityUpdateError::NotAllowed)
+        ));
+        service
+            .mark_attendance(Actor::Admin(ADMIN), ids[0], ALICE, Attendance::Present)
+            .unwrap();
+        service
+            .mark_attendance(Actor::Admin(ADMIN), ids[0], BOB, Attendance::Absent)
+            .unwrap();
+        assert!(matches!(
+            service.mark_attendance(Actor::Admin(ADMIN), ids[0], ADMIN, Attendance::Present),
+            Err(ActivityUpdateError::Activity(ActivityError::NotInActivity))
+        ));
+        assert!(matches!(
+            service.mark_attendance(Actor::Admin(ADMIN), 42, ALICE, Attendance::Present),
+            Err(ActivityUpdateError::InvalidActivity)
+        ));
+
+        let archived = &guild_data.history()[0];
+        assert_eq!(archived.members, vec![ALICE]);
+        assert_eq!(archived.alternates, vec![BOB]);
+        assert_eq!(archived.attendance.get(&ALICE), Some(&Attendance::Present));
+
+        // Deleted activities never happened, so they aren't archived.
+        let (mut guild_data, ids) = guild_with(vec![activity(6, 1)]);
+        ActivityService::new(&mut guild_data)
+            .delete(Actor::Member(CREATOR), ids[0])
+            .unwrap();
+        assert!(guild_data.history().is_empty());
+    }
+}
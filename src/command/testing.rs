@@ -0,0 +1,361 @@
+//! An in-memory stand-in for Discord, used by the tests.
+//!
+//! `FakeDiscord` records everything the bot sends, and answers `await_reply`, `await_reaction`
+//! and `await_reply_or_reaction` from a script of user actions. When the script runs out, waiting
+//! behaves like a timeout, so a test that doesn't script enough replies fails instead of hanging.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::{self as json, Value as JsonValue};
+use serenity::builder::CreateEmbed;
+use serenity::collector::ReactionAction;
+use serenity::model::channel::{Message, Reaction, ReactionType};
+use serenity::model::id::{ChannelId, MessageId, UserId};
+use serenity::utils::hashmap_to_json_map;
+
+use crate::command::discord::{Discord, Input};
+
+/// The user ID that the bot's own messages are sent as.
+pub const BOT: UserId = UserId(1);
+
+/// Something the bot did.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Sent {
+    Message {
+        id: MessageId,
+        channel: ChannelId,
+        content: String,
+    },
+    Reply {
+        to: MessageId,
+        content: String,
+    },
+    Embed {
+        id: MessageId,
+        channel: ChannelId,
+        embed: JsonValue,
+    },
+    Edit {
+        id: MessageId,
+        embed: JsonValue,
+    },
+    React {
+        id: MessageId,
+        reaction: String,
+    },
+    DeleteReactions(MessageId),
+    Delete(MessageId),
+}
+
+/// Something a scripted user does.
+#[derive(Clone, Debug)]
+enum Action {
+    Say { author: UserId, content: String },
+    React { user: UserId, emoji: String },
+    Unreact { user: UserId, emoji: String },
+}
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    sent: Vec<Sent>,
+    script: VecDeque<Action>,
+    /// Whether sending new messages fails, like it does when the bot can't post in a channel.
+    sends_fail: bool,
+}
+
+impl State {
+    fn next_id(&mut self) -> MessageId {
+        self.next_id += 1;
+        MessageId(1000 + self.next_id)
+    }
+}
+
+#[derive(Default)]
+pub struct FakeDiscord {
+    state: Mutex<State>,
+}
+
+impl FakeDiscord {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a message from `author`. It's received by the next `await_reply`.
+    pub fn user_says<S: Into<String>>(&self, author: UserId, content: S) -> &Self {
+        self.push(Action::Say {
+            author,
+            content: content.into(),
+        })
+    }
+
+    /// Queues a reaction from `user`. It's received by the next `await_reaction`.
+    pub fn user_reacts<S: Into<String>>(&self, user: UserId, emoji: S) -> &Self {
+        self.push(Action::React {
+            user,
+            emoji: emoji.into(),
+        })
+    }
+
+    /// Queues `user` removing a reaction.
+    pub fn user_unreacts<S: Into<String>>(&self, user: UserId, emoji: S) -> &Self {
+        self.push(Action::Unreact {
+            user,
+            emoji: emoji.into(),
+        })
+    }
+
+    /// Makes every later `say`, `reply` and `send_embed` fail.
+    pub fn fail_sends(&self) -> &Self {
+        self.state.lock().unwrap().sends_fail = true;
+        self
+    }
+
+    /// Everything the bot has done so far, in order.
+    pub fn sent(&self) -> Vec<Sent> {
+        self.state.lock().unwrap().sent.clone()
+    }
+
+    /// The content of every plain message and reply the bot has sent, in order.
+    pub fn said(&self) -> Vec<String> {
+        self.sent()
+            .into_iter()
+            .filter_map(|sent| match sent {
+                Sent::Message { content, .. } | Sent::Reply { content, .. } => Some(content),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Whether every scripted action has been used.
+    pub fn script_finished(&self) -> bool {
+        self.state.lock().unwrap().script.is_empty()
+    }
+
+    fn push(&self, action: Action) -> &Self {
+        self.state.lock().unwrap().script.push_back(action);
+        self
+    }
+
+    fn record(&self, sent: Sent) {
+        self.state.lock().unwrap().sent.push(sent);
+    }
+
+    fn check_send(&self) -> serenity::Result<()> {
+        if self.state.lock().unwrap().sends_fail {
+            Err(serenity::Error::Other("Sending is failing in this test"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[serenity::async_trait]
+impl Discord for FakeDiscord {
+    async fn say(&self, channel: ChannelId, content: String) -> serenity::Result<Message> {
+        self.check_send()?;
+        let id = self.state.lock().unwrap().next_id();
+        let msg = message(id.0, channel, BOT, &content);
+
+        self.record(Sent::Message {
+            id,
+            channel,
+            content,
+        });
+
+        Ok(msg)
+    }
+
+    async fn reply(&self, msg: &Message, content: String) -> serenity::Result<Message> {
+        self.check_send()?;
+        let id = self.state.lock().unwrap().next_id();
+        let reply = message(id.0, msg.channel_id, BOT, &content);
+
+        self.record(Sent::Reply {
+            to: msg.id,
+            content,
+        });
+
+        Ok(reply)
+    }
+
+    async fn send_embed(
+        &self,
+        channel: ChannelId,
+        embed: CreateEmbed,
+    ) -> serenity::Result<Message> {
+        self.check_send()?;
+        let id = self.state.lock().unwrap().next_id();
+
+        self.record(Sent::Embed {
+            id,
+            channel,
+            embed: embed_json(embed),
+        });
+
+        Ok(message(id.0, channel, BOT, ""))
+    }
+
+    async fn edit_embed(&self, msg: &mut Message, embed: CreateEmbed) -> serenity::Result<()> {
+        self.record(Sent::Edit {
+            id: msg.id,
+            embed: embed_json(embed),
+        });
+
+        Ok(())
+    }
+
+    async fn react(&self, msg: &Message, reaction: ReactionType) -> serenity::Result<()> {
+        self.record(Sent::React {
+            id: msg.id,
+            reaction: reaction.as_data(),
+        });
+
+        Ok(())
+    }
+
+    async fn delete_reactions(&self, msg: &Message) -> serenity::Result<()> {
+        self.record(Sent::DeleteReactions(msg.id));
+        Ok(())
+    }
+
+    async fn delete(&self, msg: &Message) -> serenity::Result<()> {
+        self.record(Sent::Delete(msg.id));
+        Ok(())
+    }
+
+    async fn await_reply(
+        &self,
+        channel: ChannelId,
+        author: UserId,
+        _timeout: Option<Duration>,
+    ) -> Option<Message> {
+        let mut state = self.state.lock().unwrap();
+
+        // Like the real collector, anything that isn't a message from `author` is skipped.
+        while let Some(action) = state.script.pop_front() {
+            if let Action::Say {
+                author: sender,
+                content,
+            } = action
+            {
+                if sender == author {
+                    let id = state.next_id();
+                    return Some(message(id.0, channel, sender, &content));
+                }
+            }
+        }
+
+        None
+    }
+
+    async fn await_reaction(
+        &self,
+        msg: &Message,
+        author: Option<UserId>,
+        _timeout: Option<Duration>,
+    ) -> Option<Arc<ReactionAction>> {
+        let mut state = self.state.lock().unwrap();
+
+        while let Some(action) = state.script.pop_front() {
+            let (user, emoji, added) = match action {
+                Action::React { user, emoji } => (user, emoji, true),
+                Action::Unreact { user, emoji } => (user, emoji, false),
+                Action::Say { .. } => continue,
+            };
+
+            if author.is_none() || author == Some(user) {
+                let reaction = Arc::new(reaction(msg, user, &emoji));
+
+                return Some(Arc::new(if added {
+                    ReactionAction::Added(reaction)
+                } else {
+                    ReactionAction::Removed(reaction)
+                }));
+            }
+        }
+
+        None
+    }
+
+    async fn await_reply_or_reaction(
+        &self,
+        msg: &Message,
+        author: UserId,
+        _timeout: Option<Duration>,
+    ) -> Option<Input> {
+        let mut state = self.state.lock().unwrap();
+
+        while let Some(action) = state.script.pop_front() {
+            match action {
+                Action::Say {
+                    author: sender,
+                    content,
+                } if sender == author => {
+                    let id = state.next_id();
+                    let reply = message(id.0, msg.channel_id, sender, &content);
+                    return Some(Input::Reply(Box::new(reply)));
+                }
+                Action::React { user, emoji } if user == author => {
+                    let reaction = Arc::new(reaction(msg, user, &emoji));
+                    return Some(Input::Reaction(Arc::new(ReactionAction::Added(reaction))));
+                }
+                Action::Unreact { user, emoji } if user == author => {
+                    let reaction = Arc::new(reaction(msg, user, &emoji));
+                    return Some(Input::Reaction(Arc::new(ReactionAction::Removed(reaction))));
+                }
+                _ => continue,
+            }
+        }
+
+        None
+    }
+
+    async fn content_safe(&self, msg: &Message) -> String {
+        msg.content.clone()
+    }
+}
+
+pub fn embed_json(embed: CreateEmbed) -> JsonValue {
+    JsonValue::Object(hashmap_to_json_map(embed.0))
+}
+
+/// Builds a message as Discord would send it.
+pub fn message(id: u64, channel: ChannelId, author: UserId, content: &str) -> Message {
+    json::from_value(json::json!({
+        "id": id.to_string(),
+        "attachments": [],
+        "author": {
+            "id": author.0.to_string(),
+            "avatar": null,
+            "bot": author == BOT,
+            "discriminator": "0001",
+            "username": if author == BOT { "Bingus".to_string() } else { format!("user{}", author.0) },
+        },
+        "channel_id": channel.0.to_string(),
+        "content": content,
+        "edited_timestamp": null,
+        "embeds": [],
+        "mention_everyone": false,
+        "mention_roles": [],
+        "mentions": [],
+        "pinned": false,
+        "reactions": [],
+        "timestamp": "2021-01-01T00:00:00+00:00",
+        "tts": false,
+        "type": 0,
+    }))
+    .expect("valid message JSON")
+}
+
+fn reaction(msg: &Message, user: UserId, emoji: &str) -> Reaction {
+    json::from_value(json::json!({
+        "channel_id": msg.channel_id.0.to_string(),
+        "message_id": msg.id.0.to_string(),
+        "user_id": user.0.to_string(),
+        "emoji": { "id": null, "name": emoji },
+    }))
+    .expect("valid reaction JSON")
+}
@@ -0,0 +1,195 @@
+//! Canned replies to keywords in normal messages.
+
+use rand::Rng;
+use serenity::model::channel::Message;
+use serenity::model::id::UserId;
+use tracing::warn;
+
+use crate::command::discord::Discord;
+
+/// The user who gets told no whenever they ask about GMs or nightfalls.
+const ARC: UserId = UserId(213695908393517056);
+
+const NO_LINKS: &[&str] = &[
+        "https://cdn.discordapp.com/attachments/761023449572311071/870117652000030731/az9og6Z_460swp.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875553389654712340/no-no-93.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875553414933798932/No.PNG",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875553470562836480/im_gonna_pretend_i_didnt_see_that.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875554649623646248/d518802fbfe0180d5c818f388e5979a8.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875554722390618163/unknown.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875554762437849159/Nogrumpycat-5ae79bb7c5542e00390dd621.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875554825809575946/110e3daa389718d1a33b751b62938dde.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875554907317489724/yellow-octopus-no-meme-6.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875555461976449096/unknown.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875555529097900092/395-3950272_no-stop-reaction-meme-memes-wtf-whatthehell-brendon.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875555583011487774/4802887.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875555645795991582/not-today-little-boy-meme.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875555728784515112/yellow-octopus-no-meme-15.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875555932652838942/962.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875555987199774740/f014e13efec361d2972e234d3dd1e6b792e8336b.png",
+        "https://cdn.discordapp.com/attachments/820186217974595595/875556129822867496/How-About-No-Bear.png",
+];
+
+/// How a reply to a keyword is sent.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum KeywordReply {
+    /// Sent as a normal message in the same channel.
+    Say(&'static str),
+    /// Sent as a reply to the message with the keyword.
+    Reply(&'static str),
+}
+
+impl KeywordReply {
+    fn content(self) -> &'static str {
+        match self {
+            KeywordReply::Say(content) | KeywordReply::Reply(content) => content,
+        }
+    }
+}
+
+/// Returns the replies for every keyword in a message, in the order they're sent.
+pub fn keyword_replies<R: Rng>(content: &str, author: UserId, rng: &mut R) -> Vec<KeywordReply> {
+    let content = content.to_ascii_lowercase();
+    let mut replies = Vec::new();
+
+    if content.contains("sompies") {
+        replies.push(KeywordReply::Say("sompies to be removed fro mgame"));
+    }
+
+    if content.contains("monke") {
+        replies.push(KeywordReply::Say("https://youtu.be/XZ5Uv4JKTU4"));
+    }
+
+    if content.contains("bingus") {
+        replies.push(KeywordReply::Say("hi"));
+    }
+
+    if author == ARC {
+        let arc_question = content.contains("gm")
+            || content.contains("grandmaster")
+            || content.contains("nightfall")
+            || content.contains("nf");
+
+        if arc_question {
+            let index = rng.gen_range(0..NO_LINKS.len());
+            replies.push(KeywordReply::Reply(NO_LINKS[index]));
+        }
+    }
+
+    if content.contains("linux") {
+        replies.push(KeywordReply::Reply("https://preview.redd.it/ps4p9o323ub11.jpg?width=640&crop=smart&auto=webp&s=bd53639576973220c48940f8926d91349300950e"));
+    }
+
+    if content.contains("soder") {
+        replies.push(KeywordReply::Reply("https://youtu.be/p7LabSw36qs"));
+    }
+
+    if content.contains("time") {
+        replies.push(KeywordReply::Reply("Reset time. Same as usual. You can start the game though just to see the title screen."));
+    }
+
+    if content == "os" {
+        replies.push(KeywordReply::Reply("https://cdn.discordapp.com/attachments/480613470367252500/854518024500936764/a9EyEKZ_460svvp9.webm"));
+    }
+
+    if content.contains("reddit") {
+        replies.push(KeywordReply::Reply("reddit moment"));
+    }
+
+    if content.contains("sigma") {
+        replies.push(KeywordReply::Reply("https://cdn.discordapp.com/attachments/918692837728612372/967957254114541639/unknown.png"));
+    }
+
+    replies
+}
+
+/// Sends the replies for a message. A reply failing doesn't stop the others from being sent.
+pub async fn send_keyword_replies(discord: &dyn Discord, msg: &Message, replies: &[KeywordReply]) {
+    for &keyword_reply in replies {
+        let res = match keyword_reply {
+            KeywordReply::Say(content) => discord.say(msg.channel_id, content.into()).await,
+            KeywordReply::Reply(content) => discord.reply(msg, content.into()).await,
+        };
+
+        if let Err(e) = res {
+            warn!(reply = keyword_reply.content(), error = %e, "Failed to reply to a keyword");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::testing::{self, FakeDiscord, Sent};
+    use rand::rngs::mock::StepRng;
+    use serenity::model::id::ChannelId;
+
+    const USER: UserId = UserId(5);
+
+    fn replies(content: &str, author: UserId) -> Vec<KeywordReply> {
+        keyword_replies(content, author, &mut StepRng::new(0, 0))
+    }
+
+    #[test]
+    fn keywords_are_case_insensitive() {
+        assert_eq!(replies("BINGUS", USER), vec![KeywordReply::Say("hi")]);
+        assert_eq!(
+            replies("what a Reddit moment", USER),
+            vec![KeywordReply::Reply("reddit moment")]
+        );
+    }
+
+    #[test]
+    fn messages_without_keywords_get_no_replies() {
+        assert!(replies("good morning", USER).is_empty());
+    }
+
+    #[test]
+    fn every_keyword_in_a_message_gets_a_reply_in_order() {
+        assert_eq!(
+            replies("monke uses linux", USER),
+            vec![
+                KeywordReply::Say("https://youtu.be/XZ5Uv4JKTU4"),
+                KeywordReply::Reply("https://preview.redd.it/ps4p9o323ub11.jpg?width=640&crop=smart&auto=webp&s=bd53639576973220c48940f8926d91349300950e"),
+            ]
+        );
+    }
+
+    #[test]
+    fn os_has_to_be_the_whole_message() {
+        assert_eq!(replies("OS", USER).len(), 1);
+        assert!(replies("chaos", USER).is_empty());
+    }
+
+    #[test]
+    fn only_arc_is_told_no() {
+        assert!(replies("gm tonight?", USER).is_empty());
+        assert_eq!(
+            replies("gm tonight?", ARC),
+            vec![KeywordReply::Reply(NO_LINKS[0])]
+        );
+    }
+
+    #[tokio::test]
+    async fn replies_are_sent_the_right_way() {
+        let discord = FakeDiscord::new();
+        let msg = testing::message(50, ChannelId(10), USER, "bingus reddit");
+
+        send_keyword_replies(&discord, &msg, &replies(&msg.content, USER)).await;
+
+        assert_eq!(
+            discord.sent(),
+            vec![
+                Sent::Message {
+                    id: serenity::model::id::MessageId(1001),
+                    channel: ChannelId(10),
+                    content: String::from("hi"),
+                },
+                Sent::Reply {
+                    to: msg.id,
+                    content: String::from("reddit moment"),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,464 @@
+//! The `activity create` wizard.
+//!
+//! The wizard is a state machine over `Step`s, each shown by editing the same message. Every step
+//! can be cancelled with ❌, and every step after the first can go back to the one before it with
+//! ⬅️. The activity is only returned once the user confirms a summary of it. If the wizard is
+//! cancelled, times out or fails, its message is deleted so that no half-made roster is left
+//! behind.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serenity::builder::CreateEmbed;
+use serenity::collector::ReactionAction;
+use serenity::framework::standard::CommandResult;
+use serenity::model::channel::{Message, ReactionType};
+use serenity::model::id::{ChannelId, UserId};
+use tracing::warn;
+
+use crate::command::data::{
+    EmbedError, EmbedWithMeta, KnownActivity, RosterData, RosterKind, StartTime,
+};
+use crate::command::discord::{Discord, Input};
+use crate::command::imp::{self, NewActivity};
+
+const BACK: &str = "⬅️";
+const CANCEL: &str = "❌";
+const CONFIRM: &str = "✅";
+
+/// A screen of the wizard.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Step {
+    /// A menu from `embeds.json`, picked from with reactions.
+    Menu(String),
+    StartTime,
+    Description,
+    /// A summary of the activity, which the user confirms before it's created.
+    Confirm,
+}
+
+/// Where to go after a step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Transition {
+    Next(Step),
+    Back,
+    Cancel,
+    TimedOut,
+    Finish,
+}
+
+pub(crate) struct Wizard<'a> {
+    discord: &'a dyn Discord,
+    embed_map: &'a HashMap<String, EmbedWithMeta>,
+    channel: ChannelId,
+    author: UserId,
+    timeout: Option<Duration>,
+    /// The message that every step is shown in. It's sent with the first step.
+    message: Option<Message>,
+    /// The steps before the current one, for going back.
+    history: Vec<Step>,
+    activity: Option<KnownActivity>,
+    start: Option<StartTime>,
+    description: Option<String>,
+}
+
+impl<'a> Wizard<'a> {
+    pub fn new(
+        discord: &'a dyn Discord,
+        embed_map: &'a HashMap<String, EmbedWithMeta>,
+        channel: ChannelId,
+        author: UserId,
+        timeout: Option<Duration>,
+    ) -> Self {
+        Self {
+            discord,
+            embed_map,
+            channel,
+            author,
+            timeout,
+            message: None,
+            history: Vec::new(),
+            activity: None,
+            start: None,
+            description: None,
+        }
+    }
+
+    /// Runs the wizard, starting at the menu named `start_menu` in `embeds.json`. Returns `None`
+    /// if the user cancelled or stopped replying, after telling them so.
+    pub async fn run(mut self, start_menu: &str) -> CommandResult<Option<NewActivity>> {
+        let result = self.run_steps(Step::Menu(start_menu.to_string())).await;
+
+        if !matches!(result, Ok(Some(_))) {
+            self.clean_up().await;
+        }
+
+        result
+    }
+
+    async fn run_steps(&mut self, mut step: Step) -> CommandResult<Option<NewActivity>> {
+        loop {
+            let transition = match &step {
+                Step::Menu(name) => self.menu(name).await?,
+                Step::StartTime => self.start_time().await?,
+                Step::Description => self.description().await?,
+                Step::Confirm => self.confirm().await?,
+            };
+
+            step = match transition {
+                Transition::Next(next) => {
+                    self.history.push(step);
+                    next
+                }
+                // Going back from the first step isn't offered, so this only stays put if a
+                // custom menu reacts with ⬅️ itself.
+                Transition::Back => self.history.pop().unwrap_or(step),
+                Transition::Cancel => {
+                    self.discord
+                        .say(
+                            self.channel,
+                            String::from("Cancelled creating the activity."),
+                        )
+                        .await?;
+                    return Ok(None);
+                }
+                Transition::TimedOut => return Ok(None),
+                Transition::Finish => return self.finish().await.map(Some),
+            };
+        }
+    }
+
+    async fn menu(&mut self, name: &str) -> CommandResult<Transition> {
+        let menu = self
+            .embed_map
+            .get(name)
+            .ok_or_else(|| EmbedError::Missing(name.to_string()))?;
+        let options = menu.meta.clone().ok_or(EmbedError::Other)?;
+
+        let mut reactions = options
+            .iter()
+            .map(|option| option.name.as_str())
+            .collect::<Vec<_>>();
+
+        // Menus in older configs have their own ⬅️ option, which is kept instead of the control.
+        for control in self.controls() {
+            if !reactions.contains(&control) {
+                reactions.push(control);
+            }
+        }
+
+        let embed = self.with_footer(imp::create_embed(&menu.embed));
+        self.show(embed, &reactions).await?;
+
+        let emoji = self.await_reaction().await?;
+
+        if let Some(option) = options.into_iter().find(|option| option.name == emoji) {
+            return Ok(match option.kind {
+                RosterKind::SelectNext(next) => {
+                    if !self.embed_map.contains_key(&next) {
+                        return Err(EmbedError::InvalidReaction.into());
+                    }
+
+                    Transition::Next(Step::Menu(next))
+                }
+                RosterKind::Finished {
+                    activity_name,
+                    size,
+                    duration,
+                    roles,
+                } => {
+                    self.activity = Some(KnownActivity {
+                        name: activity_name,
+                        size,
+                        duration: duration.map(|minutes| Duration::from_secs(minutes * 60)),
+                        roles,
+                    });
+
+                    Transition::Next(Step::StartTime)
+                }
+            });
+        }
+
+        self.control(&emoji)
+            .ok_or_else(|| EmbedError::InvalidReaction.into())
+    }
+
+    async fn start_time(&mut self) -> CommandResult<Transition> {
+        let embed = self.text_embed("activity_roster_time")?;
+        self.show(embed, &self.controls()).await?;
+
+        loop {
+            let reply = match self.await_reply().await? {
+                Ok(reply) => reply,
+                Err(transition) => return Ok(transition),
+            };
+
+            let hint = match imp::parse_start(&reply.content) {
+                Some(start) if start.until().is_some() => {
+                    self.discord.delete(&reply).await?;
+                    self.start = Some(start);
+
+                    return Ok(Transition::Next(Step::Description));
+                }
+                Some(_) => "Please enter a date and time that is in the future.",
+                None => imp::START_TIME_HINT,
+            };
+
+            self.discord.say(self.channel, hint.into()).await?;
+        }
+    }
+
+    async fn description(&mut self) -> CommandResult<Transition> {
+        let embed = self.text_embed("activity_roster_description")?;
+        self.show(embed, &self.controls()).await?;
+
+        loop {
+            let reply = match self.await_reply().await? {
+                Ok(reply) => reply,
+                Err(transition) => return Ok(transition),
+            };
+
+            let safe_content = self.discord.content_safe(&reply).await;
+
+            match imp::description_hint(&safe_content) {
+                None => {
+                    self.discord.delete(&reply).await?;
+                    self.description = Some(safe_content);
+
+                    return Ok(Transition::Next(Step::Confirm));
+                }
+                Some(hint) => self.discord.say(self.channel, hint.into()).await?,
+            };
+        }
+    }
+
+    async fn confirm(&mut self) -> CommandResult<Transition> {
+        let mut embed = self.summary_embed();
+        embed.footer(|footer| {
+            footer.text(format!(
+                "React with {} to create the activity, {} to go back or {} to cancel.",
+                CONFIRM, BACK, CANCEL
+            ))
+        });
+
+        self.show(embed, &[CONFIRM, BACK, CANCEL]).await?;
+
+        let emoji = self.await_reaction().await?;
+
+        if emoji == CONFIRM {
+            Ok(Transition::Finish)
+        } else {
+            self.control(&emoji)
+                .ok_or_else(|| EmbedError::InvalidReaction.into())
+        }
+    }
+
+    fn summary_embed(&self) -> CreateEmbed {
+        let mut embed = CreateEmbed::default();
+        embed
+            .author(|author| author.name("Activity Roster Setup"))
+            .color(0x212121)
+            .description("Here's the activity you're creating.");
+
+        if let Some(activity) = &self.activity {
+            embed.field("Activity", &activity.name, true);
+            embed.field("Fireteam size", activity.size, true);
+
+            if let Some(duration) = activity.duration {
+                embed.field(
+                    "Duration",
+                    format!("{} minutes", duration.as_secs() / 60),
+                    true,
+                );
+            }
+
+            if !activity.roles.is_empty() {
+                let roles = activity
+                    .roles
+                    .iter()
+                    .map(|role| format!("{} ({})", role.name, role.capacity))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                embed.field("Roles", roles, false);
+            }
+        }
+
+        if let Some(start) = &self.start {
+            embed.field("Start", start, false);
+        }
+
+        match self.description.as_deref() {
+            Some(description) if !description.is_empty() => {
+                embed.field("Description", description, false);
+            }
+            _ => {
+                embed.field("Description", "None", false);
+            }
+        }
+
+        embed
+    }
+
+    async fn finish(&mut self) -> CommandResult<NewActivity> {
+        let message = self.message.clone().ok_or(EmbedError::Other)?;
+        let activity = self.activity.take().ok_or(EmbedError::Other)?;
+        let start = self.start.take().ok_or(EmbedError::Other)?;
+
+        self.discord.delete_reactions(&message).await?;
+
+        Ok(NewActivity {
+            roster: RosterData {
+                activity_name: activity.name,
+                size: activity.size,
+                duration: activity.duration,
+                roles: activity.roles,
+                message,
+            },
+            start,
+            description: self.description.take().unwrap_or_default(),
+        })
+    }
+
+    /// The controls for the current step. The first step can't go back.
+    fn controls(&self) -> Vec<&'static str> {
+        if self.history.is_empty() {
+            vec![CANCEL]
+        } else {
+            vec![BACK, CANCEL]
+        }
+    }
+
+    fn control(&self, emoji: &str) -> Option<Transition> {
+        match emoji {
+            BACK if !self.history.is_empty() => Some(Transition::Back),
+            CANCEL => Some(Transition::Cancel),
+            _ => None,
+        }
+    }
+
+    fn with_footer(&self, mut embed: CreateEmbed) -> CreateEmbed {
+        let text = if self.history.is_empty() {
+            format!("React with {} to cancel.", CANCEL)
+        } else {
+            format!("React with {} to go back or {} to cancel.", BACK, CANCEL)
+        };
+
+        embed.footer(|footer| footer.text(text));
+        embed
+    }
+
+    fn text_embed(&self, name: &str) -> Result<CreateEmbed, EmbedError> {
+        let embed = self
+            .embed_map
+            .get(name)
+            .ok_or_else(|| EmbedError::Missing(name.to_string()))?;
+
+        Ok(self.with_footer(imp::create_embed(&embed.embed)))
+    }
+
+    /// Shows a step in the wizard's message, sending it if this is the first step.
+    async fn show(&mut self, embed: CreateEmbed, reactions: &[&str]) -> serenity::Result<()> {
+        let message = match &mut self.message {
+            Some(message) => {
+                self.discord.delete_reactions(message).await?;
+                self.discord.edit_embed(message, embed).await?;
+                message
+            }
+            None => self
+                .message
+                .insert(self.discord.send_embed(self.channel, embed).await?),
+        };
+
+        for &reaction in reactions {
+            self.discord
+                .react(message, ReactionType::Unicode(reaction.to_string()))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Waits for the user to react to the wizard's message, and returns the emoji they used.
+    async fn await_reaction(&self) -> Result<String, EmbedError> {
+        let message = self.message.as_ref().ok_or(EmbedError::Other)?;
+        let action = self
+            .discord
+            .await_reaction(message, Some(self.author), self.timeout)
+            .await;
+
+        match action.as_deref() {
+            Some(ReactionAction::Added(reaction)) => Ok(reaction.emoji.as_data()),
+            Some(ReactionAction::Removed(_)) => {
+                self.discord
+                    .say(
+                        self.channel,
+                        "You removed a reaction before the bot was ready. Please try again.".into(),
+                    )
+                    .await?;
+                Err(EmbedError::RemovedReaction)
+            }
+            None => match self.timeout {
+                Some(duration) => {
+                    self.discord
+                        .say(
+                            self.channel,
+                            format!(
+                                "You did not add a reaction in time. Please react within {} seconds.",
+                                duration.as_secs()
+                            ),
+                        )
+                        .await?;
+                    Err(EmbedError::TimedOut)
+                }
+                // Reported to the user by the after hook.
+                None => Err(EmbedError::Other),
+            },
+        }
+    }
+
+    /// Waits for the user to reply, or to use one of the controls on the wizard's message.
+    /// Other reactions are ignored.
+    async fn await_reply(&self) -> CommandResult<Result<Message, Transition>> {
+        let message = self.message.as_ref().ok_or(EmbedError::Other)?;
+
+        loop {
+            let input = self
+                .discord
+                .await_reply_or_reaction(message, self.author, self.timeout)
+                .await;
+
+            let action = match input {
+                Some(Input::Reply(reply)) => return Ok(Ok(*reply)),
+                Some(Input::Reaction(action)) => action,
+                None => {
+                    imp::send_timeout_message(self.discord, self.channel, self.timeout).await?;
+                    return Ok(Err(Transition::TimedOut));
+                }
+            };
+
+            if let Some(transition) = added_emoji(&action).and_then(|emoji| self.control(&emoji)) {
+                return Ok(Err(transition));
+            }
+        }
+    }
+
+    /// Deletes the wizard's message. Failing to do so is only logged, so that it doesn't hide
+    /// why the wizard stopped.
+    async fn clean_up(&mut self) {
+        if let Some(message) = self.message.take() {
+            if let Err(e) = self.discord.delete(&message).await {
+                warn!(error = %e, "Failed to delete an unfinished activity setup");
+            }
+        }
+    }
+}
+
+/// The emoji of an added reaction.
+fn added_emoji(action: &Arc<ReactionAction>) -> Option<String> {
+    match &**action {
+        ReactionAction::Added(reaction) => Some(reaction.emoji.as_data()),
+        ReactionAction::Removed(_) => None,
+    }
+}
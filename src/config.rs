@@ -1,16 +1,39 @@
+//! Loading the bot configuration.
+//!
+//! The config is built from layers, where each layer overrides the ones before it:
+//!
+//! 1. The defaults from `BotConfig::default`.
+//! 2. The config file, if one is used. It can be JSON, TOML or YAML, picked by its extension.
+//! 3. `BINGUS_*` environment variables, like `BINGUS_PREFIX`.
+//! 4. Command line flags, like `--prefix`.
+//!
+//! Secrets can be read from a separate file with `token_file`, `api_token_file` and
+//! `webhook_secret_file`, so that they don't have to be kept in the config itself.
+
 use std::{
     convert::Infallible,
-    fs::OpenOptions,
-    io::prelude::*,
+    error, fmt,
+    fs::{self, OpenOptions},
+    io::{self, prelude::*},
+    net::SocketAddr,
     path::{Path, PathBuf},
     str::FromStr,
 };
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use serde::{Deserialize, Serialize};
-use serde_json as json;
+use serde_json::{self as json, Map as JsonMap, Value as JsonValue};
 use structopt::StructOpt;
 
+use crate::logging::{LogFormat, LogRotation};
+
+/// Secrets that can be read from a file, along with the key that holds the file's path.
+const SECRET_FILES: &[(&str, &str)] = &[
+    ("token", "token_file"),
+    ("api_token", "api_token_file"),
+    ("webhook_secret", "webhook_secret_file"),
+];
+
 #[derive(Debug, StructOpt)]
 pub struct ConfigPath {
     #[structopt(parse(from_os_str))]
@@ -25,9 +48,15 @@ impl Default for ConfigPath {
     }
 }
 
-impl ToString for ConfigPath {
-    fn to_string(&self) -> String {
-        self.buf.to_string_lossy().into()
+impl fmt::Display for ConfigPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.buf.to_string_lossy())
+    }
+}
+
+impl AsRef<Path> for ConfigPath {
+    fn as_ref(&self) -> &Path {
+        &self.buf
     }
 }
 
@@ -41,115 +70,498 @@ impl FromStr for ConfigPath {
     }
 }
 
+// Like `ConfigMode`, this is only built once at startup.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    #[structopt(about = "Write a config template, unless the file already exists")]
+    Init {
+        #[structopt(default_value)]
+        path: ConfigPath,
+    },
+
+    #[structopt(flatten)]
+    Run(ConfigMode),
+}
+
+// This is only built once at startup, so the size difference doesn't matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, StructOpt)]
 pub enum ConfigMode {
-    #[structopt(about = "Load the bot configuration from a file")]
+    #[structopt(about = "Load the bot configuration from a JSON, TOML or YAML file")]
     File {
         #[structopt(default_value)]
         path: ConfigPath,
+
+        #[structopt(flatten)]
+        overrides: ConfigOverrides,
     },
 
-    #[structopt(about = "Load the bot configuration from command line arguments")]
-    Cmd(BotConfig),
+    #[structopt(about = "Load the bot configuration from environment variables and flags")]
+    Cmd(ConfigOverrides),
 }
 
 impl ConfigMode {
-    pub fn into_config(self) -> anyhow::Result<BotConfig> {
+    /// Loads and validates the config.
+    pub fn into_config(self) -> Result<BotConfig, ConfigError> {
+        match self {
+            ConfigMode::File { path, overrides } => ConfigSource {
+                path: path.buf,
+                overrides,
+            }
+            .load(),
+            ConfigMode::Cmd(overrides) => {
+                let config = BotConfig::from_layers(None::<PathBuf>, overrides, env_var)?;
+                config.validate()?;
+
+                Ok(config)
+            }
+        }
+    }
+
+    /// Where the config is loaded from, if it can be loaded again later.
+    pub fn source(&self) -> Option<ConfigSource> {
         match self {
-            ConfigMode::File { path } => BotConfig::from_path(&path.buf),
-            ConfigMode::Cmd(cfg) => Ok(cfg),
+            ConfigMode::File { path, overrides } => Some(ConfigSource {
+                path: path.buf.clone(),
+                overrides: overrides.clone(),
+            }),
+            ConfigMode::Cmd(_) => None,
         }
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, StructOpt)]
-pub struct BotConfig {
+/// A config file and the overrides that were applied on top of it. This is kept so that the file
+/// can be reloaded while the bot is running.
+#[derive(Clone, Debug)]
+pub struct ConfigSource {
+    pub path: PathBuf,
+    pub overrides: ConfigOverrides,
+}
+
+impl ConfigSource {
+    /// Loads and validates the config.
+    pub fn load(&self) -> Result<BotConfig, ConfigError> {
+        let config = BotConfig::from_layers(Some(&self.path), self.overrides.clone(), env_var)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+}
+
+/// Why the bot config couldn't be loaded.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config file doesn't exist.
+    Missing(PathBuf),
+    /// The config couldn't be read or parsed.
+    Invalid(anyhow::Error),
+    /// The config was loaded, but some settings are wrong. Each string describes a problem.
+    Validation(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Missing(path) => write!(
+                f,
+                "Could not find the config file {}. Run `bingus_bot init {}` to create one",
+                path.display(),
+                path.display()
+            ),
+            ConfigError::Invalid(e) => write!(f, "Invalid bot config: {:#}", e),
+            ConfigError::Validation(problems) => {
+                f.write_str("Invalid bot config:")?;
+
+                for problem in problems {
+                    write!(f, "\n- {}", problem)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for ConfigError {}
+
+impl From<anyhow::Error> for ConfigError {
+    fn from(e: anyhow::Error) -> Self {
+        ConfigError::Invalid(e)
+    }
+}
+
+// Settings from environment variables and command line flags. Anything that isn't set is left as
+// it is in the config file. This isn't a doc comment because structopt would use it as the
+// subcommand's description.
+#[derive(Clone, Debug, Default, Serialize, StructOpt)]
+pub struct ConfigOverrides {
     #[structopt(
         short,
         long,
-        env = "DISCORD_TOKEN",
-        help = "The Discord API token for the bot"
+        env = "BINGUS_TOKEN",
+        hide_env_values = true,
+        help = "The Discord API token for the bot. DISCORD_TOKEN is also read"
     )]
-    pub token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+
+    #[structopt(
+        long,
+        env = "BINGUS_TOKEN_FILE",
+        parse(from_os_str),
+        help = "A file to read the Discord API token from"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_file: Option<PathBuf>,
 
-    #[structopt(short, long, help = "A list of owner IDs")]
+    #[structopt(
+        short,
+        long,
+        env = "BINGUS_OWNER_IDS",
+        use_delimiter = true,
+        help = "A list of owner IDs"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub owner_ids: Option<Vec<u64>>,
 
     #[structopt(
         short = "d",
         long,
-        help = "If present, the bot will respond to Direct Messages"
+        env = "BINGUS_ALLOW_DM",
+        help = "If present, the bot will respond to Direct Messages. Takes an optional true or false"
     )]
-    pub allow_dm: bool,
+    #[serde(
+        serialize_with = "serialize_flag",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub allow_dm: Option<Option<bool>>,
 
     #[structopt(
         short = "b",
         long,
-        help = "If present, will respond to other bots, including itself"
+        env = "BINGUS_ALLOW_BOTS",
+        help = "If present, the bot will respond to other bots, including itself. Takes an optional \
+                true or false"
     )]
-    pub allow_bots: bool,
+    #[serde(
+        serialize_with = "serialize_flag",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub allow_bots: Option<Option<bool>>,
 
-    #[structopt(short, long, help = "The message prefix for bot commands")]
-    pub prefix: String,
+    #[structopt(
+        long,
+        env = "BINGUS_FORWARD_ERRORS_TO_OWNERS",
+        help = "Whether unexpected command errors are sent to the owners in a DM"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forward_errors_to_owners: Option<bool>,
+
+    #[structopt(
+        short,
+        long,
+        env = "BINGUS_PREFIX",
+        help = "The message prefix for bot commands"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
 
     #[structopt(
         short,
         long,
+        env = "BINGUS_ASSETS_FILE",
         parse(from_os_str),
         help = "The location of the assets.json file"
     )]
-    pub assets_file: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assets_file: Option<PathBuf>,
 
     #[structopt(
         short,
         long,
+        env = "BINGUS_EMBEDS_FILE",
         parse(from_os_str),
         help = "The location of the embeds.json file"
     )]
-    pub embeds_file: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embeds_file: Option<PathBuf>,
 
     #[structopt(
         short,
         long,
+        env = "BINGUS_CHANGELOG_FILE",
         parse(from_os_str),
         help = "The location of the changelog.json file"
     )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changelog_file: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        env = "BINGUS_API_ADDRESS",
+        help = "The address to serve the HTTP API on. The API is disabled if this is not set"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_address: Option<SocketAddr>,
+
+    #[structopt(
+        long,
+        env = "BINGUS_API_TOKEN",
+        hide_env_values = true,
+        help = "The bearer token required by HTTP API requests that change activities"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_token: Option<String>,
+
+    #[structopt(
+        long,
+        env = "BINGUS_API_TOKEN_FILE",
+        parse(from_os_str),
+        help = "A file to read the HTTP API token from"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_token_file: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        env = "BINGUS_METRICS_ADDRESS",
+        help = "The address to serve Prometheus metrics on. Metrics are not served if this is not set"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics_address: Option<SocketAddr>,
+
+    #[structopt(
+        long,
+        env = "BINGUS_WEBHOOK_URL",
+        help = "The URL that activity events are sent to. Webhooks are disabled if this is not set"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+
+    #[structopt(
+        long,
+        env = "BINGUS_WEBHOOK_SECRET",
+        hide_env_values = true,
+        help = "The secret used to sign webhook payloads"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook_secret: Option<String>,
+
+    #[structopt(
+        long,
+        env = "BINGUS_WEBHOOK_SECRET_FILE",
+        parse(from_os_str),
+        help = "A file to read the webhook secret from"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook_secret_file: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        env = "BINGUS_WEBHOOK_DEAD_LETTER_FILE",
+        parse(from_os_str),
+        help = "The file that webhook payloads are written to when they can't be delivered"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook_dead_letter_file: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        env = "BINGUS_LOG_LEVEL",
+        help = "The log level, such as `info` or `bingus_bot=debug,serenity=warn`. RUST_LOG takes precedence"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<String>,
+
+    #[structopt(
+        long,
+        env = "BINGUS_LOG_FORMAT",
+        possible_values = &["pretty", "compact", "json"],
+        help = "How log lines are formatted"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_format: Option<LogFormat>,
+
+    #[structopt(
+        long,
+        env = "BINGUS_LOG_DIRECTORY",
+        parse(from_os_str),
+        help = "The directory that log files are written to. Logs go to stdout if this is not set"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_directory: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        env = "BINGUS_LOG_ROTATION",
+        possible_values = &["minutely", "hourly", "daily", "never"],
+        help = "How often a new log file is started"
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_rotation: Option<LogRotation>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BotConfig {
+    pub token: String,
+
+    pub owner_ids: Option<Vec<u64>>,
+
+    pub allow_dm: bool,
+
+    pub allow_bots: bool,
+
+    #[serde(default)]
+    pub forward_errors_to_owners: bool,
+
+    pub prefix: String,
+
+    pub assets_file: PathBuf,
+
+    pub embeds_file: PathBuf,
+
     pub changelog_file: PathBuf,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_address: Option<SocketAddr>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_token: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_address: Option<SocketAddr>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_secret: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_dead_letter_file: Option<PathBuf>,
+
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+
+    #[serde(default)]
+    pub log_format: LogFormat,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_directory: Option<PathBuf>,
+
+    #[serde(default)]
+    pub log_rotation: LogRotation,
+}
+
+fn default_log_level() -> String {
+    String::from("info")
 }
 
 impl BotConfig {
-    pub fn from_path<P: AsRef<Path>>(path: &P) -> anyhow::Result<Self> {
-        let file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(path.as_ref())
-            .with_context(|| {
-                format!(
-                    "Failed to read bot config from {:?}",
-                    path.as_ref().as_os_str()
-                )
-            })?;
+    /// Builds the config from the defaults, then the file at `path` if there is one, then
+    /// `overrides`. Environment variables that aren't part of the overrides are looked up with
+    /// `env`, so that tests don't depend on the environment they're run in.
+    pub fn from_layers<P: AsRef<Path>>(
+        path: Option<P>,
+        overrides: ConfigOverrides,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let mut layers = vec![json::to_value(Self::default()).map_err(anyhow::Error::from)?];
+
+        if let Some(path) = path {
+            layers.push(read_layer(path.as_ref())?);
+        }
 
-        Ok(json::from_reader(file)?)
+        // Kept for compatibility with older setups. BINGUS_TOKEN and --token take precedence.
+        if let Some(token) = env("DISCORD_TOKEN") {
+            layers.push(json::json!({ "token": token }));
+        }
+
+        layers.push(json::to_value(overrides).map_err(anyhow::Error::from)?);
+
+        let mut merged = JsonMap::new();
+
+        for layer in layers {
+            merge_layer(&mut merged, layer)?;
+        }
+
+        read_secret_files(&mut merged)?;
+
+        let config = json::from_value(JsonValue::Object(merged)).map_err(anyhow::Error::from)?;
+
+        Ok(config)
     }
 
-    pub fn gen_default_file<P: AsRef<Path>>(path: &P) -> anyhow::Result<Self> {
-        let path_ref = path.as_ref();
+    /// Checks for settings that would stop the bot from working, so that they're found before
+    /// connecting to Discord.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
 
-        let mut default_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(path_ref)?;
+        if self.token.trim().is_empty() {
+            problems.push(String::from(
+                "`token` is empty. Set it to the bot's Discord token",
+            ));
+        }
+
+        if self.owner_ids.as_ref().is_none_or(|ids| ids.is_empty()) {
+            problems.push(String::from(
+                "`owner_ids` is empty. Set it to the Discord user IDs of the bot's owners",
+            ));
+        }
 
-        let json_string = json::to_string_pretty(&Self::default())?;
+        let files = [
+            ("assets_file", &self.assets_file),
+            ("embeds_file", &self.embeds_file),
+            ("changelog_file", &self.changelog_file),
+        ];
 
-        default_file.write_all(json_string.as_bytes())?;
-        default_file.flush()?;
+        for (name, path) in files.iter() {
+            if !path.is_file() {
+                problems.push(format!("`{}` {} doesn't exist", name, path.display()));
+            }
+        }
 
-        Ok(Self::default())
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Validation(problems))
+        }
+    }
+
+    /// Writes the default config to `path` as JSON, TOML or YAML, depending on its extension.
+    /// Existing files are never overwritten.
+    pub fn write_template<P: AsRef<Path>>(path: &P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+
+        let template = match extension(path).as_deref() {
+            Some("toml") => toml::to_string_pretty(&Self::default())?,
+            Some("yaml") | Some("yml") => serde_yaml::to_string(&Self::default())?,
+            _ => json::to_string_pretty(&Self::default())?,
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(anyhow!(
+                    "{} already exists, so it was left as it is",
+                    path.display()
+                ))
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to create {}", path.display()))
+            }
+        };
+
+        file.write_all(template.as_bytes())?;
+        file.flush()?;
+
+        Ok(())
     }
 }
 
@@ -158,12 +570,283 @@ impl Default for BotConfig {
         Self {
             token: Default::default(),
             owner_ids: Default::default(),
-            allow_dm: true,
+            allow_dm: false,
             allow_bots: false,
+            forward_errors_to_owners: false,
             prefix: String::from("!"),
             assets_file: PathBuf::from("./config/assets.json"),
             embeds_file: PathBuf::from("./config/embeds.json"),
-            changelog_file: PathBuf::from(".config/changelog.json"),
+            changelog_file: PathBuf::from("./config/changelog.json"),
+            api_address: None,
+            api_token: None,
+            metrics_address: None,
+            webhook_url: None,
+            webhook_secret: None,
+            webhook_dead_letter_file: None,
+            log_level: default_log_level(),
+            log_format: LogFormat::default(),
+            log_directory: None,
+            log_rotation: LogRotation::default(),
+        }
+    }
+}
+
+/// Reads a config file as JSON, TOML or YAML, depending on its extension.
+fn read_layer(path: &Path) -> Result<JsonValue, ConfigError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(ConfigError::Missing(path.to_owned()))
+        }
+        Err(e) => {
+            return Err(anyhow::Error::from(e)
+                .context(format!("Failed to read bot config from {}", path.display()))
+                .into())
+        }
+    };
+
+    let layer = match extension(path).as_deref() {
+        Some("toml") => toml::from_str(&contents).map_err(anyhow::Error::from),
+        Some("yaml") | Some("yml") => serde_yaml::from_str(&contents).map_err(anyhow::Error::from),
+        _ => json::from_str(&contents).map_err(anyhow::Error::from),
+    };
+
+    let layer =
+        layer.with_context(|| format!("Failed to parse bot config from {}", path.display()))?;
+
+    Ok(layer)
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase())
+}
+
+/// Copies every key in `layer` over `merged`. Setting a secret or its file in a layer replaces
+/// both, so that a token on the command line isn't overridden by a token file from the config.
+fn merge_layer(merged: &mut JsonMap<String, JsonValue>, layer: JsonValue) -> anyhow::Result<()> {
+    let layer = match layer {
+        JsonValue::Object(map) => map,
+        JsonValue::Null => return Ok(()),
+        _ => return Err(anyhow!("The bot config must be a map of settings")),
+    };
+
+    for (key, value) in layer {
+        for &(secret, file) in SECRET_FILES {
+            if key == secret {
+                merged.remove(file);
+            } else if key == file {
+                merged.remove(secret);
+            }
+        }
+
+        merged.insert(key, value);
+    }
+
+    Ok(())
+}
+
+/// Looks up an environment variable for `BotConfig::from_layers`.
+fn env_var(key: &str) -> Option<String> {
+    std::env::var(key).ok()
+}
+
+/// Serializes a flag that can be given without a value, like `--allow-dm`, which means `true`.
+fn serialize_flag<S: serde::Serializer>(
+    flag: &Option<Option<bool>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    flag.map(|value| value.unwrap_or(true))
+        .serialize(serializer)
+}
+
+/// Replaces secret file paths with the contents of the files.
+fn read_secret_files(merged: &mut JsonMap<String, JsonValue>) -> anyhow::Result<()> {
+    for &(secret, file) in SECRET_FILES {
+        let path = match merged.remove(file) {
+            Some(JsonValue::String(path)) => PathBuf::from(path),
+            Some(JsonValue::Null) | None => continue,
+            Some(_) => return Err(anyhow!("`{}` must be a path", file)),
+        };
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read `{}` from {}", secret, path.display()))?;
+
+        merged.insert(
+            secret.to_owned(),
+            JsonValue::String(contents.trim().to_owned()),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn temp_file(dir: &TempDir, name: &str, contents: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn toml_and_yaml_files_are_read() {
+        let dir = TempDir::new().unwrap();
+        let toml = temp_file(
+            &dir,
+            "config.toml",
+            "token = \"abc\"\nprefix = \"?\"\nowner_ids = [5]\n",
+        );
+        let yaml = temp_file(
+            &dir,
+            "config.yml",
+            "token: abc\nprefix: '?'\nowner_ids: [5]\n",
+        );
+
+        for path in &[toml, yaml] {
+            let config =
+                BotConfig::from_layers(Some(path), ConfigOverrides::default(), no_env).unwrap();
+
+            assert_eq!(config.token, "abc");
+            assert_eq!(config.prefix, "?");
+            assert_eq!(config.owner_ids, Some(vec![5]));
+            // Anything not in the file comes from the defaults.
+            assert_eq!(config.assets_file, PathBuf::from("./config/assets.json"));
         }
     }
+
+    #[test]
+    fn overrides_take_precedence_over_the_file() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_file(
+            &dir,
+            "precedence.json",
+            r#"{"token": "abc", "prefix": "?"}"#,
+        );
+
+        let overrides = ConfigOverrides {
+            prefix: Some(String::from("~")),
+            allow_dm: Some(Some(false)),
+            ..ConfigOverrides::default()
+        };
+
+        let config = BotConfig::from_layers(Some(&path), overrides, no_env).unwrap();
+
+        assert_eq!(config.token, "abc");
+        assert_eq!(config.prefix, "~");
+        assert!(!config.allow_dm);
+    }
+
+    #[test]
+    fn secrets_are_read_from_files() {
+        let dir = TempDir::new().unwrap();
+        let secret = temp_file(&dir, "token", "from-file\n");
+        let path = temp_file(
+            &dir,
+            "secret.toml",
+            &format!("token_file = {:?}", secret.to_string_lossy()),
+        );
+
+        let config =
+            BotConfig::from_layers(Some(&path), ConfigOverrides::default(), no_env).unwrap();
+        assert_eq!(config.token, "from-file");
+
+        let overrides = ConfigOverrides {
+            token: Some(String::from("from-flag")),
+            ..ConfigOverrides::default()
+        };
+
+        let config = BotConfig::from_layers(Some(&path), overrides, no_env).unwrap();
+        assert_eq!(config.token, "from-flag");
+    }
+
+    #[test]
+    fn discord_token_is_below_the_overrides() {
+        let env = |key: &str| (key == "DISCORD_TOKEN").then(|| String::from("from-env"));
+
+        let config =
+            BotConfig::from_layers(None::<PathBuf>, ConfigOverrides::default(), env).unwrap();
+        assert_eq!(config.token, "from-env");
+
+        let overrides = ConfigOverrides {
+            token: Some(String::from("from-flag")),
+            ..ConfigOverrides::default()
+        };
+
+        let config = BotConfig::from_layers(None::<PathBuf>, overrides, env).unwrap();
+        assert_eq!(config.token, "from-flag");
+    }
+
+    #[test]
+    fn allow_flags_work_with_and_without_a_value() {
+        let overrides =
+            ConfigOverrides::from_iter_safe(&["cmd", "--allow-dm", "-b", "false"]).unwrap();
+
+        let config = BotConfig::from_layers(None::<PathBuf>, overrides, no_env).unwrap();
+        assert!(config.allow_dm);
+        assert!(!config.allow_bots);
+    }
+
+    #[test]
+    fn missing_and_invalid_files_are_different_errors() {
+        let dir = TempDir::new().unwrap();
+        let invalid = temp_file(&dir, "invalid.yaml", "prefix: [unclosed");
+        let missing = invalid.with_file_name("missing.json");
+
+        assert!(matches!(
+            BotConfig::from_layers(Some(&invalid), ConfigOverrides::default(), no_env),
+            Err(ConfigError::Invalid(_))
+        ));
+        assert!(matches!(
+            BotConfig::from_layers(Some(&missing), ConfigOverrides::default(), no_env),
+            Err(ConfigError::Missing(path)) if path == missing
+        ));
+    }
+
+    #[test]
+    fn templates_never_overwrite_a_config() {
+        let dir = TempDir::new().unwrap();
+        let path = temp_file(&dir, "existing.toml", "token = \"keep me\"");
+
+        assert!(BotConfig::write_template(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "token = \"keep me\"");
+
+        let new_path = path.with_file_name("template.toml");
+        BotConfig::write_template(&new_path).unwrap();
+
+        let config =
+            BotConfig::from_layers(Some(&new_path), ConfigOverrides::default(), no_env).unwrap();
+        assert!(!config.allow_dm);
+        assert_eq!(
+            config.changelog_file,
+            PathBuf::from("./config/changelog.json")
+        );
+    }
+
+    #[test]
+    fn validation_lists_every_problem() {
+        let config = BotConfig {
+            assets_file: PathBuf::from("/nonexistent/assets.json"),
+            ..BotConfig::default()
+        };
+
+        let problems = match config.validate() {
+            Err(ConfigError::Validation(problems)) => problems,
+            other => panic!("expected validation problems, got {:?}", other),
+        };
+
+        assert!(problems[0].starts_with("`token` is empty"));
+        assert!(problems[1].starts_with("`owner_ids` is empty"));
+        assert!(problems
+            .iter()
+            .any(|problem| problem.contains("/nonexistent/assets.json")));
+    }
 }
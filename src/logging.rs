@@ -0,0 +1,155 @@
+//! Sets up `tracing` from the bot config.
+//!
+//! Logs go to stdout, or to a rotating file in `log_directory` if one is set. The level is an
+//! `EnvFilter` directive like `info` or `bingus_bot=debug,serenity=warn`, and can be overridden
+//! with the `RUST_LOG` environment variable.
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::EnvFilter;
+
+use crate::config::BotConfig;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Multi-line, human readable output.
+    #[default]
+    Pretty,
+    /// One line per event.
+    Compact,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pretty" => Ok(LogFormat::Pretty),
+            "compact" => Ok(LogFormat::Compact),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!(
+                "Invalid log format {:?}. Valid formats are pretty, compact and json.",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogFormat::Pretty => "pretty",
+            LogFormat::Compact => "compact",
+            LogFormat::Json => "json",
+        })
+    }
+}
+
+/// How often a new log file is started.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "minutely" => Ok(LogRotation::Minutely),
+            "hourly" => Ok(LogRotation::Hourly),
+            "daily" => Ok(LogRotation::Daily),
+            "never" => Ok(LogRotation::Never),
+            _ => Err(format!(
+                "Invalid log rotation {:?}. Valid rotations are minutely, hourly, daily and never.",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LogRotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogRotation::Minutely => "minutely",
+            LogRotation::Hourly => "hourly",
+            LogRotation::Daily => "daily",
+            LogRotation::Never => "never",
+        })
+    }
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+/// Installs the global subscriber. The returned guard flushes buffered logs when it's dropped,
+/// so it has to be kept alive until the bot exits.
+pub fn init(config: &BotConfig) -> anyhow::Result<WorkerGuard> {
+    let filter = match std::env::var(EnvFilter::DEFAULT_ENV) {
+        Ok(directives) => EnvFilter::try_new(&directives)
+            .with_context(|| format!("Invalid {} {:?}", EnvFilter::DEFAULT_ENV, directives))?,
+        Err(_) => EnvFilter::try_new(&config.log_level)
+            .with_context(|| format!("Invalid log level {:?}", config.log_level))?,
+    };
+
+    let (writer, guard) = match &config.log_directory {
+        Some(directory) => {
+            tracing_appender::non_blocking(file_appender(directory, config.log_rotation)?)
+        }
+        None => tracing_appender::non_blocking(std::io::stdout()),
+    };
+
+    // Colors would end up as escape codes in log files.
+    let ansi = config.log_directory.is_none();
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(ansi);
+
+    let result = match config.log_format {
+        LogFormat::Pretty => builder.pretty().try_init(),
+        LogFormat::Compact => builder.compact().try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).try_init(),
+    };
+
+    result.map_err(|e| anyhow::anyhow!("Failed to set up logging: {}", e))?;
+
+    Ok(guard)
+}
+
+fn file_appender(directory: &Path, rotation: LogRotation) -> anyhow::Result<RollingFileAppender> {
+    std::fs::create_dir_all(directory).with_context(|| {
+        format!(
+            "Failed to create log directory {}",
+            directory.to_string_lossy()
+        )
+    })?;
+
+    Ok(RollingFileAppender::new(
+        rotation.into(),
+        directory,
+        "bingus.log",
+    ))
+}
@@ -0,0 +1,300 @@
+//! Prometheus metrics, served as text on `metrics_address` if it's set.
+//!
+//! Counters and histograms are updated as things happen. Per-guild gauges, like the number of
+//! activities, are read from the guild data when the metrics are scraped.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use serenity::framework::standard::CommandError;
+use serenity::prelude::Context;
+use tracing::{error, info};
+
+use crate::command::data::{ActivityError, EmbedError, SettingsError};
+use crate::command::imp::{data_keys, ActivityUpdateError};
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+struct Metrics {
+    registry: Registry,
+    commands: IntCounterVec,
+    command_duration: HistogramVec,
+    command_errors: IntCounterVec,
+    discord_failures: IntCounterVec,
+    active_activities: IntGaugeVec,
+    pending_timers: IntGauge,
+    markov_chains: IntGaugeVec,
+    markov_messages: IntGaugeVec,
+    auto_responses: IntCounter,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some(String::from("bingus")), None).expect("valid metrics prefix");
+
+        let commands = IntCounterVec::new(
+            Opts::new("commands_total", "Commands run"),
+            &["group", "command"],
+        )
+        .expect("valid metric");
+
+        let command_duration = HistogramVec::new(
+            HistogramOpts::new("command_duration_seconds", "How long commands took to run")
+                .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 15.0, 60.0, 300.0]),
+            &["group", "command"],
+        )
+        .expect("valid metric");
+
+        let command_errors = IntCounterVec::new(
+            Opts::new("command_errors_total", "Commands that returned an error"),
+            &["group", "command", "kind"],
+        )
+        .expect("valid metric");
+
+        let discord_failures = IntCounterVec::new(
+            Opts::new("discord_failures_total", "Failed requests to Discord"),
+            &["operation"],
+        )
+        .expect("valid metric");
+
+        let active_activities = IntGaugeVec::new(
+            Opts::new("active_activities", "Activities that haven't started yet"),
+            &["guild_id"],
+        )
+        .expect("valid metric");
+
+        let pending_timers = IntGauge::new(
+            "pending_timers",
+            "Activity start timers that haven't fired or been cancelled",
+        )
+        .expect("valid metric");
+
+        let markov_chains = IntGaugeVec::new(
+            Opts::new("markov_chains", "Markov chains kept in memory"),
+            &["guild_id"],
+        )
+        .expect("valid metric");
+
+        let markov_messages = IntGaugeVec::new(
+            Opts::new(
+                "markov_cached_messages",
+                "Messages cached for building markov chains",
+            ),
+            &["guild_id"],
+        )
+        .expect("valid metric");
+
+        let auto_responses = IntCounter::new(
+            "auto_responses_total",
+            "Replies sent to keywords in normal messages",
+        )
+        .expect("valid metric");
+
+        let collectors: Vec<Box<dyn prometheus::core::Collector>> = vec![
+            Box::new(commands.clone()),
+            Box::new(command_duration.clone()),
+            Box::new(command_errors.clone()),
+            Box::new(discord_failures.clone()),
+            Box::new(active_activities.clone()),
+            Box::new(pending_timers.clone()),
+            Box::new(markov_chains.clone()),
+            Box::new(markov_messages.clone()),
+            Box::new(auto_responses.clone()),
+        ];
+
+        for collector in collectors {
+            registry.register(collector).expect("unique metric names");
+        }
+
+        Self {
+            registry,
+            commands,
+            command_duration,
+            command_errors,
+            discord_failures,
+            active_activities,
+            pending_timers,
+            markov_chains,
+            markov_messages,
+            auto_responses,
+        }
+    }
+}
+
+/// Records a finished command. `error` is the error it returned, if any. Failed requests to
+/// Discord are counted where they're made, so they only show up here as the error `kind`.
+pub fn command_finished(
+    group: &str,
+    command: &str,
+    duration: Option<Duration>,
+    error: Option<&CommandError>,
+) {
+    let labels = [group, command];
+
+    METRICS.commands.with_label_values(&labels).inc();
+
+    if let Some(duration) = duration {
+        METRICS
+            .command_duration
+            .with_label_values(&labels)
+            .observe(duration.as_secs_f64());
+    }
+
+    if let Some(e) = error {
+        let kind = error_kind(e);
+
+        METRICS
+            .command_errors
+            .with_label_values(&[group, command, kind])
+            .inc();
+    }
+}
+
+/// Records a failed request to Discord. `operation` is what was being done, like `say`.
+pub fn discord_failure(operation: &str) {
+    METRICS
+        .discord_failures
+        .with_label_values(&[operation])
+        .inc();
+}
+
+/// Records replies sent to keywords in a normal message.
+pub fn auto_responses(count: usize) {
+    METRICS.auto_responses.inc_by(count as u64);
+}
+
+/// Counts an activity start timer as pending until it's dropped.
+pub struct PendingTimer(());
+
+impl PendingTimer {
+    pub fn start() -> Self {
+        METRICS.pending_timers.inc();
+        PendingTimer(())
+    }
+}
+
+impl Drop for PendingTimer {
+    fn drop(&mut self) {
+        METRICS.pending_timers.dec();
+    }
+}
+
+/// A short name for the type of a command error, used as a label.
+fn error_kind(e: &CommandError) -> &'static str {
+    if let Some(e) = e.downcast_ref::<ActivityUpdateError>() {
+        match e {
+            ActivityUpdateError::Serenity(_) => "discord",
+            ActivityUpdateError::Activity(_) => "activity",
+            _ => "activity_update",
+        }
+    } else if e.is::<ActivityError>() {
+        "activity"
+    } else if e.is::<EmbedError>() {
+        "embed"
+    } else if e.is::<SettingsError>() {
+        "settings"
+    } else if e.is::<serenity::Error>() {
+        "discord"
+    } else {
+        "other"
+    }
+}
+
+/// Serves the metrics on `address` until the process exits.
+pub async fn serve(ctx: Context, address: SocketAddr) {
+    let make_service = make_service_fn(move |_| {
+        let ctx = ctx.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let ctx = ctx.clone();
+
+                async move { Ok::<_, Infallible>(handle(&ctx, req).await) }
+            }))
+        }
+    });
+
+    let server = match Server::try_bind(&address) {
+        Ok(builder) => builder.serve(make_service),
+        Err(e) => {
+            error!(%address, error = %e, "Unable to bind the metrics server");
+            return;
+        }
+    };
+
+    info!(%address, "Serving metrics");
+
+    if let Err(e) = server.await {
+        error!(error = %e, "Metrics server error");
+    }
+}
+
+async fn handle(ctx: &Context, req: Request<Body>) -> Response<Body> {
+    if req.method() != Method::GET || req.uri().path() != "/metrics" {
+        return status(StatusCode::NOT_FOUND);
+    }
+
+    update_guild_gauges(ctx).await;
+
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+
+    if let Err(e) = encoder.encode(&METRICS.registry.gather(), &mut buffer) {
+        error!(error = %e, "Failed to encode metrics");
+        return status(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, encoder.format_type())
+        .body(Body::from(buffer))
+        .expect("valid response")
+}
+
+async fn update_guild_gauges(ctx: &Context) {
+    // Guilds that no longer have data shouldn't keep reporting their old values.
+    METRICS.active_activities.reset();
+    METRICS.markov_chains.reset();
+    METRICS.markov_messages.reset();
+
+    let type_map = ctx.data.read().await;
+
+    let guild_map = match type_map.get::<data_keys::GetGuildData>() {
+        Some(guild_map) => guild_map,
+        None => return,
+    };
+
+    for (guild_id, guild_data) in guild_map.iter() {
+        let guild_id = guild_id.to_string();
+
+        METRICS
+            .active_activities
+            .with_label_values(&[&guild_id])
+            .set(guild_data.activities().len() as i64);
+
+        METRICS
+            .markov_chains
+            .with_label_values(&[&guild_id])
+            .set(guild_data.markov().len() as i64);
+
+        METRICS
+            .markov_messages
+            .with_label_values(&[&guild_id])
+            .set(guild_data.messages().values().map(Vec::len).sum::<usize>() as i64);
+    }
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .expect("valid response")
+}
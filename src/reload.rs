@@ -0,0 +1,475 @@
+//! Reloads config files while the bot is running.
+//!
+//! `assets.json`, `embeds.json`, the changelog and the bot config file are watched for changes.
+//! Changed files are parsed and checked before anything is replaced, so a file with a mistake in
+//! it leaves the old values in place. Everything that loaded is then swapped in at once, and the
+//! results are sent to the owners.
+//!
+//! Only `prefix`, `allow_dm` and `owner_ids` are reloaded from the bot config. Other settings,
+//! like the token or the API address, need a restart.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context as _;
+use notify::{Event, RecursiveMode, Watcher};
+use serde_json::{self as json, Value as JsonValue};
+use serenity::model::id::UserId;
+use serenity::prelude::Context;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::client::{self, JsonPaths};
+use crate::command::data::{EmbedWithMeta, RosterKind};
+use crate::command::imp::data_keys;
+use crate::command::report;
+use crate::config::BotConfig;
+
+/// How long to wait for more changes after a file changes. Editors often save a file in several
+/// steps, and it should only be reloaded once they're done.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Embeds that commands look up by name.
+const REQUIRED_EMBEDS: &[&str] = &[
+    "activity_roster_start",
+    "activity_roster_time",
+    "activity_roster_description",
+];
+
+/// The settings from the bot config that can change while the bot is running.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    pub prefix: String,
+    pub allow_dm: bool,
+    pub owners: HashSet<UserId>,
+}
+
+impl RuntimeConfig {
+    pub fn from_config(config: &BotConfig) -> Self {
+        Self {
+            prefix: config.prefix.clone(),
+            allow_dm: config.allow_dm,
+            owners: config
+                .owner_ids
+                .iter()
+                .flatten()
+                .map(|&id| UserId(id))
+                .collect(),
+        }
+    }
+}
+
+/// A file that can be reloaded.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Source {
+    Assets,
+    Embeds,
+    Changelog,
+    Config,
+}
+
+impl Source {
+    pub const JSON: &'static [Source] = &[Source::Assets, Source::Embeds, Source::Changelog];
+    pub const ALL: &'static [Source] = &[
+        Source::Assets,
+        Source::Embeds,
+        Source::Changelog,
+        Source::Config,
+    ];
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Source::Assets => "assets file",
+            Source::Embeds => "embeds file",
+            Source::Changelog => "changelog",
+            Source::Config => "bot config",
+        })
+    }
+}
+
+/// How reloading a file went. Errors are kept as strings so that they can be sent to the owners.
+pub type ReloadResult = (Source, Result<(), String>);
+
+/// Reloads `sources`, replacing the values of each one that loads without errors.
+pub async fn reload(ctx: &Context, sources: &[Source]) -> Vec<ReloadResult> {
+    let (paths, config_source) = {
+        let type_map = ctx.data.read().await;
+
+        (
+            type_map.get::<data_keys::GetJsonPaths>().cloned(),
+            type_map.get::<data_keys::GetConfigSource>().cloned(),
+        )
+    };
+
+    let paths = match paths {
+        Some(paths) => paths,
+        None => {
+            return sources
+                .iter()
+                .map(|&source| (source, Err(String::from("Paths are not initialized yet"))))
+                .collect()
+        }
+    };
+
+    let mut emoji_map = None;
+    let mut embed_map = None;
+    let mut runtime_config = None;
+    let mut results = Vec::with_capacity(sources.len());
+
+    // Files are loaded before taking the lock, so that commands aren't blocked on disk reads.
+    for &source in sources {
+        let result = match source {
+            Source::Assets => client::load_emoji_map(&paths.assets).map(|map| {
+                emoji_map = Some(map);
+            }),
+            Source::Embeds => client::load_embed_map(&paths.embeds)
+                .and_then(|map| check_embed_map(&map).map(|()| map))
+                .map(|map| {
+                    embed_map = Some(map);
+                }),
+            // The changelog isn't kept in memory, so it's only checked.
+            Source::Changelog => check_changelog(&paths.changelog),
+            Source::Config => match &config_source {
+                Some(config_source) => config_source
+                    .load()
+                    .map(|config| {
+                        runtime_config = Some(RuntimeConfig::from_config(&config));
+                    })
+                    .map_err(anyhow::Error::from),
+                None => Err(anyhow::anyhow!("The bot was started without a config file")),
+            },
+        };
+
+        results.push((source, result.map_err(|e| format!("{:#}", e))));
+    }
+
+    // Everything is swapped under one lock, so commands never see half of a reload.
+    let mut type_map = ctx.data.write().await;
+
+    if let Some(map) = emoji_map {
+        type_map.insert::<data_keys::GetEmojiMap>(map);
+    }
+
+    if let Some(map) = embed_map {
+        type_map.insert::<data_keys::GetEmbedMap>(map);
+    }
+
+    if let Some(config) = runtime_config {
+        type_map.insert::<data_keys::GetRuntimeConfig>(config);
+    }
+
+    results
+}
+
+/// Checks that the embeds that activity creation starts from, and every embed a selection leads
+/// to, exist. Otherwise `activity create` would fail partway through for everyone.
+fn check_embed_map(embed_map: &HashMap<String, EmbedWithMeta>) -> anyhow::Result<()> {
+    let targets = embed_map
+        .values()
+        .filter_map(|embed| embed.meta.as_ref())
+        .flatten()
+        .filter_map(|selection| match &selection.kind {
+            RosterKind::SelectNext(target) => Some(target.as_str()),
+            RosterKind::Finished { .. } => None,
+        });
+
+    let missing: BTreeSet<_> = REQUIRED_EMBEDS
+        .iter()
+        .copied()
+        .chain(targets)
+        .filter(|name| !embed_map.contains_key(*name))
+        .collect();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Embeds file is missing {}",
+            missing
+                .iter()
+                .map(|name| format!("`{}`", name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+}
+
+fn check_changelog(path: &Path) -> anyhow::Result<()> {
+    let file = File::open(path)
+        .with_context(|| format!("Unable to read the changelog {}", path.display()))?;
+
+    json::from_reader::<_, JsonValue>(BufReader::new(file))
+        .context("Changelog is not valid JSON")?;
+
+    Ok(())
+}
+
+/// Describes the results of a reload, one line per file.
+pub fn summary(results: &[ReloadResult]) -> String {
+    results
+        .iter()
+        .map(|(source, result)| match result {
+            Ok(()) => format!("Reloaded the {}.", source),
+            Err(e) => format!(
+                "Could not reload the {}, so it was left as it was: {}",
+                source, e
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Logs the results of a reload.
+pub fn log(results: &[ReloadResult]) {
+    for (source, result) in results {
+        match result {
+            Ok(()) => info!(source = %source, "Reloaded"),
+            Err(e) => warn!(source = %source, error = %e, "Reload failed"),
+        }
+    }
+}
+
+/// Logs the results of a reload and sends them to the owners.
+pub async fn report(ctx: &Context, results: &[ReloadResult]) {
+    log(results);
+    report::send_to_owners(ctx, &summary(results)).await;
+}
+
+/// Watches the config files, reloading them when they change. This runs until the process
+/// exits.
+pub async fn watch(ctx: Context) {
+    let watched = watched_files(&ctx).await;
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+
+    let mut watcher = match notify::recommended_watcher(move |event| {
+        // This only fails once the receiver is gone, which means nothing is watching anymore.
+        let _ = sender.send(event);
+    }) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            error!(error = %e, "Unable to watch the config files");
+            return;
+        }
+    };
+
+    // Directories are watched instead of the files themselves, because editors often replace a
+    // file when saving it, which would stop a watch on the old file.
+    let directories = watched
+        .keys()
+        .filter_map(|path| path.parent())
+        .collect::<HashSet<_>>();
+
+    for directory in directories {
+        if let Err(e) = watcher.watch(directory, RecursiveMode::NonRecursive) {
+            warn!(directory = %directory.display(), error = %e, "Unable to watch a directory");
+        }
+    }
+
+    info!(files = watched.len(), "Watching config files for changes");
+
+    while let Some(event) = receiver.recv().await {
+        let mut changed = BTreeSet::new();
+        changed.extend(changed_sources(&watched, event));
+
+        tokio::time::sleep(DEBOUNCE).await;
+
+        while let Ok(event) = receiver.try_recv() {
+            changed.extend(changed_sources(&watched, event));
+        }
+
+        if changed.is_empty() {
+            continue;
+        }
+
+        let sources = changed.into_iter().collect::<Vec<_>>();
+        let results = reload(&ctx, &sources).await;
+        report(&ctx, &results).await;
+    }
+}
+
+/// The absolute path of every watched file.
+async fn watched_files(ctx: &Context) -> HashMap<PathBuf, Source> {
+    let type_map = ctx.data.read().await;
+    let mut files = Vec::new();
+
+    if let Some(JsonPaths {
+        changelog,
+        assets,
+        embeds,
+    }) = type_map.get::<data_keys::GetJsonPaths>()
+    {
+        files.push((assets.clone(), Source::Assets));
+        files.push((embeds.clone(), Source::Embeds));
+        files.push((changelog.clone(), Source::Changelog));
+    }
+
+    if let Some(config_source) = type_map.get::<data_keys::GetConfigSource>() {
+        files.push((config_source.path.clone(), Source::Config));
+    }
+
+    files
+        .into_iter()
+        .filter_map(|(path, source)| Some((absolute(&path)?, source)))
+        .collect()
+}
+
+/// Resolves the directory of `path`, so that it can be compared to the paths in watch events.
+fn absolute(path: &Path) -> Option<PathBuf> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    match parent.canonicalize() {
+        Ok(parent) => Some(parent.join(path.file_name()?)),
+        Err(e) => {
+            warn!(path = %path.display(), error = %e, "Unable to watch a config file");
+            None
+        }
+    }
+}
+
+fn changed_sources(
+    watched: &HashMap<PathBuf, Source>,
+    event: notify::Result<Event>,
+) -> Vec<Source> {
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            warn!(error = %e, "Error while watching the config files");
+            return Vec::new();
+        }
+    };
+
+    // Removing a file isn't a change that can be loaded. If it's being replaced, the new file
+    // shows up as a separate event.
+    if !(event.kind.is_create() || event.kind.is_modify()) {
+        return Vec::new();
+    }
+
+    event
+        .paths
+        .iter()
+        .filter_map(|path| watched.get(path).copied())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, EventKind, ModifyKind, RemoveKind};
+
+    fn event(kind: EventKind, path: &str) -> notify::Result<Event> {
+        Ok(Event::new(kind).add_path(PathBuf::from(path)))
+    }
+
+    #[test]
+    fn only_changes_to_watched_files_are_reloaded() {
+        let watched = vec![
+            (PathBuf::from("/bot/config/embeds.json"), Source::Embeds),
+            (PathBuf::from("/bot/config/config.toml"), Source::Config),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            changed_sources(
+                &watched,
+                event(
+                    EventKind::Modify(ModifyKind::Any),
+                    "/bot/config/embeds.json"
+                )
+            ),
+            vec![Source::Embeds]
+        );
+        assert_eq!(
+            changed_sources(
+                &watched,
+                event(
+                    EventKind::Create(CreateKind::File),
+                    "/bot/config/config.toml"
+                )
+            ),
+            vec![Source::Config]
+        );
+        assert!(changed_sources(
+            &watched,
+            event(EventKind::Modify(ModifyKind::Any), "/bot/config/other.json")
+        )
+        .is_empty());
+        assert!(changed_sources(
+            &watched,
+            event(
+                EventKind::Remove(RemoveKind::File),
+                "/bot/config/embeds.json"
+            )
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn embeds_are_checked_for_missing_targets() {
+        let mut embed_map: HashMap<String, EmbedWithMeta> =
+            json::from_str(include_str!("../config/embeds.json")).unwrap();
+
+        assert!(check_embed_map(&embed_map).is_ok());
+
+        embed_map.remove("activity_roster_time");
+        let target = embed_map["activity_roster_start"].meta.as_ref().unwrap()[0]
+            .kind
+            .clone();
+
+        if let RosterKind::SelectNext(target) = target {
+            embed_map.remove(&target);
+        }
+
+        let error = check_embed_map(&embed_map).unwrap_err().to_string();
+
+        assert!(error.starts_with("Embeds file is missing "));
+        assert!(error.contains("`activity_roster_time`"));
+        assert_eq!(error.matches('`').count(), 4);
+    }
+
+    #[test]
+    fn runtime_config_only_takes_reloadable_settings() {
+        let config = BotConfig {
+            prefix: String::from("~"),
+            allow_dm: true,
+            owner_ids: Some(vec![1, 2]),
+            ..BotConfig::default()
+        };
+
+        assert_eq!(
+            RuntimeConfig::from_config(&config),
+            RuntimeConfig {
+                prefix: String::from("~"),
+                allow_dm: true,
+                owners: vec![UserId(1), UserId(2)].into_iter().collect(),
+            }
+        );
+    }
+
+    #[test]
+    fn summaries_explain_failed_reloads() {
+        let results = vec![
+            (Source::Embeds, Ok(())),
+            (
+                Source::Assets,
+                Err(String::from("Assets file is not a JSON object")),
+            ),
+        ];
+
+        assert_eq!(
+            summary(&results),
+            "Reloaded the embeds file.\n\
+             Could not reload the assets file, so it was left as it was: Assets file is not a JSON object"
+        );
+    }
+}
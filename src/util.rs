@@ -6,35 +6,26 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::task::{Context, Poll, Waker};
 
+/// Completes once the `ActivityToken` it was made from is cancelled.
 pub struct CancelActivity {
     shared: Arc<Shared>,
 }
 
-impl CancelActivity {
-    pub fn new_pair() -> (Self, ActivityToken) {
-        let shared = Arc::new(Shared {
-            cancelled: AtomicBool::new(false),
-            waker: AtomicCell::new(None),
-        });
-
-        let token = ActivityToken {
-            shared: Arc::clone(&shared),
-        };
-
-        let future = Self { shared };
-
-        (future, token)
-    }
-}
-
 impl Future for CancelActivity {
     type Output = ();
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.shared.cancelled.load(Ordering::Acquire) {
+            return Poll::Ready(());
+        }
+
+        self.shared.waker.store(Some(cx.waker().clone()));
+
+        // The token may have been cancelled between the first check and storing the waker, in
+        // which case nothing would be left to wake this future up.
         if self.shared.cancelled.load(Ordering::Acquire) {
             Poll::Ready(())
         } else {
-            self.shared.waker.store(Some(cx.waker().clone()));
             Poll::Pending
         }
     }
@@ -46,15 +37,36 @@ pub struct ActivityToken {
 }
 
 impl ActivityToken {
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                cancelled: AtomicBool::new(false),
+                waker: AtomicCell::new(None),
+            }),
+        }
+    }
+
+    /// Returns a future that completes when this token is cancelled. Only one of these futures
+    /// should be waited on at a time.
+    pub fn cancelled(&self) -> CancelActivity {
+        CancelActivity {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Cancels the activity. Returns false if it had already been cancelled.
     pub fn cancel(&self) -> bool {
-        match self.shared.waker.take() {
-            Some(waker) => {
-                self.shared.cancelled.store(true, Ordering::Release);
-                waker.wake();
-                true
-            }
-            None => false,
+        let already_cancelled = self.shared.cancelled.swap(true, Ordering::AcqRel);
+
+        if let Some(waker) = self.shared.waker.take() {
+            waker.wake();
         }
+
+        !already_cancelled
     }
 }
 
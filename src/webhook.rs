@@ -0,0 +1,398 @@
+//! Outgoing webhooks for activity lifecycle events.
+//!
+//! Every payload is POSTed as JSON to the configured URL. When a secret is set, the body is signed
+//! with HMAC-SHA256 and the hex digest is sent in the `X-Bingus-Signature` header as
+//! `sha256=<digest>`. Failed deliveries are retried with exponential backoff, and payloads that
+//! still can't be delivered are appended to the dead-letter file as JSON lines.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json as json;
+use serenity::model::id::{GuildId, UserId};
+use sha2::Sha256;
+use tokio::io::AsyncWriteExt;
+use tracing::{error, warn};
+
+use crate::api::ActivityView;
+use crate::command::data::Activity;
+
+/// How many times a payload is sent before it's given up on.
+const MAX_ATTEMPTS: u32 = 5;
+/// The delay before the first retry. It doubles after each failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub secret: Option<String>,
+    pub dead_letter_file: PathBuf,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "activity_created")]
+    Created,
+    #[serde(rename = "activity_edited")]
+    Edited,
+    #[serde(rename = "activity_joined")]
+    Joined,
+    #[serde(rename = "activity_left")]
+    Left,
+    #[serde(rename = "activity_started")]
+    Started,
+    #[serde(rename = "activity_deleted")]
+    Deleted,
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    event: WebhookEvent,
+    sent_at: String,
+    guild_id: String,
+    /// The user who joined or left, for `activity_joined` and `activity_left`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_id: Option<String>,
+    /// Whether the user joined as an alternate, for `activity_joined`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alternate: Option<bool>,
+    activity: &'a ActivityView,
+}
+
+#[derive(Serialize)]
+struct DeadLetter<'a> {
+    failed_at: String,
+    url: &'a str,
+    attempts: u32,
+    error: &'a str,
+    payload: json::Value,
+}
+
+/// Sends webhook payloads in the background. Cloning this is cheap.
+#[derive(Clone)]
+pub struct Webhooks {
+    client: reqwest::Client,
+    config: Arc<WebhookConfig>,
+}
+
+impl Webhooks {
+    pub fn new(config: WebhookConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config: Arc::new(config),
+        }
+    }
+
+    /// Queues a payload for an event. This never waits on the request, so it can be called
+    /// while holding locks.
+    pub fn notify(&self, event: WebhookEvent, guild_id: GuildId, activity: &Activity) {
+        self.send(event, guild_id, activity, None, None);
+    }
+
+    /// Queues an `activity_joined` or `activity_left` payload for a user.
+    pub fn notify_member(
+        &self,
+        event: WebhookEvent,
+        guild_id: GuildId,
+        activity: &Activity,
+        user_id: UserId,
+        alternate: Option<bool>,
+    ) {
+        self.send(event, guild_id, activity, Some(user_id), alternate);
+    }
+
+    fn send(
+        &self,
+        event: WebhookEvent,
+        guild_id: GuildId,
+        activity: &Activity,
+        user_id: Option<UserId>,
+        alternate: Option<bool>,
+    ) {
+        let payload = Payload {
+            event,
+            sent_at: Utc::now().to_rfc3339(),
+            guild_id: guild_id.to_string(),
+            user_id: user_id.map(|id| id.to_string()),
+            alternate,
+            activity: &ActivityView::from(activity),
+        };
+
+        let body = match json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                error!(error = %e, "Unable to serialize webhook payload");
+                return;
+            }
+        };
+
+        let webhooks = self.clone();
+
+        tokio::spawn(async move { webhooks.deliver(event, body).await });
+    }
+
+    async fn deliver(&self, event: WebhookEvent, body: Vec<u8>) {
+        let event_name = json::to_value(event)
+            .ok()
+            .and_then(|value| value.as_str().map(String::from))
+            .unwrap_or_default();
+
+        let signature = self
+            .config
+            .secret
+            .as_ref()
+            .map(|secret| sign(secret, &body));
+
+        let mut backoff = INITIAL_BACKOFF;
+        let mut last_error = String::new();
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            let mut request = self
+                .client
+                .post(&self.config.url)
+                .timeout(REQUEST_TIMEOUT)
+                .header("Content-Type", "application/json")
+                .header("User-Agent", "bingus-bot")
+                .header("X-Bingus-Event", &event_name)
+                .body(body.clone());
+
+            if let Some(signature) = &signature {
+                request = request.header("X-Bingus-Signature", format!("sha256={}", signature));
+            }
+
+            match request.send().await {
+                Ok(response) if response.status().is_success() => return,
+                Ok(response) => {
+                    let status = response.status();
+                    last_error = format!("HTTP {}", status);
+
+                    // Other client errors mean the receiver rejected the payload itself, so
+                    // sending it again won't help.
+                    if status.is_client_error()
+                        && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+                        && status != reqwest::StatusCode::REQUEST_TIMEOUT
+                    {
+                        self.dead_letter(attempt, &last_error, &body).await;
+                        return;
+                    }
+                }
+                Err(e) => last_error = e.to_string(),
+            }
+
+            if attempt < MAX_ATTEMPTS {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+
+        self.dead_letter(MAX_ATTEMPTS, &last_error, &body).await;
+    }
+
+    async fn dead_letter(&self, attempts: u32, error: &str, body: &[u8]) {
+        warn!(
+            url = %self.config.url,
+            attempts,
+            error,
+            "Giving up on webhook delivery"
+        );
+
+        let letter = DeadLetter {
+            failed_at: Utc::now().to_rfc3339(),
+            url: &self.config.url,
+            attempts,
+            error,
+            payload: json::from_slice(body).unwrap_or(json::Value::Null),
+        };
+
+        let mut line = match json::to_vec(&letter) {
+            Ok(line) => line,
+            Err(e) => {
+                error!(error = %e, "Unable to serialize dead letter");
+                return;
+            }
+        };
+        line.push(b'\n');
+
+        let result = async {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.config.dead_letter_file)
+                .await?;
+
+            file.write_all(&line).await?;
+            file.flush().await
+        }
+        .await;
+
+        if let Err(e) = result {
+            error!(
+                path = %self.config.dead_letter_file.to_string_lossy(),
+                error = %e,
+                "Unable to write to the webhook dead-letter file"
+            );
+        }
+    }
+}
+
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take a key of any size");
+    mac.update(body);
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::imp::parse_date_time;
+    use crate::command::testing;
+    use crate::util::ActivityToken;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Response, Server, StatusCode};
+    use serenity::model::id::ChannelId;
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn activity() -> Activity {
+        Activity::new(
+            "Vault of Glass",
+            "",
+            parse_date_time("12/25/2099 8:00 PM").unwrap(),
+            3,
+            6,
+            UserId(2),
+            testing::message(50, ChannelId(10), testing::BOT, ""),
+            ActivityToken::new(),
+        )
+    }
+
+    /// Starts a receiver that answers every request with `status`, counting the requests in
+    /// `hits`.
+    fn receiver(status: StatusCode, hits: Arc<AtomicUsize>) -> SocketAddr {
+        let make_service = make_service_fn(move |_| {
+            let hits = hits.clone();
+
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_| {
+                    hits.fetch_add(1, Ordering::SeqCst);
+
+                    async move {
+                        let mut response = Response::new(Body::empty());
+                        *response.status_mut() = status;
+                        Ok::<_, Infallible>(response)
+                    }
+                }))
+            }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let address = server.local_addr();
+        tokio::spawn(server);
+
+        address
+    }
+
+    #[test]
+    fn signatures_are_hmac_sha256_hex_digests() {
+        // Test case 2 from RFC 4231.
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn payloads_name_the_event_and_leave_out_unset_fields() {
+        let activity = activity();
+        let view = ActivityView::from(&activity);
+
+        let payload = json::to_value(Payload {
+            event: WebhookEvent::Created,
+            sent_at: String::new(),
+            guild_id: String::from("1"),
+            user_id: None,
+            alternate: None,
+            activity: &view,
+        })
+        .unwrap();
+
+        assert_eq!(payload["event"], "activity_created");
+        assert_eq!(payload["activity"]["id"], 3);
+        assert!(payload.get("user_id").is_none());
+        assert!(payload.get("alternate").is_none());
+
+        let payload = json::to_value(Payload {
+            event: WebhookEvent::Joined,
+            sent_at: String::new(),
+            guild_id: String::from("1"),
+            user_id: Some(String::from("2")),
+            alternate: Some(true),
+            activity: &view,
+        })
+        .unwrap();
+
+        assert_eq!(payload["event"], "activity_joined");
+        assert_eq!(payload["user_id"], "2");
+        assert_eq!(payload["alternate"], true);
+
+        let names = [
+            WebhookEvent::Edited,
+            WebhookEvent::Left,
+            WebhookEvent::Started,
+            WebhookEvent::Deleted,
+        ]
+        .iter()
+        .map(|&event| json::to_value(event).unwrap())
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            vec![
+                "activity_edited",
+                "activity_left",
+                "activity_started",
+                "activity_deleted"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_payloads_are_dead_lettered_without_retrying() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let address = receiver(StatusCode::BAD_REQUEST, hits.clone());
+        let dir = tempfile::tempdir().unwrap();
+        let dead_letter_file = dir.path().join("dead_letters.jsonl");
+
+        let webhooks = Webhooks::new(WebhookConfig {
+            url: format!("http://{}/", address),
+            secret: None,
+            dead_letter_file: dead_letter_file.clone(),
+        });
+
+        webhooks
+            .deliver(
+                WebhookEvent::Created,
+                br#"{"event":"activity_created"}"#.to_vec(),
+            )
+            .await;
+
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        let contents = std::fs::read_to_string(&dead_letter_file).unwrap();
+        let letters = contents.lines().collect::<Vec<_>>();
+        assert_eq!(letters.len(), 1);
+
+        let letter = json::from_str::<json::Value>(letters[0]).unwrap();
+        assert_eq!(letter["attempts"], 1);
+        assert_eq!(letter["error"], "HTTP 400 Bad Request");
+        assert_eq!(letter["payload"]["event"], "activity_created");
+    }
+}